@@ -1,14 +1,38 @@
-use std::{time::Duration, usize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+    usize,
+};
 
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use rand::RngCore;
 use serde_json::{json, Value};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::{
     client::{ApiClient, ApiClientBuilder, ApiRequestBuilder, ApiResponse, API_ROUTE},
+    download::{file_md5, part_path},
+    page::{paginate, paginate_cursor, CursorPage, Page, Paginated},
+    types::{
+        Album, AlbumSublistResp, Artist, ArtistSongsResp, ArtistSublistResp, Comment,
+        HotCommentsResp, LikeListResp, NosTokenResp, PlayRecord, Playlist, PlaylistDetail,
+        PlaylistDetailResp, PlaylistSubscribersResp, RelatedPlaylist, ResourceCommentsResp, Song,
+        SongDetailResp, TopPlaylistResp, UploadImageResp, UserAccountResp, UserPlaylistResp,
+        UserProfile, UserRecordResp,
+    },
     TResult,
 };
 
+/// The max number of ids in one song detail request.
+const SONG_DETAIL_CHUNK_SIZE: usize = 500;
+
+/// The max number of song detail requests running at the same time.
+const SONG_DETAIL_CONCURRENCY: usize = 4;
+
+/// Where the images are uploaded to.
+const NOS_UPLOAD_URL: &str = "https://nosup-hz1.127.net/yyimgs";
+
 /// API wrapper.
 pub struct NcmApi {
     client: ApiClient,
@@ -81,35 +105,84 @@ impl NcmApi {
         self._search(key, "cloudsearch", opt).await
     }
 
-    // /// 说明 : 调用此接口,可收藏/取消收藏专辑
-    // /// required
-    // /// id : 专辑 id
-    // /// t : 1 为收藏,其他为取消收藏
-    // pub async fn album_sub(&self, id: usize, op: u8) -> TResult<ApiResponse> {
-    //     let op = if op == 1 { "sub" } else { "unsub" };
-    //     let u = replace_all_route_params(API_ROUTE["album_sub"], op);
-    //     let r = ApiRequestBuilder::post(&u)
-    //         .set_data(json!({
-    //             "id": id,
-    //         }))
-    //         .build();
+    /// 分页搜索, R 为对应搜索类型的响应, 如 search_type 为 SearchType::Song 时使用 SearchSongResp
+    pub async fn cloud_search_page<R: Paginated>(
+        &self,
+        key: &str,
+        search_type: SearchType,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<R::Item>> {
+        let resp = self
+            .cloud_search(
+                key,
+                Some(json!({
+                    "type": search_type,
+                    "offset": offset,
+                    "limit": limit,
+                })),
+            )
+            .await?;
 
-    //     self.client.request(r).await
-    // }
+        Ok(resp.deserialize::<R>()?.into_page(offset, limit))
+    }
 
-    // /// 说明 : 调用此接口 , 可获得已收藏专辑列表
-    // /// optional
-    // /// limit: 取出数量 , 默认为 25
-    // /// offset: 偏移数量 , 用于分页 , 如 :( 页数 -1)*25, 其中 25 为 limit 的值 , 默认 为 0
-    // pub async fn album_sublist(&self, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["album_sublist"])
-    //         .set_data(limit_offset(25, 0))
-    //         .insert("total", Value::Bool(true))
-    //         .merge(opt.unwrap_or_default())
-    //         .build();
+    /// 依次获取所有搜索结果
+    pub fn cloud_search_pages<'a, R: Paginated + 'a>(
+        &'a self,
+        key: &'a str,
+        search_type: SearchType,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<R::Item>>> + 'a {
+        paginate(limit, move |offset, limit| {
+            self.cloud_search_page::<R>(key, search_type, offset, limit)
+        })
+    }
 
-    //     self.client.request(r).await
-    // }
+    /// 说明 : 调用此接口,可收藏/取消收藏专辑
+    /// required
+    /// id : 专辑 id
+    /// t : 1 为收藏,其他为取消收藏
+    pub async fn album_sub(&self, id: usize, op: u8) -> TResult<ApiResponse> {
+        let op = if op == 1 { "sub" } else { "unsub" };
+        let u = replace_all_route_params(API_ROUTE["album_sub"], op);
+        let r = ApiRequestBuilder::post(&u)
+            .set_data(json!({
+                "id": id,
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 可获得已收藏专辑列表
+    /// optional
+    /// limit: 取出数量 , 默认为 25
+    /// offset: 偏移数量 , 用于分页 , 如 :( 页数 -1)*25, 其中 25 为 limit 的值 , 默认 为 0
+    pub async fn album_sublist(&self, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["album_sublist"])
+            .set_data(limit_offset(25, 0))
+            .insert("total", Value::Bool(true))
+            .merge(opt.unwrap_or_default())
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 依次获取所有已收藏的专辑
+    pub fn album_sublist_pages(
+        &self,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Album>>> + '_ {
+        paginate(limit, move |offset, limit| async move {
+            let resp = self
+                .album_sublist(Some(limit_offset(limit, offset)))
+                .await?;
+            Ok(resp
+                .deserialize::<AlbumSublistResp>()?
+                .into_page(offset, limit))
+        })
+    }
 
     // /// 说明 : 调用此接口 , 传入专辑 id, 可获得专辑内容
     // /// required
@@ -121,61 +194,108 @@ impl NcmApi {
     //     self.client.request(r).await
     // }
 
-    // /// 说明 : 调用此接口,可获取歌手全部歌曲 必选参数 :
-    // /// required
-    // /// id : 歌手 id
-    // /// optional:
-    // /// order : hot ,time 按照热门或者时间排序
-    // /// limit: 取出歌单数量 , 默认为 50
-    // /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*50, 其中 50 为 limit 的值
-    // pub async fn artist_songs(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["artist_songs"])
-    //         .set_data(json!({
-    //             "id": id,
-    //             "private_cloud": true,
-    //             "work_type":     1,
-    //             "order":         "hot",
-    //             "offset":        0,
-    //             "limit":         100,
-    //         }))
-    //         .merge(opt.unwrap_or_default())
-    //         .add_cookie("os", "pc")
-    //         .build();
+    /// 说明 : 调用此接口,可获取歌手全部歌曲 必选参数 :
+    /// required
+    /// id : 歌手 id
+    /// optional:
+    /// order : hot ,time 按照热门或者时间排序
+    /// limit: 取出歌单数量 , 默认为 50
+    /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*50, 其中 50 为 limit 的值
+    pub async fn artist_songs(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["artist_songs"])
+            .set_data(json!({
+                "id": id,
+                "private_cloud": true,
+                "work_type":     1,
+                "order":         "hot",
+                "offset":        0,
+                "limit":         100,
+            }))
+            .merge(opt.unwrap_or_default())
+            .add_cookie("os", "pc")
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口,可收藏歌手
-    // /// required
-    // /// id : 歌手 id
-    // /// t:操作,1 为收藏,其他为取消收藏
-    // pub async fn artist_sub(&self, id: usize, sub: u8) -> TResult<ApiResponse> {
-    //     let mut opt = "sub";
-    //     if sub != 1 {
-    //         opt = "unsub";
-    //     }
-
-    //     let u = replace_all_route_params(API_ROUTE["artist_sub"], opt);
-    //     let r = ApiRequestBuilder::post(&u)
-    //         .set_data(json!({
-    //             "artistId": id,
-    //             "artistIds": [id]
-    //         }))
-    //         .build();
+    /// 分页获取歌手歌曲, order 为 hot 或 time
+    pub async fn artist_songs_page(
+        &self,
+        id: usize,
+        order: &str,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Song>> {
+        let resp = self
+            .artist_songs(
+                id,
+                Some(json!({"order": order, "offset": offset, "limit": limit})),
+            )
+            .await?;
 
-    //     self.client.request(r).await
-    // }
+        Ok(resp
+            .deserialize::<ArtistSongsResp>()?
+            .into_page(offset, limit))
+    }
 
-    // /// 说明 : 调用此接口,可获取收藏的歌手列表
-    // pub async fn artist_sublist(&self, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["artist_sublist"])
-    //         .set_data(limit_offset(25, 0))
-    //         .merge(opt.unwrap_or_default())
-    //         .insert("total", Value::Bool(true))
-    //         .build();
+    /// 依次获取歌手的所有歌曲
+    pub fn artist_songs_pages<'a>(
+        &'a self,
+        id: usize,
+        order: &'a str,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Song>>> + 'a {
+        paginate(limit, move |offset, limit| {
+            self.artist_songs_page(id, order, offset, limit)
+        })
+    }
 
-    //     self.client.request(r).await
-    // }
+    /// 说明 : 调用此接口,可收藏歌手
+    /// required
+    /// id : 歌手 id
+    /// t:操作,1 为收藏,其他为取消收藏
+    pub async fn artist_sub(&self, id: usize, sub: u8) -> TResult<ApiResponse> {
+        let mut opt = "sub";
+        if sub != 1 {
+            opt = "unsub";
+        }
+
+        let u = replace_all_route_params(API_ROUTE["artist_sub"], opt);
+        let r = ApiRequestBuilder::post(&u)
+            .set_data(json!({
+                "artistId": id,
+                "artistIds": [id]
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口,可获取收藏的歌手列表
+    pub async fn artist_sublist(&self, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["artist_sublist"])
+            .set_data(limit_offset(25, 0))
+            .merge(opt.unwrap_or_default())
+            .insert("total", Value::Bool(true))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 依次获取所有已收藏的歌手
+    pub fn artist_sublist_pages(
+        &self,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Artist>>> + '_ {
+        paginate(limit, move |offset, limit| async move {
+            let resp = self
+                .artist_sublist(Some(limit_offset(limit, offset)))
+                .await?;
+            Ok(resp
+                .deserialize::<ArtistSublistResp>()?
+                .into_page(offset, limit))
+        })
+    }
 
     // /// 说明 : 调用此接口,可获取歌手热门50首歌曲
     // /// required
@@ -188,51 +308,121 @@ impl NcmApi {
     //     self.client.request(r).await
     // }
 
-    // /// 说明: 调用此接口,传入歌曲 id, 可获取音乐是否可用,返回 { success: true, message: 'ok' } 或者 { success: false, message: '亲爱的,暂无版权' }
-    // /// requried
-    // /// 必选参数 : id : 歌曲 id
-    // /// optional
-    // /// 可选参数 : br: 码率,默认设置了 999000 即最大码率,如果要 320k 则可设置为 320000,其他类推
-    // pub async fn check_music(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["check_music"])
-    //         .set_data(json!({"br": 999000}))
-    //         .merge(opt.unwrap_or_default())
-    //         .merge(json!({ "ids": [id] }))
-    //         .build();
+    /// 说明: 调用此接口,传入歌曲 id, 可获取音乐是否可用, 返回的 data 中每首歌的 code 为 200 时可用
+    /// requried
+    /// 必选参数 : ids : 歌曲 id, 可多个
+    /// br: 码率, 999000 即最大码率,如果要 320k 则可设置为 320000,其他类推
+    pub async fn check_music(&self, ids: &[usize], br: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["check_music"])
+            .set_data(json!({"ids": ids, "br": br}))
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入 type, 资源 id 可获得对应资源热门评论 ( 不需要登录 )
-    // /// required
-    // /// id : 资源 id
-    // /// type: 数字 , 资源类型
-    // ///
-    // /// optional
-    // /// 可选参数 : limit: 取出评论数量 , 默认为 20
-    // /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*20, 其中 20 为 limit 的值
-    // /// before: 分页参数,取上一页最后一项的 time 获取下一页数据(获取超过5000条评论的时候需要用到)
-    // pub async fn comment_hot(
-    //     &self,
-    //     id: usize,
-    //     resouce_type: ResourceType,
-    //     opt: Option<Value>,
-    // ) -> TResult<ApiResponse> {
-    //     let u = replace_all_route_params(API_ROUTE["comment_hot"], "");
-    //     let u = format!("{}{}{}", u, map_resource_code(resouce_type), id);
+    /// 说明 : 调用此接口 , 传入 type, 资源 id 可获得对应资源热门评论 ( 不需要登录 )
+    /// required
+    /// id : 资源 id
+    /// type: 数字 , 资源类型
+    ///
+    /// optional
+    /// 可选参数 : limit: 取出评论数量 , 默认为 20
+    /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*20, 其中 20 为 limit 的值
+    /// before: 分页参数,取上一页最后一项的 time 获取下一页数据(获取超过5000条评论的时候需要用到)
+    pub async fn comment_hot(
+        &self,
+        id: usize,
+        resouce_type: ResourceType,
+        opt: Option<Value>,
+    ) -> TResult<ApiResponse> {
+        let u = replace_all_route_params(API_ROUTE["comment_hot"], "");
+        let u = format!("{}{}{}", u, map_resource_code(resouce_type), id);
 
-    //     let r = ApiRequestBuilder::post(&u)
-    //         .add_cookie("os", "pc")
-    //         .set_data(limit_offset(20, 0))
-    //         .merge(opt.unwrap_or_default())
-    //         .merge(json!({
-    //             "beforeTime": 0,
-    //             "rid": id
-    //         }))
-    //         .build();
+        let r = ApiRequestBuilder::post(&u)
+            .add_cookie("os", "pc")
+            .set_data(limit_offset(20, 0))
+            .merge(opt.unwrap_or_default())
+            .merge(json!({
+                "beforeTime": 0,
+                "rid": id
+            }))
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
+
+    /// 分页获取热门评论
+    pub async fn comment_hot_page(
+        &self,
+        id: usize,
+        resource_type: ResourceType,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Comment>> {
+        let resp = self
+            .comment_hot(id, resource_type, Some(limit_offset(limit, offset)))
+            .await?;
+
+        Ok(resp
+            .deserialize::<HotCommentsResp>()?
+            .into_page(offset, limit))
+    }
+
+    /// 说明 : 调用此接口 , 传入资源 parentCommentId 和资源类型 type和资源id参数, 可获得该资源的歌曲楼层评论
+    ///
+    /// required
+    /// parent_comment_id: 楼层评论 id
+    /// id : 资源 id
+    /// type: 数字 , 资源类型
+    ///
+    /// optional
+    /// limit: 取出评论数量 , 默认为 20
+    /// time: 分页参数,取上一页最后一项的 time 获取下一页数据
+    pub async fn comment_floor(
+        &self,
+        parent_comment_id: usize,
+        id: usize,
+        resource_type: ResourceType,
+        opt: Option<Value>,
+    ) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["comment_floor"])
+            .set_data(json!({"limit": 20, "time": -1}))
+            .merge(opt.unwrap_or_default())
+            .merge(json!({
+                "parentCommentId": parent_comment_id,
+                "threadId": format!("{}{}", map_resource_code(resource_type), id),
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入 type, 资源 id, 和评论 id cid 和 是否点赞参数 t 即可给对应评论点赞 ( 需要登录 )
+    ///
+    /// required
+    /// id : 资源 id
+    /// type: 数字 , 资源类型
+    /// cid : 评论 id
+    /// like: true 为点赞, false 为取消点赞
+    pub async fn comment_like(
+        &self,
+        id: usize,
+        resource_type: ResourceType,
+        cid: usize,
+        like: bool,
+    ) -> TResult<ApiResponse> {
+        let t = if like { "like" } else { "unlike" };
+        let u = replace_all_route_params(API_ROUTE["comment_like"], t);
+        let r = ApiRequestBuilder::post(&u)
+            .add_cookie("os", "pc")
+            .set_data(json!({
+                "threadId": format!("{}{}", map_resource_code(resource_type), id),
+                "commentId": cid,
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
 
     /// 新版评论接口
     /// 说明 : 调用此接口 , 传入资源类型和资源id,以及排序方式,可获取对应资源的评论
@@ -281,6 +471,53 @@ impl NcmApi {
         self.client.request(r).await
     }
 
+    /// 按 offset 分页获取评论, offset 应为 limit 的整数倍.
+    /// 按时间排序 (sort_type 为 3) 时翻页需要上一页的 time 作为 cursor, 无法按 offset 分页,
+    /// 应使用 comment.
+    pub async fn comment_page(
+        &self,
+        id: usize,
+        resource_type: ResourceType,
+        sort_type: usize,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Comment>> {
+        if sort_type == 3 {
+            return Err("comments sorted by time can't be paginated by offset".into());
+        }
+        if limit == 0 {
+            return Err("the page limit should be positive".into());
+        }
+        let resp = self
+            .comment(
+                id,
+                resource_type,
+                limit,
+                offset / limit + 1,
+                sort_type,
+                0,
+                true,
+            )
+            .await?;
+
+        Ok(resp
+            .deserialize::<ResourceCommentsResp>()?
+            .into_page(offset, limit))
+    }
+
+    /// 依次获取所有评论, 不支持按时间排序 (sort_type 为 3).
+    pub fn comment_pages(
+        &self,
+        id: usize,
+        resource_type: ResourceType,
+        sort_type: usize,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Comment>>> + '_ {
+        paginate(limit, move |offset, limit| {
+            self.comment_page(id, resource_type, sort_type, offset, limit)
+        })
+    }
+
     /// required
     /// rid: resource id
     /// rt:  resource type
@@ -377,36 +614,41 @@ impl NcmApi {
     //     self.client.request(r).await
     // }
 
-    // /// 说明 : 调用此接口 , 传入音乐 id, 可喜欢该音乐
-    // ///
-    // /// required
-    // /// 必选参数 : id: 歌曲 id
-    // ///
-    // /// optional
-    // /// 可选参数 : like: 布尔值 , 默认为 true 即喜欢 , 若传 false, 则取消喜欢
-    // pub async fn like(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["like"])
-    //         .add_cookie("os", "pc")
-    //         .add_cookie("appver", "2.7.1.198277")
-    //         .set_real_ip("118.88.88.88")
-    //         .set_data(json!({"alg": "itembased", "time": 3, "like": true, "trackId": id}))
-    //         .merge(opt.unwrap_or_default())
-    //         .build();
+    /// 说明 : 调用此接口 , 传入音乐 id, 可喜欢该音乐
+    ///
+    /// required
+    /// 必选参数 : id: 歌曲 id
+    ///
+    /// optional
+    /// 可选参数 : like: 布尔值 , 默认为 true 即喜欢 , 若传 false, 则取消喜欢
+    pub async fn like(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["like"])
+            .add_cookie("os", "pc")
+            .add_cookie("appver", "2.7.1.198277")
+            .set_real_ip("118.88.88.88")
+            .set_data(json!({"alg": "itembased", "time": 3, "like": true, "trackId": id}))
+            .merge(opt.unwrap_or_default())
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入用户 id, 可获取已喜欢音乐id列表(id数组)
-    // ///
-    // /// required
-    // /// 必选参数 : uid: 用户 id
-    // pub async fn likelist(&self, uid: usize) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["likelist"])
-    //         .set_data(json!({ "uid": uid }))
-    //         .build();
+    /// 说明 : 调用此接口 , 传入用户 id, 可获取已喜欢音乐id列表(id数组)
+    ///
+    /// required
+    /// 必选参数 : uid: 用户 id
+    pub async fn likelist(&self, uid: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["likelist"])
+            .set_data(json!({ "uid": uid }))
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
+
+    /// 获取用户喜欢的所有歌曲的 id
+    pub async fn liked_song_ids(&self, uid: usize) -> TResult<Vec<usize>> {
+        Ok(self.likelist(uid).await?.deserialize::<LikeListResp>()?.ids)
+    }
 
     /// 必选参数 :
     /// phone: 手机号码
@@ -444,6 +686,15 @@ impl NcmApi {
         self.client.request(r).await
     }
 
+    /// 获取当前登录的用户, 未登录时返回 None
+    pub async fn current_user(&self) -> TResult<Option<UserProfile>> {
+        Ok(self
+            .login_status()
+            .await?
+            .deserialize::<UserAccountResp>()?
+            .profile)
+    }
+
     /// 说明 : 调用此接口 , 可退出登录
     pub async fn logout(&self) -> TResult<ApiResponse> {
         let r = ApiRequestBuilder::post(API_ROUTE["logout"]).build();
@@ -463,6 +714,33 @@ impl NcmApi {
                 "lv": -1,
                 "kv": -1,
                 "tv": -1,
+                "rv": -1,
+                "yv": -1,
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入音乐 id 可获得对应音乐的逐字歌词 yrc, 以及 lrc, 翻译和音译歌词
+    ///
+    /// required
+    /// 必选参数 : id: 音乐 id
+    pub async fn lyric_new(&self, id: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["lyric_new"])
+            .set_crypto(crate::crypto::Crypto::Eapi)
+            .add_cookie("os", "pc")
+            .set_api_url("/api/song/lyric/v1")
+            .set_data(json!({
+                "id": id,
+                "cp": false,
+                "lv": 0,
+                "kv": 0,
+                "tv": 0,
+                "rv": 0,
+                "yv": 0,
+                "ytv": 0,
+                "yrv": 0,
             }))
             .build();
 
@@ -511,57 +789,511 @@ impl NcmApi {
         let r = ApiRequestBuilder::post(API_ROUTE["playlist_tracks"])
             .add_cookie("os", "pc")
             .set_data(json!({"op": op, "pid": pid, "trackIds": tracks, "imme": true}))
+            .set_cache(false)
             .build();
 
         self.client.request(r).await
     }
 
-    // /// 说明 : 登录后调用此接口,可以更新用户歌单
-    // ///
-    // /// required
-    // /// id:歌单id
-    // /// name:歌单名字
-    // /// desc:歌单描述
-    // /// tags:歌单tag ,多个用 `;` 隔开,只能用官方规定标签
-    // pub async fn playlist_update(
-    //     &self,
-    //     pid: usize,
-    //     name: &str,
-    //     desc: &str,
-    //     tags: Vec<&str>,
-    // ) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["playlist_update"])
-    //         .add_cookie("os", "pc")
-    //         .set_data(json!({
-    //             "/api/playlist/update/name": {"id": pid, "name": name},
-    //             "/api/playlist/desc/update": {"id": pid, "desc": desc},
-    //             "/api/playlist/tags/update": {"id": pid, "tags": tags.join(";")},
-    //         }))
-    //         .build();
-
-    //     self.client.request(r).await
-    // }
-
-    /// 新建歌单
-    pub async fn create_playlist(&self, name: String, privacy: bool) -> TResult<ApiResponse> {
-        let privacy = if privacy {
-            10
-        } else {
-            0
-        };
-        let r = ApiRequestBuilder::post(API_ROUTE["playlist_create"])
+    /// 说明 : 登录后调用此接口,可以一次更新用户歌单的名字, 描述和标签
+    ///
+    /// required
+    /// id:歌单id
+    /// name:歌单名字
+    /// desc:歌单描述
+    /// tags:歌单tag ,多个用 `;` 隔开,只能用官方规定标签
+    pub async fn playlist_update(
+        &self,
+        pid: usize,
+        name: &str,
+        desc: &str,
+        tags: &[&str],
+    ) -> TResult<ApiResponse> {
+        // each api in the batch takes its own parameters as a json string
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_update"])
             .add_cookie("os", "pc")
-            .set_data(json!({"name": name, "privacy": privacy}))
+            .set_data(json!({
+                "/api/playlist/update/name": json!({"id": pid, "name": name}).to_string(),
+                "/api/playlist/desc/update": json!({"id": pid, "desc": desc}).to_string(),
+                "/api/playlist/tags/update": json!({"id": pid, "tags": tags.join(";")}).to_string(),
+            }))
             .build();
 
         self.client.request(r).await
     }
 
-    /// 删除歌单
-    pub async fn delete_playlist(&self, pid: usize) -> TResult<ApiResponse> {
-        let r = ApiRequestBuilder::post(API_ROUTE["playlist_delete"])
-            .add_cookie("os", "pc")
+    /// 说明 : 登录后调用此接口,可以单独更新用户歌单描述
+    ///
+    /// required
+    /// id: 歌单id
+    /// desc: 歌单描述
+    pub async fn playlist_desc_update(&self, pid: usize, desc: &str) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_desc_update"])
+            .set_crypto(crate::crypto::Crypto::Eapi)
+            .set_api_url("/api/playlist/desc/update")
+            .set_data(json!({"id": pid, "desc": desc}))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 登录后调用此接口,可以单独更新用户歌单标签
+    ///
+    /// required
+    /// id: 歌单id
+    /// tags: 歌单标签, 只能用官方规定标签, 可由 playlist_catlist 获取
+    pub async fn playlist_tags_update(&self, pid: usize, tags: &[&str]) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_tags_update"])
+            .set_crypto(crate::crypto::Crypto::Eapi)
+            .set_api_url("/api/playlist/tags/update")
+            .set_data(json!({"id": pid, "tags": tags.join(";")}))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 上传图片, 返回图片 id, 用于更新歌单封面等
+    ///
+    /// required
+    /// image: 图片内容, 支持 jpg 和 png
+    /// ext: 图片扩展名
+    /// size: 裁剪后图片的边长, 图片从左上角开始裁剪为正方形
+    pub async fn upload_image(&self, image: Vec<u8>, ext: &str, size: usize) -> TResult<String> {
+        let ext = ext.to_lowercase();
+        // the allocation request is cached by its parameters, a unique file name
+        // makes sure we get a new token for every upload
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let filename = format!("{}.{}", now, ext);
+        let r = ApiRequestBuilder::post(API_ROUTE["nos_token_alloc"])
+            .set_data(json!({
+                "bucket": "yyimgs",
+                "ext": ext,
+                "filename": filename,
+                "local": false,
+                "nos_product": 0,
+                "return_body": r#"{"code":200,"size":"$(ObjectSize)"}"#,
+                "type": "other",
+            }))
+            .build();
+        let token = self
+            .client
+            .request(r)
+            .await?
+            .deserialize::<NosTokenResp>()?
+            .result;
+
+        let content_type = if ext == "png" {
+            "image/png"
+        } else {
+            "image/jpeg"
+        };
+        let url = format!(
+            "{}/{}?offset=0&complete=true&version=1.0",
+            NOS_UPLOAD_URL, token.object_key
+        );
+        self.client
+            .upload(&url, &token.token, content_type, image)
+            .await?;
+
+        let r = ApiRequestBuilder::post(API_ROUTE["upload_img_op"])
+            .set_data(json!({
+                "imgid": token.doc_id,
+                "imgX": 0,
+                "imgY": 0,
+                "imgSize": size,
+                "imgWidth": size,
+                "imgHeight": size,
+            }))
+            .build();
+        let resp = self
+            .client
+            .request(r)
+            .await?
+            .deserialize::<UploadImageResp>()?;
+
+        if resp.code == 200 {
+            Ok(resp.id)
+        } else {
+            Err(format!("failed to upload image, code {}", resp.code).into())
+        }
+    }
+
+    /// 说明 : 登录后调用此接口,可以更新歌单封面
+    ///
+    /// required
+    /// id: 歌单id
+    /// image: 图片内容, 支持 jpg 和 png
+    /// ext: 图片扩展名
+    /// size: 裁剪后图片的边长
+    pub async fn playlist_cover_update(
+        &self,
+        pid: usize,
+        image: Vec<u8>,
+        ext: &str,
+        size: usize,
+    ) -> TResult<ApiResponse> {
+        let img_id = self.upload_image(image, ext, size).await?;
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_cover_update"])
+            .set_data(json!({"id": pid, "coverImgId": img_id}))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入歌单 id, 可以收藏或取消收藏歌单 ( 需要登录 )
+    ///
+    /// required
+    /// id : 歌单 id
+    /// subscribe: true 为收藏, false 为取消收藏
+    pub async fn playlist_subscribe(&self, pid: usize, subscribe: bool) -> TResult<ApiResponse> {
+        let t = if subscribe {
+            "subscribe"
+        } else {
+            "unsubscribe"
+        };
+        let u = replace_all_route_params(API_ROUTE["playlist_subscribe"], t);
+        let r = ApiRequestBuilder::post(&u)
+            .set_data(json!({ "id": pid }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入歌单 id 可获取歌单的所有收藏者
+    ///
+    /// required
+    /// id : 歌单 id
+    ///
+    /// optional
+    /// limit: 取出评论数量 , 默认为 20
+    /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*20, 其中 20 为 limit 的值
+    pub async fn playlist_subscribers(
+        &self,
+        pid: usize,
+        opt: Option<Value>,
+    ) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_subscribers"])
+            .set_data(limit_offset(20, 0))
+            .merge(opt.unwrap_or_default())
+            .insert("id", json!(pid))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 分页获取歌单收藏者
+    pub async fn playlist_subscribers_page(
+        &self,
+        pid: usize,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<UserProfile>> {
+        let resp = self
+            .playlist_subscribers(pid, Some(limit_offset(limit, offset)))
+            .await?;
+
+        Ok(resp
+            .deserialize::<PlaylistSubscribersResp>()?
+            .into_page(offset, limit))
+    }
+
+    /// 依次获取歌单的所有收藏者
+    pub fn playlist_subscribers_pages(
+        &self,
+        pid: usize,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<UserProfile>>> + '_ {
+        paginate(limit, move |offset, limit| {
+            self.playlist_subscribers_page(pid, offset, limit)
+        })
+    }
+
+    /// 说明 : 调用此接口,可获取歌单分类,包含 category 信息
+    pub async fn playlist_catlist(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_catlist"]).build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口,可获取歌单分类中的热门标签
+    pub async fn playlist_hot(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_hot"]).build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 可获取网友精选碟歌单
+    ///
+    /// optional
+    /// 可选参数 :
+    /// order: 可选值为 'new' 和 'hot', 分别对应最新和最热 , 默认为 'hot'
+    /// cat: tag, 比如 " 华语 "、" 古风 " 、" 欧美 "、" 流行 ", 默认为 "全部",可从歌单分类接口获取
+    /// limit: 取出歌单数量 , 默认为 50
+    /// offset: 偏移数量 , 用于分页 , 如 :( 评论页数 -1)*50, 其中 50 为 limit 的值
+    pub async fn top_playlist(&self, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["top_playlist"])
+            .set_data(json!({
+                "cat": "全部",
+                "order": "hot",
+                "limit": 50,
+                "offset": 0,
+                "total": true,
+            }))
+            .merge(opt.unwrap_or_default())
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 分页获取分类歌单, order 为 hot 或 new
+    pub async fn top_playlist_page(
+        &self,
+        cat: &str,
+        order: &str,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Playlist>> {
+        let resp = self
+            .top_playlist(Some(json!({
+                "cat": cat,
+                "order": order,
+                "offset": offset,
+                "limit": limit,
+            })))
+            .await?;
+
+        Ok(resp
+            .deserialize::<TopPlaylistResp>()?
+            .into_page(offset, limit))
+    }
+
+    /// 依次获取分类的所有歌单
+    pub fn top_playlist_pages<'a>(
+        &'a self,
+        cat: &'a str,
+        order: &'a str,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Playlist>>> + 'a {
+        paginate(limit, move |offset, limit| {
+            self.top_playlist_page(cat, order, offset, limit)
+        })
+    }
+
+    /// 说明 : 调用此接口 , 可获取精品歌单
+    ///
+    /// optional
+    /// 可选参数 :
+    /// cat: tag, 比如 " 华语 "、" 古风 " 、" 欧美 "、" 流行 ", 默认为 "全部",可从精品歌单标签列表接口获取
+    /// limit: 取出歌单数量 , 默认为 50
+    /// before: 分页参数,取上一页最后一个歌单的 updateTime 获取下一页数据
+    pub async fn top_playlist_highquality(&self, opt: Option<Value>) -> TResult<ApiResponse> {
+        let opt = opt.unwrap_or_default();
+        let lasttime = opt.get("before").cloned().unwrap_or(json!(0));
+        let r = ApiRequestBuilder::post(API_ROUTE["top_playlist_highquality"])
+            .set_data(json!({"cat": "全部", "limit": 50, "total": true}))
+            .merge(opt)
+            .insert("lasttime", lasttime)
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 获取 BEFORE 之前更新的一页精品歌单, BEFORE 为 None 时从最新的开始
+    pub async fn top_playlist_highquality_page(
+        &self,
+        cat: &str,
+        before: Option<u64>,
+        limit: usize,
+    ) -> TResult<CursorPage<Playlist>> {
+        let resp = self
+            .top_playlist_highquality(Some(json!({
+                "cat": cat,
+                "before": before.unwrap_or_default(),
+                "limit": limit,
+            })))
+            .await?
+            .deserialize::<TopPlaylistResp>()?;
+
+        let cursor = resp.playlists.last().map(|playlist| playlist.update_time);
+        Ok(CursorPage::new(resp.playlists, cursor, resp.more))
+    }
+
+    /// 依次获取分类的所有精品歌单
+    pub fn top_playlist_highquality_pages<'a>(
+        &'a self,
+        cat: &'a str,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<CursorPage<Playlist>>> + 'a {
+        paginate_cursor(move |before| self.top_playlist_highquality_page(cat, before, limit))
+    }
+
+    /// 说明 : 调用此接口,传入歌单 id 可获取相关歌单(对应页面 https://music.163.com/#/playlist?id=1)
+    ///
+    /// required
+    /// id : 歌单 id
+    pub async fn related_playlist(&self, id: usize) -> TResult<Vec<RelatedPlaylist>> {
+        let u = replace_all_route_params(API_ROUTE["related_playlist"], &id.to_string());
+        let r = ApiRequestBuilder::get(&u)
+            .set_ua(crate::client::UA::Chrome)
+            .build();
+        let resp = self.client.request(r).await?;
+
+        Ok(parse_related_playlists(&resp.to_string()))
+    }
+
+    /// 说明 : 调用此接口,可获取所有榜单, 包含更新频率和封面
+    pub async fn toplist(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["toplist"]).build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口,可获取所有榜单内容摘要, 即每个榜单的前几首歌曲
+    pub async fn toplist_detail(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["toplist_detail"]).build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 可获取排行榜中的歌手榜
+    ///
+    /// required
+    /// area: 地区, 不支持全部
+    pub async fn toplist_artist(&self, area: Area) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["toplist_artist"])
+            .set_data(json!({
+                "type": area.artist_type(),
+                "limit": 100,
+                "offset": 0,
+                "total": true,
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口,传入榜单 id, 可获取榜单详情, 榜单即歌单
+    ///
+    /// required
+    /// id : 榜单 id, 可从 toplist 接口获取
+    pub async fn top_list(&self, id: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["top_list"])
+            .set_data(json!({"id": id, "n": 500, "s": 0}))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 获取榜单的所有歌曲, 榜单即歌单
+    pub async fn toplist_songs(&self, id: usize) -> TResult<Vec<Song>> {
+        self.playlist_songs(id).await
+    }
+
+    /// 获取歌单的所有歌曲
+    /// 歌单详情中的 tracks 是不完整的, 先取完整的 trackIds 再请求 song_detail 获取所有歌曲
+    pub async fn playlist_songs(&self, id: usize) -> TResult<Vec<Song>> {
+        Ok(self.playlist_with_tracks(id).await?.tracks)
+    }
+
+    /// 获取歌单详情, 其中的 tracks 替换为歌单的所有歌曲
+    /// 歌单详情不使用缓存, 修改歌单后可以立即读到最新的歌曲
+    pub async fn playlist_with_tracks(&self, id: usize) -> TResult<PlaylistDetail> {
+        let mut playlist = self.latest_playlist_detail(id).await?;
+        let ids = playlist
+            .track_ids
+            .iter()
+            .map(|track| track.id)
+            .collect::<Vec<_>>();
+        playlist.tracks = if ids.is_empty() {
+            Vec::new()
+        } else {
+            self.song_detail(&ids).await?.songs
+        };
+        Ok(playlist)
+    }
+
+    /// 获取歌单所有歌曲的 id, 按歌单中的顺序排列, 不使用缓存
+    pub async fn playlist_track_ids(&self, id: usize) -> TResult<Vec<usize>> {
+        let playlist = self.latest_playlist_detail(id).await?;
+        Ok(playlist.track_ids.iter().map(|track| track.id).collect())
+    }
+
+    async fn latest_playlist_detail(&self, id: usize) -> TResult<PlaylistDetail> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_detail"])
+            .set_data(json!({"n": 100000, "s": 8, "id": id}))
+            .set_cache(false)
+            .build();
+
+        Ok(self
+            .client
+            .request(r)
+            .await?
+            .deserialize::<PlaylistDetailResp>()?
+            .playlist
+            .ok_or("playlist not found")?)
+    }
+
+    /// 说明 : 调用此接口 , 可获取新歌速递
+    ///
+    /// required
+    /// area: 地区
+    pub async fn top_song(&self, area: Area) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["top_song"])
+            .set_data(json!({"areaId": area.song_area_id(), "total": true}))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 可获取新碟上架列表
+    ///
+    /// required
+    /// area: 地区
+    ///
+    /// optional
+    /// limit: 取出数量 , 默认为 50
+    /// offset: 偏移数量 , 用于分页 , 如 :( 页数 -1)*50, 其中 50 为 limit 的值 , 默认为 0
+    /// type : new:全部 hot:热门,默认为 new
+    /// year : 年,默认本年
+    /// month : 月,默认本月
+    pub async fn top_album(&self, area: Area, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["top_album"])
+            .set_data(json!({
+                "limit": 50,
+                "offset": 0,
+                "type": "new",
+                "total": false,
+                "rcmd": true,
+            }))
+            .merge(opt.unwrap_or_default())
+            .insert("area", json!(area.album_area()))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 新建歌单
+    pub async fn create_playlist(&self, name: String, privacy: bool) -> TResult<ApiResponse> {
+        let privacy = if privacy {
+            10
+        } else {
+            0
+        };
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_create"])
+            .add_cookie("os", "pc")
+            .set_data(json!({"name": name, "privacy": privacy}))
+            .set_cache(false)
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 删除歌单
+    pub async fn delete_playlist(&self, pid: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playlist_delete"])
+            .add_cookie("os", "pc")
             .set_data(json!({"ids": [pid]}))
+            .set_cache(false)
             .build();
 
         self.client.request(r).await
@@ -572,6 +1304,7 @@ impl NcmApi {
         let r = ApiRequestBuilder::post(API_ROUTE["playlist_name_update"])
             .add_cookie("os", "pc")
             .set_data(json!({"id": pid, "name": name}))
+            .set_cache(false)
             .build();
 
         self.client.request(r).await
@@ -581,6 +1314,7 @@ impl NcmApi {
     pub async fn update_playlist_order(&self, pid: usize, ids: Vec<usize>) -> TResult<ApiResponse> {
         let r = ApiRequestBuilder::post(API_ROUTE["song_order_update"])
             .set_data(json!({"pid": pid, "trackIds": ids, "op": "update"}))
+            .set_cache(false)
             .build();
 
         self.client.request(r).await
@@ -633,117 +1367,211 @@ impl NcmApi {
     //     self.client.request(r).await
     // }
 
-    // /// 说明 : 调用此接口 , 可获取默认搜索关键词
-    // pub async fn search_default(&self) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["search_default"])
-    //         .set_crypto(crate::crypto::Crypto::Eapi)
-    //         .set_api_url("/api/search/defaultkeyword/get")
-    //         .build();
+    /// 说明 : 调用此接口 , 可获取默认搜索关键词
+    pub async fn search_default(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["search_default"])
+            .set_crypto(crate::crypto::Crypto::Eapi)
+            .set_api_url("/api/search/defaultkeyword/get")
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口,可获取热门搜索列表
-    // pub async fn search_hot_detail(&self) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["search_hot_detail"]).build();
+    /// 说明 : 调用此接口,可获取热门搜索列表
+    pub async fn search_hot_detail(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["search_hot_detail"]).build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口,可获取热门搜索列表(简略)
-    // pub async fn search_hot(&self) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["search_hot"])
-    //         .set_data(json!({"type": 1111}))
-    //         .set_ua(crate::client::UA::IPhone)
-    //         .build();
+    /// 说明 : 调用此接口,可获取热门搜索列表(简略)
+    pub async fn search_hot(&self) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["search_hot"])
+            .set_data(json!({"type": 1111}))
+            .set_ua(crate::client::UA::IPhone)
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入搜索关键词可获得搜索建议 , 搜索结果同时包含单曲 , 歌手 , 歌单 ,mv 信息
-    // ///
-    // /// required
-    // /// 必选参数 : keywords : 关键词
-    // ///
-    // /// optional
-    // /// 可选参数 : type : 如果传 'mobile' 则返回移动端数据
-    // pub async fn search_suggest(&self, keyword: &str, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let mut device = "web";
-    //     if let Some(val) = opt {
-    //         if val["type"] == "mobile" {
-    //             device = "mobile"
-    //         }
-    //     }
-
-    //     let u = format!("{}{}", API_ROUTE["search_suggest"], device);
-    //     let r = ApiRequestBuilder::post(&u)
-    //         .set_data(json!({ "s": keyword }))
-    //         .build();
+    /// 说明 : 调用此接口 , 传入搜索关键词可获得搜索建议 , 搜索结果同时包含单曲 , 歌手 , 歌单 ,mv 信息
+    ///
+    /// required
+    /// 必选参数 : keywords : 关键词
+    ///
+    /// optional
+    /// 可选参数 : type : 如果传 'mobile' 则返回移动端数据
+    pub async fn search_suggest(&self, keyword: &str, opt: Option<Value>) -> TResult<ApiResponse> {
+        let mut device = "web";
+        if let Some(val) = opt {
+            if val["type"] == "mobile" {
+                device = "keyword"
+            }
+        }
 
-    //     self.client.request(r).await
-    // }
+        let u = format!("{}{}", API_ROUTE["search_suggest"], device);
+        let r = ApiRequestBuilder::post(&u)
+            .set_data(json!({ "s": keyword }))
+            .build();
 
-    // /// 说明 : 调用此接口 , 传入歌手 id, 可获得相似歌手
-    // ///
-    // /// requried
-    // /// 必选参数 : id: 歌手 id
-    // pub async fn simi_artist(&self, artist_id: usize) -> TResult<ApiResponse> {
-    //     let mut r = ApiRequestBuilder::post(API_ROUTE["simi_artist"])
-    //         .set_data(json!({ "artistid": artist_id }));
-    //     if self
-    //         .client
-    //         .cookie("MUSIC_U", self.client.base_url())
-    //         .is_none()
-    //     {
-    //         r = r.add_cookie("MUSIC_A", ANONYMOUS_TOKEN);
-    //     }
-
-    //     self.client.request(r.build()).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入歌曲 id, 可获得相似歌单
-    // ///
-    // /// required
-    // /// 必选参数 : id: 歌曲 id
-    // pub async fn simi_playlist(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["simi_playlist"])
-    //         .set_data(limit_offset(50, 0))
-    //         .merge(opt.unwrap_or_default())
-    //         .insert("songid", json!(id))
-    //         .build();
+    /// 说明 : 调用此接口 , 传入搜索关键词可获得最匹配的歌手 , 专辑等
+    ///
+    /// required
+    /// 必选参数 : keywords : 关键词
+    pub async fn search_multimatch(&self, keyword: &str) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["search_multimatch"])
+            .set_data(json!({ "type": 1, "s": keyword }))
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入歌曲 id, 可获得相似歌曲
-    // ///
-    // /// required
-    // /// 必选参数 : id: 歌曲 id
-    // pub async fn simi_song(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["simi_song"])
-    //         .set_data(limit_offset(50, 0))
-    //         .merge(opt.unwrap_or_default())
-    //         .insert("songid", json!(id))
-    //         .build();
+    /// 说明 : 调用此接口 , 传入歌手 id, 可获得相似歌手
+    ///
+    /// requried
+    /// 必选参数 : id: 歌手 id
+    pub async fn simi_artist(&self, artist_id: usize) -> TResult<ApiResponse> {
+        let rb = ApiRequestBuilder::post(API_ROUTE["simi_artist"])
+            .set_data(json!({ "artistid": artist_id }));
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(self.anonymous_nuid(rb).build()).await
+    }
 
-    // /// 说明 : 调用此接口 , 传入音乐 id(支持多个 id, 用 , 隔开), 可获得歌曲详情
-    // ///
-    // /// requried
-    // /// 必选参数 : ids: 音乐 id, 如 ids=347230
-    // pub async fn song_detail(&self, ids: &Vec<usize>) -> TResult<ApiResponse> {
-    //     let list = ids
-    //         .iter()
-    //         .map(|id| json!({ "id": id }).to_string())
-    //         .collect::<Vec<_>>();
-    //     let r = ApiRequestBuilder::post(API_ROUTE["song_detail"])
-    //         .set_data(json!({ "c": list }))
-    //         .build();
+    /// 说明 : 调用此接口 , 传入 mv id, 可获得相似 mv
+    ///
+    /// requried
+    /// 必选参数 : mvid: mv id
+    pub async fn simi_mv(&self, mvid: usize) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["simi_mv"])
+            .set_data(json!({ "mvid": mvid }))
+            .build();
 
-    //     self.client.request(r).await
-    // }
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入歌曲 id, 可获得相似歌单
+    ///
+    /// required
+    /// 必选参数 : id: 歌曲 id
+    pub async fn simi_playlist(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["simi_playlist"])
+            .set_data(limit_offset(50, 0))
+            .merge(opt.unwrap_or_default())
+            .insert("songid", json!(id))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入歌曲 id, 可获得相似歌曲
+    ///
+    /// required
+    /// 必选参数 : id: 歌曲 id
+    pub async fn simi_song(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["simi_song"])
+            .set_data(limit_offset(50, 0))
+            .merge(opt.unwrap_or_default())
+            .insert("songid", json!(id))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 登录后调用此接口 , 可获取心动模式/智能播放列表
+    ///
+    /// required
+    /// song_id : 歌曲 id, 一般为喜欢的音乐歌单中的一首歌
+    /// playlist_id : 歌单 id, 一般为喜欢的音乐歌单
+    ///
+    /// optional
+    /// start_id : 要开始播放的歌曲的 id, 默认为 song_id
+    pub async fn intelligence_list(
+        &self,
+        song_id: usize,
+        playlist_id: usize,
+        start_id: Option<usize>,
+    ) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["playmode_intelligence_list"])
+            .set_data(json!({
+                "songId": song_id,
+                "type": "fromPlayOne",
+                "playlistId": playlist_id,
+                "startMusicId": start_id.unwrap_or(song_id),
+                "count": 1,
+            }))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入歌曲 id, 可获得最近 5 个听了这首歌的用户
+    ///
+    /// required
+    /// 必选参数 : id: 歌曲 id
+    pub async fn simi_user(&self, id: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["simi_user"])
+            .set_data(limit_offset(50, 0))
+            .merge(opt.unwrap_or_default())
+            .insert("songid", json!(id))
+            .build();
+
+        self.client.request(r).await
+    }
+
+    /// 说明 : 调用此接口 , 传入音乐 id(支持多个 id), 可获得歌曲详情
+    /// 一次请求的 id 数量有限, 过多的 id 会被拆分为多个请求并发执行,
+    /// 返回的 songs 与 privileges 按 ids 的顺序排列, 重复的 id 会重复出现, 不存在的歌曲会被忽略
+    ///
+    /// requried
+    /// 必选参数 : ids: 音乐 id, 如 ids=347230
+    pub async fn song_detail(&self, ids: &[usize]) -> TResult<SongDetailResp> {
+        let chunks = stream::iter(ids.chunks(SONG_DETAIL_CHUNK_SIZE))
+            .map(|chunk| self.song_detail_chunk(chunk))
+            .buffered(SONG_DETAIL_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let mut songs = HashMap::new();
+        let mut privileges = HashMap::new();
+        for chunk in chunks {
+            songs.extend(chunk.songs.into_iter().map(|song| (song.id, song)));
+            privileges.extend(
+                chunk
+                    .privileges
+                    .into_iter()
+                    .map(|privilege| (privilege.id, privilege)),
+            );
+        }
+
+        Ok(SongDetailResp {
+            code: 200,
+            songs: ids.iter().filter_map(|id| songs.get(id).cloned()).collect(),
+            privileges: ids
+                .iter()
+                .filter_map(|id| privileges.get(id).cloned())
+                .collect(),
+        })
+    }
+
+    async fn song_detail_chunk(&self, ids: &[usize]) -> TResult<SongDetailResp> {
+        let list = ids.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>();
+        let r = ApiRequestBuilder::post(API_ROUTE["song_detail"])
+            .set_data(json!({ "c": Value::Array(list).to_string() }))
+            .build();
+
+        let resp = self
+            .client
+            .request(r)
+            .await?
+            .deserialize::<SongDetailResp>()?;
+        if resp.code != 200 {
+            return Err(format!("song detail failed with code {}", resp.code).into());
+        }
+        Ok(resp)
+    }
 
     /// 说明 : 使用歌单详情接口后 , 能得到的音乐的 id, 但不能得到的音乐 url, 调用此接口, 传入的音乐 id( 可多个 , 用逗号隔开 ),
     /// 可以获取对应的音乐的 url,未登录状态或者非会员返回试听片段(返回字段包含被截取的正常歌曲的开始时间和结束时间)
@@ -751,27 +1579,84 @@ impl NcmApi {
     /// required
     /// 必选参数 : id : 音乐 id
     ///
-    /// optional
-    /// 可选参数 : br: 码率,默认设置了 999000 即最大码率,如果要 320k 则可设置为 320000,其他类推
-    pub async fn song_url(&self, ids: &Vec<usize>) -> TResult<ApiResponse> {
-        let mut rb = ApiRequestBuilder::post(API_ROUTE["song_url"])
+    /// quality: 音质, 对应的码率见 Quality::br, Quality::Lossless 即最大码率 999000
+    pub async fn song_url(&self, ids: &[usize], quality: Quality) -> TResult<ApiResponse> {
+        let rb = ApiRequestBuilder::post(API_ROUTE["song_url"])
             .set_crypto(crate::crypto::Crypto::Eapi)
             .add_cookie("os", "pc")
             .set_api_url("/api/song/enhance/player/url")
-            .set_data(json!({"ids": ids, "br": 999000}));
+            .set_data(json!({"ids": ids, "br": quality.br()}));
+
+        self.client.request(self.anonymous_nuid(rb).build()).await
+    }
+
+    /// 说明 : 新版获取音乐 url 接口, 传入的音乐 id 可多个, 以音质等级 level 代替码率
+    ///
+    /// required
+    /// 必选参数 : id : 音乐 id, quality: 音质等级, 见 Quality::level
+    pub async fn song_url_v1(&self, ids: &[usize], quality: Quality) -> TResult<ApiResponse> {
+        let ids = format!(
+            "[{}]",
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let rb = ApiRequestBuilder::post(API_ROUTE["song_url_v1"])
+            .set_crypto(crate::crypto::Crypto::Eapi)
+            .add_cookie("os", "android")
+            .add_cookie("appver", "8.10.05")
+            .set_api_url("/api/song/enhance/player/url/v1")
+            .set_data(json!({"ids": ids, "level": quality.level(), "encodeType": "flac"}));
+
+        self.client.request(self.anonymous_nuid(rb).build()).await
+    }
 
+    /// 获取 url 的原始内容, 如专辑封面等图片
+    pub async fn fetch(&self, url: &str) -> TResult<Vec<u8>> {
+        self.client.fetch(url).await
+    }
+
+    /// 下载 song_url 返回的 url 到 path, 可断点续传
+    /// 下载时写入 path 对应的 .part 文件, 完成并校验 md5 后重命名为 path
+    ///
+    /// optional
+    /// md5: song_url 返回的 md5, 不一致时删除已下载的文件并返回错误
+    /// progress: 每收到一块数据时以已下载大小和总大小调用
+    pub async fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        md5: Option<&str>,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> TResult<()> {
+        let part = part_path(path);
+        self.client.download(url, &part, progress).await?;
+
+        if let Some(md5) = md5.filter(|md5| !md5.is_empty()) {
+            if !file_md5(&part)?.eq_ignore_ascii_case(md5) {
+                std::fs::remove_file(&part)?;
+                return Err("md5 mismatch".into());
+            }
+        }
+        std::fs::rename(&part, path)?;
+        Ok(())
+    }
+
+    /// Add a random `_ntes_nuid` cookie when not logged in.
+    fn anonymous_nuid(&self, rb: ApiRequestBuilder) -> ApiRequestBuilder {
         if self
             .client
             .cookie("MUSIC_U", self.client.base_url())
-            .is_none()
+            .is_some()
         {
-            let mut rng = rand::thread_rng();
-            let mut token = [0u8; 16];
-            rng.fill_bytes(&mut token);
-            rb = rb.add_cookie("_ntes_nuid", &hex::encode(token));
+            return rb;
         }
 
-        self.client.request(rb.build()).await
+        let mut rng = rand::thread_rng();
+        let mut token = [0u8; 16];
+        rng.fill_bytes(&mut token);
+        rb.add_cookie("_ntes_nuid", &hex::encode(token))
     }
 
     // /// 说明 : 登录后调用此接口 ,可获取用户账号信息
@@ -908,20 +1793,56 @@ impl NcmApi {
         self.client.request(r).await
     }
 
-    // /// 说明 : 登录后调用此接口 , 传入用户 id, 可获取用户播放记录
-    // ///
-    // /// requred
-    // /// 必选参数 : uid : 用户 id
-    // ///
-    // /// optional
-    // /// 可选参数 : type : type=1 时只返回 weekData, type=0 时返回 allData
-    // pub async fn user_record(&self, uid: usize, opt: Option<Value>) -> TResult<ApiResponse> {
-    //     let r = ApiRequestBuilder::post(API_ROUTE["user_record"])
-    //         .set_data(json!({"type": 1, "uid": uid}))
-    //         .merge(opt.unwrap_or_default())
-    //         .build();
-    //     self.client.request(r).await
-    // }
+    /// 分页获取用户歌单
+    pub async fn user_playlist_page(
+        &self,
+        uid: usize,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Playlist>> {
+        let resp = self
+            .user_playlist(uid, Some(limit_offset(limit, offset)))
+            .await?;
+
+        Ok(resp
+            .deserialize::<UserPlaylistResp>()?
+            .into_page(offset, limit))
+    }
+
+    /// 依次获取用户的所有歌单
+    pub fn user_playlist_pages(
+        &self,
+        uid: usize,
+        limit: usize,
+    ) -> impl Stream<Item = TResult<Page<Playlist>>> + '_ {
+        paginate(limit, move |offset, limit| {
+            self.user_playlist_page(uid, offset, limit)
+        })
+    }
+
+    /// 说明 : 登录后调用此接口 , 传入用户 id, 可获取用户播放记录
+    ///
+    /// requred
+    /// 必选参数 : uid : 用户 id
+    ///
+    /// optional
+    /// 可选参数 : type : type=1 时只返回 weekData, type=0 时返回 allData
+    pub async fn user_record(&self, uid: usize, opt: Option<Value>) -> TResult<ApiResponse> {
+        let r = ApiRequestBuilder::post(API_ROUTE["user_record"])
+            .set_data(json!({"type": 1, "uid": uid}))
+            .merge(opt.unwrap_or_default())
+            .build();
+        self.client.request(r).await
+    }
+
+    /// 获取用户最近一周或所有时间的播放记录, 按播放次数排序
+    pub async fn user_play_records(&self, uid: usize, all: bool) -> TResult<Vec<PlayRecord>> {
+        let resp = self
+            .user_record(uid, Some(json!({"type": if all { 0 } else { 1 }})))
+            .await?
+            .deserialize::<UserRecordResp>()?;
+        Ok(if all { resp.all_data } else { resp.week_data })
+    }
 
     // /// 说明 : 登录后调用此接口 , 可以获取用户信息
     // /// 获取用户信息 , 歌单，收藏，mv, dj 数量
@@ -931,6 +1852,29 @@ impl NcmApi {
     // }
 }
 
+/// The related playlists are only shown in the sidebar of the web page.
+fn parse_related_playlists(html: &str) -> Vec<RelatedPlaylist> {
+    let re = regex::Regex::new(concat!(
+        r#"<div class="cver u-cover u-cover-3">[\s\S]*?<img src="([^"]+)">[\s\S]*?"#,
+        r#"<a class="sname f-fs1 s-fc0" href="/playlist\?id=(\d+)"[^>]*>([^<]+?)</a>[\s\S]*?"#,
+        r#"<a class="nm nm f-thide s-fc3" href="/user/home\?id=(\d+)"[^>]*>([^<]+?)</a>"#,
+    ))
+    .unwrap();
+
+    re.captures_iter(html)
+        .map(|cap| RelatedPlaylist {
+            id: cap[2].parse().unwrap_or_default(),
+            name: cap[3].to_owned(),
+            cover_img_url: cap[1].replace("?param=50y50", ""),
+            creator: UserProfile {
+                user_id: cap[4].parse().unwrap_or_default(),
+                nickname: cap[5].to_owned(),
+                ..Default::default()
+            },
+        })
+        .collect()
+}
+
 fn replace_all_route_params(u: &str, rep: &str) -> String {
     let re = regex::Regex::new(r"\$\{.*\}").unwrap();
     re.replace_all(u, rep).to_string()
@@ -971,6 +1915,97 @@ pub enum SearchType {
     All = 1018,
 }
 
+/// 音质等级
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Quality {
+    Standard,
+    Higher,
+    Exhigh,
+    #[default]
+    Lossless,
+    Hires,
+}
+
+impl Quality {
+    /// 对应的码率
+    pub fn br(&self) -> usize {
+        match self {
+            Quality::Standard => 128000,
+            Quality::Higher => 192000,
+            Quality::Exhigh => 320000,
+            Quality::Lossless => 999000,
+            Quality::Hires => 1999000,
+        }
+    }
+
+    /// song_url_v1 接口使用的音质等级
+    pub fn level(&self) -> &'static str {
+        match self {
+            Quality::Standard => "standard",
+            Quality::Higher => "higher",
+            Quality::Exhigh => "exhigh",
+            Quality::Lossless => "lossless",
+            Quality::Hires => "hires",
+        }
+    }
+
+    pub fn from_level(level: &str) -> Option<Self> {
+        match level {
+            "standard" => Some(Quality::Standard),
+            "higher" => Some(Quality::Higher),
+            "exhigh" => Some(Quality::Exhigh),
+            "lossless" => Some(Quality::Lossless),
+            "hires" => Some(Quality::Hires),
+            _ => None,
+        }
+    }
+}
+
+/// 新歌, 新碟和歌手榜的地区
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Area {
+    #[default]
+    All,
+    Chinese,
+    Western,
+    Korean,
+    Japanese,
+}
+
+impl Area {
+    /// top_song 接口使用的地区 id
+    pub fn song_area_id(&self) -> usize {
+        match self {
+            Area::All => 0,
+            Area::Chinese => 7,
+            Area::Western => 96,
+            Area::Korean => 16,
+            Area::Japanese => 8,
+        }
+    }
+
+    /// top_album 接口使用的地区
+    pub fn album_area(&self) -> &'static str {
+        match self {
+            Area::All => "ALL",
+            Area::Chinese => "ZH",
+            Area::Western => "EA",
+            Area::Korean => "KR",
+            Area::Japanese => "JP",
+        }
+    }
+
+    /// toplist_artist 接口使用的地区, 歌手榜没有全部, 默认为华语
+    pub fn artist_type(&self) -> usize {
+        match self {
+            Area::All | Area::Chinese => 1,
+            Area::Western => 2,
+            Area::Korean => 3,
+            Area::Japanese => 4,
+        }
+    }
+}
+
 fn map_resource_code(t: ResourceType) -> String {
     match t {
         ResourceType::Song => String::from("R_SO_4_"),
@@ -0,0 +1,190 @@
+//! Backups of an account and the state of restoring them.
+//!
+//! Each backup is a directory named by its creation time under the backup directory,
+//! so the older backups are kept. The restore state is saved beside the backup, so an
+//! interrupted restore continues with the playlists it has already created.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::types::{Album, Artist, Playlist, PlaylistDetail, UserProfile};
+use crate::TResult;
+
+/// The version of the backup format.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// The file of a backup in its directory.
+const BACKUP_FILE: &str = "backup.json";
+
+/// Everything of an account that can be recreated on another account.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Backup {
+    pub version: u32,
+    /// The seconds since the unix epoch.
+    pub created: u64,
+    pub user: UserProfile,
+    /// The playlists created by the user, with all their songs in `tracks`.
+    pub playlists: Vec<PlaylistDetail>,
+    /// The playlists of others subscribed by the user.
+    pub subscribed_playlists: Vec<Playlist>,
+    /// The ids of the liked songs, the latest first.
+    pub liked: Vec<usize>,
+    pub albums: Vec<Album>,
+    pub artists: Vec<Artist>,
+}
+
+impl Backup {
+    pub fn new(user: UserProfile) -> Self {
+        Self {
+            version: BACKUP_VERSION,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+            user,
+            ..Default::default()
+        }
+    }
+
+    /// Save the backup into a new directory under DIR, return the new directory.
+    pub fn save(&self, dir: &Path) -> TResult<PathBuf> {
+        let dir = dir.join(self.created.to_string());
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(BACKUP_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(dir)
+    }
+
+    /// Load the backup in DIR, which is either the directory of a backup or
+    /// the backup directory, where the latest backup is used.
+    /// Return the directory of the backup and the backup.
+    pub fn load(dir: &Path) -> TResult<(PathBuf, Self)> {
+        let dir = if dir.join(BACKUP_FILE).is_file() {
+            dir.to_path_buf()
+        } else {
+            backups(dir)?.pop().ok_or("no backup found")?
+        };
+        let backup =
+            serde_json::from_str::<Self>(&std::fs::read_to_string(dir.join(BACKUP_FILE))?)?;
+        if backup.version > BACKUP_VERSION {
+            return Err(format!("unsupported backup version {}", backup.version).into());
+        }
+        Ok((dir, backup))
+    }
+}
+
+/// The directories of the backups under DIR, the oldest first.
+pub fn backups(dir: &Path) -> TResult<Vec<PathBuf>> {
+    let mut backups = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let created = entry.file_name().to_str()?.parse::<u64>().ok()?;
+            let path = entry.path();
+            if path.join(BACKUP_FILE).is_file() {
+                Some((created, path))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The progress of restoring a backup to an account.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RestoreState {
+    pub user_id: usize,
+    /// The ids of the backed up playlists and the playlists recreated from them.
+    pub playlists: BTreeMap<usize, usize>,
+}
+
+impl RestoreState {
+    fn path(dir: &Path, user_id: usize) -> PathBuf {
+        dir.join(format!("restore-{}.json", user_id))
+    }
+
+    /// Load the state of restoring the backup in DIR to the user USER_ID,
+    /// a new state if it hasn't been restored.
+    pub fn load(dir: &Path, user_id: usize) -> Self {
+        std::fs::read_to_string(Self::path(dir, user_id))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or(Self {
+                user_id,
+                ..Default::default()
+            })
+    }
+
+    pub fn save(&self, dir: &Path) -> TResult<()> {
+        std::fs::write(
+            Self::path(dir, self.user_id),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// The ids in WANTED but not in EXISTING, in the order of WANTED.
+pub fn missing(existing: &[usize], wanted: &[usize]) -> Vec<usize> {
+    let existing = existing.iter().collect::<HashSet<_>>();
+    wanted
+        .iter()
+        .filter(|id| !existing.contains(id))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backups, missing, Backup, RestoreState};
+    use crate::types::{PlaylistDetail, UserProfile};
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(missing(&[2, 4], &[1, 2, 3, 4, 5]), vec![1, 3, 5]);
+        assert!(missing(&[1, 2], &[2, 1]).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join("ncmapi_test_backup");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut old = Backup::new(UserProfile {
+            user_id: 1,
+            nickname: "Spring".to_string(),
+            ..Default::default()
+        });
+        old.created = 100;
+        old.liked = vec![3, 2, 1];
+        let mut new = old.clone();
+        new.created = 200;
+        new.playlists = vec![PlaylistDetail {
+            id: 10,
+            name: "Road trip".to_string(),
+            ..Default::default()
+        }];
+
+        let old_dir = old.save(&dir).unwrap();
+        let new_dir = new.save(&dir).unwrap();
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        assert_eq!(
+            backups(&dir).unwrap(),
+            vec![old_dir.clone(), new_dir.clone()]
+        );
+        assert_eq!(Backup::load(&dir).unwrap(), (new_dir.clone(), new));
+        assert_eq!(Backup::load(&old_dir).unwrap(), (old_dir, old));
+
+        let mut state = RestoreState::load(&new_dir, 2);
+        assert!(state.playlists.is_empty());
+        state.playlists.insert(10, 20);
+        state.save(&new_dir).unwrap();
+        assert_eq!(RestoreState::load(&new_dir, 2), state);
+        assert_eq!(RestoreState::load(&new_dir, 3).playlists.len(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+//! Find the duplicate and unplayable songs of a playlist, and plan removing them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{matching::normalize, playlist_sync::SyncPlan, types::Song};
+
+/// The near duplicates can differ this many milliseconds in duration,
+/// the remasters and other uploads are usually a few seconds longer or shorter.
+pub const DUPLICATE_DURATION_TOLERANCE: usize = 10000;
+
+/// Why a song is proposed to be removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    /// The same song id appears earlier in the playlist.
+    Duplicate,
+    /// Another version of the same recording appears earlier in the playlist,
+    /// like a live, remastered or reuploaded one.
+    NearDuplicate,
+    /// The song can't be played any more.
+    Unplayable,
+}
+
+/// A song proposed to be removed, `index` is its position in the playlist and
+/// `original` is the position of the song it duplicates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    pub index: usize,
+    pub issue: Issue,
+    pub original: Option<usize>,
+}
+
+/// The normalized title and artists of SONG.
+fn song_key(song: &Song) -> (String, HashSet<String>) {
+    let artists = song
+        .artists
+        .iter()
+        .filter_map(|artist| artist.name.as_deref())
+        .map(normalize)
+        .filter(|name| !name.is_empty())
+        .collect();
+    (normalize(&song.name), artists)
+}
+
+fn same_recording(
+    a: &Song,
+    b: &Song,
+    a_artists: &HashSet<String>,
+    b_artists: &HashSet<String>,
+) -> bool {
+    let same_artist =
+        a_artists.is_empty() || b_artists.is_empty() || !a_artists.is_disjoint(b_artists);
+    let same_duration = a.duration == 0
+        || b.duration == 0
+        || (a.duration as i64 - b.duration as i64).unsigned_abs() as usize
+            <= DUPLICATE_DURATION_TOLERANCE;
+    same_artist && same_duration
+}
+
+/// Find the songs of a playlist to remove, PLAYABLE tells whether each song is playable.
+/// The repeated ids are duplicates of their first appearance, then the unplayable songs
+/// are removed, then the remaining versions of the same recording are near duplicates
+/// of the first one. The removals are in the order of the playlist.
+pub fn find_removals(songs: &[Song], playable: &[bool]) -> Vec<Removal> {
+    let mut removals = Vec::new();
+    let mut first = HashMap::new();
+    let mut kept = Vec::new();
+    for (index, song) in songs.iter().enumerate() {
+        if let Some(original) = first.get(&song.id) {
+            removals.push(Removal {
+                index,
+                issue: Issue::Duplicate,
+                original: Some(*original),
+            });
+        } else {
+            first.insert(song.id, index);
+            if playable.get(index).copied().unwrap_or(true) {
+                kept.push(index);
+            } else {
+                removals.push(Removal {
+                    index,
+                    issue: Issue::Unplayable,
+                    original: None,
+                });
+            }
+        }
+    }
+
+    let mut groups = HashMap::<String, Vec<(usize, HashSet<String>)>>::new();
+    for index in kept {
+        let (title, artists) = song_key(&songs[index]);
+        let group = groups.entry(title).or_default();
+        let original = group
+            .iter()
+            .find(|(original, original_artists)| {
+                same_recording(&songs[*original], &songs[index], original_artists, &artists)
+            })
+            .map(|(original, _)| *original);
+        match original {
+            Some(original) => removals.push(Removal {
+                index,
+                issue: Issue::NearDuplicate,
+                original: Some(original),
+            }),
+            None => group.push((index, artists)),
+        }
+    }
+
+    removals.sort_by_key(|removal| removal.index);
+    removals
+}
+
+/// Plan removing the songs at INDEXES from the playlist whose song ids are CURRENT.
+/// Removing an id removes all its appearances, so the repeated songs still kept are
+/// removed, added back and put to their first positions again.
+pub fn removal_plan(current: &[usize], indexes: &[usize]) -> SyncPlan {
+    let indexes = indexes.iter().collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    let target = current
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !indexes.contains(index))
+        .map(|(_, id)| *id)
+        .filter(|id| seen.insert(*id))
+        .collect::<Vec<_>>();
+
+    let mut counts = HashMap::new();
+    for id in current.iter() {
+        *counts.entry(*id).or_insert(0) += 1;
+    }
+    let repeated = target
+        .iter()
+        .filter(|id| counts[*id] > 1)
+        .copied()
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    let unique = current
+        .iter()
+        .filter(|id| seen.insert(**id))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut plan = SyncPlan::new(&unique, &target);
+    if !repeated.is_empty() {
+        plan.remove.extend(repeated.iter());
+        plan.add.extend(repeated.iter());
+        plan.order = Some(target);
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_removals, removal_plan, Issue, Removal};
+    use crate::types::{Artist, Song};
+
+    fn song(id: usize, name: &str, artist: &str, duration: usize) -> Song {
+        Song {
+            id,
+            name: name.to_string(),
+            artists: vec![Artist {
+                id: 0,
+                name: Some(artist.to_string()),
+            }],
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_removals() {
+        let songs = vec![
+            song(1, "Yesterday", "The Beatles", 125000),
+            song(2, "Yesterday - Remastered 2009", "The Beatles", 126000),
+            song(1, "Yesterday", "The Beatles", 125000),
+            song(3, "Yesterday", "Another Band", 125000),
+            song(4, "Yesterday (Live)", "The Beatles", 180000),
+            song(5, "Gone", "Nobody", 200000),
+            song(6, "Gone", "Nobody", 200000),
+        ];
+        let removals = find_removals(&songs, &[true, true, true, true, true, false, true]);
+        assert_eq!(
+            removals,
+            vec![
+                Removal {
+                    index: 1,
+                    issue: Issue::NearDuplicate,
+                    original: Some(0),
+                },
+                Removal {
+                    index: 2,
+                    issue: Issue::Duplicate,
+                    original: Some(0),
+                },
+                Removal {
+                    index: 5,
+                    issue: Issue::Unplayable,
+                    original: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removal_plan() {
+        let plan = removal_plan(&[1, 2, 3], &[1]);
+        assert_eq!(plan.remove, vec![2]);
+        assert!(plan.add.is_empty());
+        assert_eq!(plan.order, None);
+
+        let plan = removal_plan(&[1, 2, 1, 3], &[2]);
+        assert_eq!(plan.remove, vec![1]);
+        assert_eq!(plan.add, vec![1]);
+        assert_eq!(plan.order, Some(vec![1, 2, 3]));
+
+        let plan = removal_plan(&[1, 2, 1, 3], &[0, 2]);
+        assert_eq!(plan.remove, vec![1]);
+        assert!(plan.add.is_empty());
+        assert_eq!(plan.order, None);
+    }
+}
@@ -12,6 +12,8 @@ pub struct ApiRequest {
     url: String,
     data: Option<H>,
     option: RequestOption,
+    #[serde(skip)]
+    cache: bool,
 }
 
 pub struct ApiRequestBuilder {
@@ -28,6 +30,7 @@ struct Config {
     crypto: Crypto,
     api_url: Option<String>,
     real_ip: Option<String>,
+    cache: bool,
 }
 
 type Pieces = (
@@ -59,11 +62,13 @@ impl ApiRequestBuilder {
                 crypto: Crypto::Weapi,
                 api_url: None,
                 real_ip: None,
+                cache: true,
             },
         }
     }
 
     pub fn build(self) -> ApiRequest {
+        let cache = self.config.cache;
         let (method, url, data, ua, cookies, crypto, api_url, real_ip) = self.pieces();
         ApiRequest {
             method,
@@ -76,6 +81,7 @@ impl ApiRequestBuilder {
                 api_url,
                 real_ip,
             },
+            cache,
         }
     }
 
@@ -83,6 +89,10 @@ impl ApiRequestBuilder {
         Self::new(Method::POST, url)
     }
 
+    pub fn get(url: &str) -> Self {
+        Self::new(Method::GET, url)
+    }
+
     pub fn pieces(self) -> Pieces {
         let config = self.config;
         (
@@ -171,6 +181,13 @@ impl ApiRequestBuilder {
         self.config.real_ip = Some(String::from(real_ip));
         self
     }
+
+    /// Whether the response may be served from and saved to the cache.
+    /// Disable it for requests that change the remote state or must see its latest version.
+    pub fn set_cache(mut self, cache: bool) -> Self {
+        self.config.cache = cache;
+        self
+    }
 }
 
 impl Default for ApiRequest {
@@ -208,6 +225,10 @@ impl ApiRequest {
         hex::encode(digest)
     }
 
+    pub fn cache(&self) -> bool {
+        self.cache
+    }
+
     // pub fn url(&self) -> &str {
     //     &self.url
     // }
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt;
 
@@ -22,11 +22,13 @@ impl ApiResponse {
         serde_json::from_slice::<ImplicitResult>(self.data()).unwrap()
     }
 
-    // pub fn deserialize<'a, T>(&self)-> Result<T, serde_json::Error>
-    // where T: Deserialize<'a>
-    // {
-    //     serde_json::from_slice::<T>(self.data())
-    // }
+    /// Deserialize the response into one of the typed structures in `crate::types`.
+    pub fn deserialize<T>(&self) -> Result<T, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_slice::<T>(self.data())
+    }
 }
 
 impl fmt::Debug for ApiResponse {
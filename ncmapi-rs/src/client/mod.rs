@@ -18,8 +18,11 @@ use rand::Rng;
 use regex::Regex;
 use reqwest::{
     cookie::{CookieStore, Jar},
-    header::{HeaderMap, HeaderValue, CONTENT_TYPE, COOKIE, REFERER, SET_COOKIE, USER_AGENT},
-    Client, Request, Response, Url,
+    header::{
+        HeaderMap, HeaderValue, CONTENT_RANGE, CONTENT_TYPE, COOKIE, RANGE, REFERER, SET_COOKIE,
+        USER_AGENT,
+    },
+    Client, Request, Response, StatusCode, Url,
 };
 use serde::Serialize;
 
@@ -30,6 +33,7 @@ use serde_json::{json, Value};
 use store::{InMemStore, Store};
 
 use crate::crypto::{eapi, linuxapi, weapi, Crypto};
+use crate::download::content_range_total;
 use crate::TResult;
 
 use self::api_request::Hm;
@@ -144,8 +148,9 @@ impl ApiClient {
 
     pub async fn request(&self, req: ApiRequest) -> TResult<ApiResponse> {
         let id = req.id();
+        let cache = req.cache();
 
-        if self.store.contains_key(&id) {
+        if cache && self.store.contains_key(&id) {
             return Ok(self.store.get(&id).unwrap());
         }
 
@@ -155,10 +160,82 @@ impl ApiClient {
         }
 
         let resp = self.client.execute(request).await?;
-        self.on_response(id, resp).await
+        self.on_response(id, resp, cache).await
     }
 
-    async fn on_response(&self, id: String, resp: Response) -> TResult<ApiResponse> {
+    /// Upload the raw BODY to the nos storage URL with the allocated TOKEN.
+    /// The response isn't cached since each upload is different.
+    pub async fn upload(
+        &self,
+        url: &str,
+        token: &str,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> TResult<ApiResponse> {
+        let resp = self
+            .client
+            .post(url)
+            .header("x-nos-token", token)
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await?;
+        let body = resp.bytes().await?;
+
+        Ok(ApiResponse::new(body.to_vec()))
+    }
+
+    /// Get the raw content of `url`, like images. The response isn't cached.
+    pub async fn fetch(&self, url: &str) -> TResult<Vec<u8>> {
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// Download `url` to `path`, resuming from the end of `path` if it exists.
+    /// `progress` is called with the downloaded and the total size after each chunk.
+    pub async fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> TResult<()> {
+        let existing = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let mut request = self.client.get(url);
+        if existing > 0 {
+            request = request.header(RANGE, format!("bytes={}-", existing));
+        }
+        let mut resp = request.send().await?;
+
+        let (mut file, mut downloaded, total) = match resp.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                let total = resp
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(content_range_total);
+                let file = OpenOptions::new().append(true).open(path)?;
+                (file, existing, total)
+            }
+            // the partial file is already complete
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                progress(existing, Some(existing));
+                return Ok(());
+            }
+            // the server ignored the range, start over
+            status if status.is_success() => (File::create(path)?, 0, resp.content_length()),
+            status => return Err(format!("download failed with status {}", status).into()),
+        };
+
+        progress(downloaded, total);
+        while let Some(chunk) = resp.chunk().await? {
+            file.write_all(&chunk)?;
+            downloaded += chunk.len() as u64;
+            progress(downloaded, total);
+        }
+        Ok(())
+    }
+
+    async fn on_response(&self, id: String, resp: Response, cache: bool) -> TResult<ApiResponse> {
         let mut cs = resp.headers().get_all(SET_COOKIE).iter().peekable();
         if cs.peek().is_some() {
             // sync cookie to jar
@@ -170,6 +247,9 @@ impl ApiClient {
 
         let body = resp.bytes().await?;
         let res = ApiResponse::new(body.to_vec());
+        if !cache {
+            return Ok(res);
+        }
 
         // cache response
         self.store
@@ -104,6 +104,7 @@ pub(crate) static API_ROUTE: Map<&'static str, &'static str> = phf_map! {
     "login_status"=>                     "https://music.163.com/weapi/w/nuser/account/get",
     "logout"=>                           "https://music.163.com/weapi/logout",
     "lyric"=>                            "https://music.163.com/api/song/lyric",
+    "lyric_new"=>                        "https://interface3.music.163.com/eapi/song/lyric/v1",
     "mlog_to_video"=>                    "https://music.163.com/weapi/mlog/video/convert/id",
     "mlog_url"=>                         "https://music.163.com/weapi/mlog/detail/v1",
     "msg_comments"=>                     "https://music.163.com/api/v1/user/comments/${query.uid}",
@@ -125,6 +126,7 @@ pub(crate) static API_ROUTE: Map<&'static str, &'static str> = phf_map! {
     "mv_sub"=>                           "https://music.163.com/weapi/mv/${query.t}",
     "mv_sublist"=>                       "https://music.163.com/weapi/cloudvideo/allvideo/sublist",
     "mv_url"=>                           "https://music.163.com/weapi/song/enhance/play/mv/url",
+    "nos_token_alloc"=>                  "https://music.163.com/weapi/nos/token/alloc",
     "personal_fm"=>                      "https://music.163.com/weapi/v1/radio/get",
     "personalized_djprogram"=>           "https://music.163.com/weapi/personalized/djprogram",
     "personalized"=>                     "https://music.163.com/weapi/personalized/playlist",
@@ -184,6 +186,7 @@ pub(crate) static API_ROUTE: Map<&'static str, &'static str> = phf_map! {
     "song_order_update"=>        "http://interface.music.163.com/api/playlist/manipulate/tracks",
     "song_purchased"=>           "https://music.163.com/weapi/single/mybought/song/list",
     "song_url"=>                 "https://interface3.music.163.com/eapi/song/enhance/player/url",
+    "song_url_v1"=>              "https://interface3.music.163.com/eapi/song/enhance/player/url/v1",
     "top_album"=>                "https://music.163.com/api/discovery/new/albums/area",
     "top_artists"=>              "https://music.163.com/weapi/artist/top",
     "topic_detail_event_hot"=>   "https://music.163.com/api/act/event/hot",
@@ -216,6 +219,7 @@ pub(crate) static API_ROUTE: Map<&'static str, &'static str> = phf_map! {
     "user_replacephone"=>        "https://music.163.com/api/user/replaceCellphone",
     "user_subcount"=>            "https://music.163.com/weapi/subcount",
     "user_update"=>              "https://music.163.com/weapi/user/profile/update",
+    "upload_img_op"=>            "https://music.163.com/upload/img/op",
     "video_category_list"=>      "https://music.163.com/api/cloudvideo/category/list",
     "video_detail_info"=>        "https://music.163.com/api/comment/commentthread/info",
     "video_detail"=>             "https://music.163.com/weapi/cloudvideo/v1/video/detail",
@@ -0,0 +1,68 @@
+//! Helpers for downloading files with resuming and checksum verification.
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use openssl::hash::{Hasher, MessageDigest};
+
+use crate::TResult;
+
+/// The path of the partial file used while downloading to `path`.
+/// The file is renamed to `path` only after it's completed and verified.
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// The hex MD5 checksum of the file at `path`.
+pub fn file_md5(path: &Path) -> TResult<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(MessageDigest::md5())?;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read])?;
+    }
+    Ok(hex::encode(hasher.finish()?))
+}
+
+/// The complete size in a `Content-Range` header, like `bytes 100-199/200`.
+pub(crate) fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit('/').next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{content_range_total, file_md5, part_path};
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("/music/a - b.flac")),
+            Path::new("/music/a - b.flac.part")
+        );
+    }
+
+    #[test]
+    fn test_file_md5() {
+        let path = std::env::temp_dir().join("ncmapi_test_file_md5");
+        std::fs::write(&path, "hello").unwrap();
+        assert_eq!(file_md5(&path).unwrap(), "5d41402abc4b2a76b9719d911017c592");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes 100-199/*"), None);
+    }
+}
@@ -1,11 +1,26 @@
 //! NetEase Cloud Music API For Rust.
 
 mod api;
+pub mod backup;
+pub mod cleanup;
 mod client;
 mod crypto;
+pub mod date;
+pub mod download;
+pub mod library;
+pub mod lyric;
+pub mod matching;
+mod page;
+pub mod playlist_file;
+pub mod playlist_sync;
+pub mod smart;
+pub mod stats;
+pub mod tag;
 pub mod types;
 
-pub use api::{NcmApi, ResourceType, SearchType};
+pub use api::{Area, NcmApi, Quality, ResourceType, SearchType};
+pub use client::ApiResponse;
+pub use page::{CursorPage, Page, Paginated};
 
 type TResult<T> = std::result::Result<T, TError>;
 type TError = Box<dyn std::error::Error>;
@@ -0,0 +1,566 @@
+//! A local library of the songs and playlists, indexed for full-text search so that
+//! they can be searched without the network.
+
+use std::path::Path;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    lyric::{LyricLayout, Lyrics},
+    page::Page,
+    stats::Play,
+    tag::Tags,
+    types::{Album, Artist, Playlist, Song},
+    TResult,
+};
+
+/// The trigram tokenizer only matches the terms with at least this many characters,
+/// the shorter terms are searched with LIKE.
+const TRIGRAM_LENGTH: usize = 3;
+
+/// The songs and playlists are stored as JSON in `data`, the other columns are indexed
+/// by the external content FTS tables, which are kept in sync by the triggers.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS songs (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    artists TEXT NOT NULL,
+    album TEXT NOT NULL,
+    lyrics TEXT NOT NULL DEFAULT '',
+    file TEXT,
+    data TEXT NOT NULL
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS songs_fts USING fts5(
+    name, artists, album, lyrics,
+    content = 'songs', content_rowid = 'id', tokenize = 'trigram'
+);
+CREATE TRIGGER IF NOT EXISTS songs_ai AFTER INSERT ON songs BEGIN
+    INSERT INTO songs_fts (rowid, name, artists, album, lyrics)
+    VALUES (new.id, new.name, new.artists, new.album, new.lyrics);
+END;
+CREATE TRIGGER IF NOT EXISTS songs_ad AFTER DELETE ON songs BEGIN
+    INSERT INTO songs_fts (songs_fts, rowid, name, artists, album, lyrics)
+    VALUES ('delete', old.id, old.name, old.artists, old.album, old.lyrics);
+END;
+CREATE TRIGGER IF NOT EXISTS songs_au AFTER UPDATE ON songs BEGIN
+    INSERT INTO songs_fts (songs_fts, rowid, name, artists, album, lyrics)
+    VALUES ('delete', old.id, old.name, old.artists, old.album, old.lyrics);
+    INSERT INTO songs_fts (rowid, name, artists, album, lyrics)
+    VALUES (new.id, new.name, new.artists, new.album, new.lyrics);
+END;
+
+CREATE TABLE IF NOT EXISTS playlists (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    creator TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS playlists_fts USING fts5(
+    name, creator,
+    content = 'playlists', content_rowid = 'id', tokenize = 'trigram'
+);
+CREATE TRIGGER IF NOT EXISTS playlists_ai AFTER INSERT ON playlists BEGIN
+    INSERT INTO playlists_fts (rowid, name, creator) VALUES (new.id, new.name, new.creator);
+END;
+CREATE TRIGGER IF NOT EXISTS playlists_ad AFTER DELETE ON playlists BEGIN
+    INSERT INTO playlists_fts (playlists_fts, rowid, name, creator)
+    VALUES ('delete', old.id, old.name, old.creator);
+END;
+CREATE TRIGGER IF NOT EXISTS playlists_au AFTER UPDATE ON playlists BEGIN
+    INSERT INTO playlists_fts (playlists_fts, rowid, name, creator)
+    VALUES ('delete', old.id, old.name, old.creator);
+    INSERT INTO playlists_fts (rowid, name, creator) VALUES (new.id, new.name, new.creator);
+END;
+
+CREATE TABLE IF NOT EXISTS plays (
+    song_id INTEGER NOT NULL,
+    played_at INTEGER NOT NULL,
+    listened INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS plays_played_at ON plays (played_at);
+";
+
+pub struct Library {
+    conn: Connection,
+}
+
+fn artist_names(artists: &[Artist]) -> String {
+    artists
+        .iter()
+        .filter_map(|artist| artist.name.to_owned())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Escape the LIKE wildcards in `term` and match it anywhere.
+fn like_pattern(term: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+impl Library {
+    /// Open the library at `path`, it's created if it doesn't exist.
+    pub fn open(path: &Path) -> TResult<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> TResult<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> TResult<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Add or update `songs`, their files and lyrics are kept.
+    pub fn add_songs(&mut self, songs: &[Song]) -> TResult<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT INTO songs (id, name, artists, album, data) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (id) DO UPDATE SET name = excluded.name, artists = excluded.artists,
+                 album = excluded.album, data = excluded.data",
+            )?;
+            for song in songs.iter() {
+                statement.execute(params![
+                    song.id as i64,
+                    song.name,
+                    artist_names(&song.artists),
+                    song.album.name.to_owned().unwrap_or_default(),
+                    serde_json::to_string(song)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Add or update `playlists`.
+    pub fn add_playlists(&mut self, playlists: &[Playlist]) -> TResult<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT INTO playlists (id, name, creator, data) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (id) DO UPDATE SET name = excluded.name,
+                 creator = excluded.creator, data = excluded.data",
+            )?;
+            for playlist in playlists.iter() {
+                let creator = playlist
+                    .creator
+                    .as_ref()
+                    .map(|creator| creator.nickname.to_owned())
+                    .unwrap_or_default();
+                statement.execute(params![
+                    playlist.id as i64,
+                    playlist.name,
+                    creator,
+                    serde_json::to_string(playlist)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Index the lyrics of the song whose id is `id`, the song should have been added.
+    pub fn set_lyrics(&self, id: usize, lyrics: &Lyrics) -> TResult<()> {
+        self.conn.execute(
+            "UPDATE songs SET lyrics = ?2 WHERE id = ?1",
+            params![id as i64, lyrics.to_text(LyricLayout::Stacked)],
+        )?;
+        Ok(())
+    }
+
+    /// Record the downloaded file of the song whose id is `id`.
+    pub fn set_file(&self, id: usize, file: Option<&Path>) -> TResult<()> {
+        self.conn.execute(
+            "UPDATE songs SET file = ?2 WHERE id = ?1",
+            params![
+                id as i64,
+                file.map(|file| file.to_string_lossy().to_string())
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Add the file at `path` with its `tags`, the song is added from the tags if it
+    /// isn't in the library, so the files can be indexed without the network.
+    pub fn add_file(&mut self, path: &Path, tags: &Tags) -> TResult<()> {
+        let exists = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM songs WHERE id = ?1",
+                params![tags.song_id as i64],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !exists {
+            let song = Song {
+                id: tags.song_id,
+                name: tags.title.to_owned(),
+                artists: tags
+                    .artists
+                    .iter()
+                    .map(|artist| Artist {
+                        name: Some(artist.to_owned()),
+                        ..Default::default()
+                    })
+                    .collect(),
+                album: Album {
+                    name: Some(tags.album.to_owned()),
+                    ..Default::default()
+                },
+                no: tags.track.unwrap_or_default() as usize,
+                ..Default::default()
+            };
+            self.add_songs(&[song])?;
+        }
+        if let Some(ref lyrics) = tags.lyrics {
+            self.set_lyrics(tags.song_id, lyrics)?;
+        }
+        self.set_file(tags.song_id, Some(path))
+    }
+
+    /// The song whose id is `id`.
+    pub fn song(&self, id: usize) -> TResult<Option<Song>> {
+        let data = self
+            .conn
+            .query_row(
+                "SELECT data FROM songs WHERE id = ?1",
+                params![id as i64],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    /// Log a play of `song`, which is added to the library too.
+    pub fn add_play(&mut self, song: &Song, played_at: u64, listened: u64) -> TResult<()> {
+        self.add_songs(std::slice::from_ref(song))?;
+        self.conn.execute(
+            "INSERT INTO plays (song_id, played_at, listened) VALUES (?1, ?2, ?3)",
+            params![song.id as i64, played_at as i64, listened as i64],
+        )?;
+        Ok(())
+    }
+
+    /// The plays since `since` in milliseconds, the earliest first.
+    pub fn plays(&self, since: u64) -> TResult<Vec<Play>> {
+        let mut statement = self.conn.prepare(
+            "SELECT songs.data, plays.played_at, plays.listened FROM plays
+             JOIN songs ON songs.id = plays.song_id
+             WHERE plays.played_at >= ?1 ORDER BY plays.played_at",
+        )?;
+        let rows = statement.query_map(params![since as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        let mut plays = Vec::new();
+        for row in rows {
+            let (data, played_at, listened) = row?;
+            plays.push(Play {
+                song: serde_json::from_str(&data)?,
+                played_at: played_at as u64,
+                listened: listened as u64,
+            });
+        }
+        Ok(plays)
+    }
+
+    /// The downloaded file of the song whose id is `id`.
+    pub fn song_file(&self, id: usize) -> TResult<Option<String>> {
+        let file = self
+            .conn
+            .query_row(
+                "SELECT file FROM songs WHERE id = ?1",
+                params![id as i64],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        Ok(file.flatten())
+    }
+
+    /// Forget the files which don't exist anymore, return how many are forgotten.
+    pub fn forget_missing_files(&self) -> TResult<usize> {
+        let files = {
+            let mut statement = self
+                .conn
+                .prepare("SELECT id, file FROM songs WHERE file IS NOT NULL")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut forgotten = 0;
+        for (id, file) in files.iter() {
+            if !Path::new(file).exists() {
+                self.set_file(*id as usize, None)?;
+                forgotten += 1;
+            }
+        }
+        Ok(forgotten)
+    }
+
+    /// Search the titles, artists, albums and lyrics of the songs.
+    pub fn search_songs(&self, query: &str, offset: usize, limit: usize) -> TResult<Page<Song>> {
+        self.search(
+            "songs",
+            &["name", "artists", "album", "lyrics"],
+            query,
+            offset,
+            limit,
+        )
+    }
+
+    /// Search the names and creators of the playlists.
+    pub fn search_playlists(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<Playlist>> {
+        self.search("playlists", &["name", "creator"], query, offset, limit)
+    }
+
+    /// Search `table` for the rows matching all the terms of `query`.
+    /// The rows are ordered by relevance when they're matched by the FTS index.
+    fn search<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> TResult<Page<T>> {
+        let terms = query.split_whitespace().collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Ok(Page::new(Vec::new(), offset, limit, Some(0), false));
+        }
+
+        let (from, condition, order, values) = if terms
+            .iter()
+            .all(|term| term.chars().count() >= TRIGRAM_LENGTH)
+        {
+            let phrases = terms
+                .iter()
+                .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            (
+                format!("{0} JOIN {0}_fts ON {0}_fts.rowid = {0}.id", table),
+                format!("{}_fts MATCH ?", table),
+                format!("{}_fts.rank", table),
+                vec![phrases],
+            )
+        } else {
+            let term_condition = format!(
+                "({})",
+                columns
+                    .iter()
+                    .map(|column| format!("{}.{} LIKE ? ESCAPE '\\'", table, column))
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            );
+            (
+                table.to_string(),
+                vec![term_condition; terms.len()].join(" AND "),
+                format!("{}.name", table),
+                terms
+                    .iter()
+                    .flat_map(|term| vec![like_pattern(term); columns.len()])
+                    .collect(),
+            )
+        };
+
+        let total = self.conn.query_row(
+            &format!("SELECT count(*) FROM {} WHERE {}", from, condition),
+            params_from_iter(values.iter()),
+            |row| row.get::<_, i64>(0),
+        )? as usize;
+
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {}.data FROM {} WHERE {} ORDER BY {} LIMIT {} OFFSET {}",
+            table, from, condition, order, limit, offset
+        ))?;
+        let items = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .map(|data| serde_json::from_str(data))
+            .collect::<Result<Vec<T>, _>>()?;
+        Ok(Page::new(items, offset, limit, Some(total), false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Library;
+    use crate::{
+        lyric::Lyrics,
+        tag::Tags,
+        types::{Album, Artist, Playlist, Song, UserProfile},
+    };
+
+    fn song(id: usize, name: &str, artist: &str, album: &str) -> Song {
+        Song {
+            id,
+            name: name.to_string(),
+            artists: vec![Artist {
+                id: 1,
+                name: Some(artist.to_string()),
+            }],
+            album: Album {
+                name: Some(album.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn library() -> Library {
+        let mut library = Library::open_in_memory().unwrap();
+        library
+            .add_songs(&[
+                song(1, "晴天", "周杰伦", "叶惠美"),
+                song(2, "Yesterday", "The Beatles", "Help!"),
+                song(3, "七里香", "周杰伦", "七里香"),
+            ])
+            .unwrap();
+        library
+    }
+
+    fn ids(page: &crate::Page<Song>) -> Vec<usize> {
+        page.items.iter().map(|song| song.id).collect()
+    }
+
+    #[test]
+    fn test_search_songs() {
+        let library = library();
+
+        let page = library.search_songs("周杰伦", 0, 1).unwrap();
+        assert_eq!(page.total, Some(2));
+        assert!(page.has_more);
+        assert_eq!(
+            ids(&library.search_songs("beatles yesterday", 0, 10).unwrap()),
+            vec![2]
+        );
+        // shorter than a trigram
+        assert_eq!(ids(&library.search_songs("晴天", 0, 10).unwrap()), vec![1]);
+        assert_eq!(
+            ids(&library.search_songs("七里 周杰伦", 0, 10).unwrap()),
+            vec![3]
+        );
+        assert!(library
+            .search_songs("100%", 0, 10)
+            .unwrap()
+            .items
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lyrics_and_files() {
+        let mut library = library();
+        library
+            .set_lyrics(
+                1,
+                &Lyrics::parse("[00:01.00]刮风这天 我试过握着你手", "", ""),
+            )
+            .unwrap();
+        library
+            .set_file(1, Some(Path::new("/music/晴天.flac")))
+            .unwrap();
+        // updating the song keeps its lyrics and file
+        library
+            .add_songs(&[song(1, "晴天", "周杰伦", "叶惠美")])
+            .unwrap();
+
+        assert_eq!(
+            ids(&library.search_songs("握着你手", 0, 10).unwrap()),
+            vec![1]
+        );
+        assert_eq!(
+            library.song_file(1).unwrap(),
+            Some("/music/晴天.flac".to_string())
+        );
+        assert_eq!(library.forget_missing_files().unwrap(), 1);
+        assert_eq!(library.song_file(1).unwrap(), None);
+
+        let tags = Tags {
+            song_id: 4,
+            title: "Hey Jude".to_string(),
+            artists: vec!["The Beatles".to_string()],
+            ..Default::default()
+        };
+        library
+            .add_file(Path::new("/music/hey.mp3"), &tags)
+            .unwrap();
+        assert_eq!(
+            ids(&library.search_songs("Beatles", 0, 10).unwrap()).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_search_playlists() {
+        let mut library = library();
+        library
+            .add_playlists(&[Playlist {
+                id: 10,
+                name: "华语经典".to_string(),
+                creator: Some(UserProfile {
+                    nickname: "someone".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }])
+            .unwrap();
+        let page = library.search_playlists("someone", 0, 10).unwrap();
+        assert_eq!(page.items[0].name, "华语经典");
+    }
+
+    #[test]
+    fn test_plays() {
+        let mut library = library();
+        let yesterday = library.song(2).unwrap().unwrap();
+        assert_eq!(yesterday.name, "Yesterday");
+        assert_eq!(library.song(100).unwrap(), None);
+
+        library.add_play(&yesterday, 2000, 120000).unwrap();
+        library
+            .add_play(
+                &song(5, "Let It Be", "The Beatles", "Let It Be"),
+                1000,
+                60000,
+            )
+            .unwrap();
+        let plays = library.plays(0).unwrap();
+        assert_eq!(
+            plays.iter().map(|play| play.song.id).collect::<Vec<_>>(),
+            vec![5, 2]
+        );
+        assert_eq!(plays[1].listened, 120000);
+        assert_eq!(library.plays(1500).unwrap().len(), 1);
+        assert!(library.song(5).unwrap().is_some());
+    }
+}
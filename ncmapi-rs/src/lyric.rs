@@ -0,0 +1,613 @@
+//! Parse the lyrics returned by the lyric api into timelines.
+
+use serde::Deserialize;
+
+/// The max difference in milliseconds between an original line and its translation.
+/// The timestamps of the translation are sometimes rounded differently.
+const ALIGN_TOLERANCE: u64 = 100;
+
+/// A timed line of a lyric, `time` is in milliseconds.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub time: u64,
+    pub text: String,
+}
+
+/// A parsed LRC lyric.
+///
+/// `lines` are sorted by time with the `[offset:]` applied, the credits NetEase
+/// prepends as JSON lines are included as ordinary lines.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Lrc {
+    pub metadata: Vec<(String, String)>,
+    pub offset: i64,
+    pub lines: Vec<LyricLine>,
+}
+
+#[derive(Deserialize)]
+struct JsonLine {
+    t: u64,
+    c: Vec<JsonLineText>,
+}
+
+#[derive(Deserialize)]
+struct JsonLineText {
+    tx: String,
+}
+
+impl Lrc {
+    pub fn parse(text: &str) -> Self {
+        let mut lrc = Lrc::default();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('{') {
+                if let Ok(json) = serde_json::from_str::<JsonLine>(line) {
+                    lrc.lines.push(LyricLine {
+                        time: json.t,
+                        text: json.c.into_iter().map(|c| c.tx).collect(),
+                    });
+                }
+                continue;
+            }
+
+            let (times, text) = parse_timestamps(line);
+            if times.is_empty() {
+                if let Some((key, value)) = parse_tag(line) {
+                    if key == "offset" {
+                        lrc.offset = value.trim_start_matches('+').parse().unwrap_or_default();
+                    } else {
+                        lrc.metadata.push((key, value));
+                    }
+                }
+                continue;
+            }
+
+            for time in times {
+                lrc.lines.push(LyricLine {
+                    time,
+                    text: text.trim().to_owned(),
+                });
+            }
+        }
+
+        // a positive offset makes the lyric appear sooner
+        for line in lrc.lines.iter_mut() {
+            line.time = (line.time as i64 - lrc.offset).max(0) as u64;
+        }
+        // the sort is stable, so the credits stay before the lines at the same time
+        lrc.lines.sort_by_key(|line| line.time);
+        lrc
+    }
+
+    /// Get the text of the line at TIME, if it's within the tolerance.
+    fn text_near(&self, time: u64) -> Option<&str> {
+        let index = self.lines.partition_point(|line| line.time < time);
+        [index.checked_sub(1), Some(index)]
+            .iter()
+            .flatten()
+            .filter_map(|index| self.lines.get(*index))
+            .filter(|line| line.time.abs_diff(time) <= ALIGN_TOLERANCE)
+            .min_by_key(|line| line.time.abs_diff(time))
+            .map(|line| line.text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// Split the leading timestamps off LINE, return the times in milliseconds and the rest.
+fn parse_timestamps(line: &str) -> (Vec<u64>, &str) {
+    let mut times = Vec::new();
+    let mut rest = line;
+    while let Some(tag) = rest.strip_prefix('[') {
+        let end = match tag.find(']') {
+            Some(end) => end,
+            None => break,
+        };
+        match parse_time(&tag[..end]) {
+            Some(time) => times.push(time),
+            None => break,
+        }
+        rest = &tag[end + 1..];
+    }
+    (times, rest)
+}
+
+/// Parse the timestamp like mm:ss, mm:ss.xx, mm:ss.xxx or mm:ss:xx into milliseconds.
+fn parse_time(time: &str) -> Option<u64> {
+    let (minutes, rest) = time.split_once(':')?;
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let (seconds, fraction) = match rest.find(['.', ':']) {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => (rest, ""),
+    };
+    let seconds = seconds.trim().parse::<u64>().ok()?;
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction[..3].parse::<u64>().ok()?,
+    };
+    Some((minutes * 60 + seconds) * 1000 + millis)
+}
+
+/// Parse the metadata tag like [ar:artist].
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let tag = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = tag.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((key.to_lowercase(), value.trim().to_owned()))
+}
+
+/// A line of the lyric with its translation and romanization.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LyricEntry {
+    pub time: u64,
+    pub original: String,
+    pub translation: Option<String>,
+    pub romaji: Option<String>,
+}
+
+/// The lyric timeline aligned with the translation and romanization.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Lyrics {
+    pub metadata: Vec<(String, String)>,
+    pub entries: Vec<LyricEntry>,
+}
+
+impl Lyrics {
+    /// Align the lines of TRANSLATION and ROMAJI to ORIGINAL by their timestamps.
+    pub fn new(original: &Lrc, translation: Option<&Lrc>, romaji: Option<&Lrc>) -> Self {
+        let entries = original
+            .lines
+            .iter()
+            .map(|line| LyricEntry {
+                time: line.time,
+                original: line.text.to_owned(),
+                translation: translation
+                    .and_then(|lrc| lrc.text_near(line.time))
+                    .map(str::to_owned),
+                romaji: romaji
+                    .and_then(|lrc| lrc.text_near(line.time))
+                    .map(str::to_owned),
+            })
+            .collect();
+
+        Self {
+            metadata: original.metadata.to_owned(),
+            entries,
+        }
+    }
+
+    /// Parse the lyric texts, empty texts are ignored.
+    pub fn parse(original: &str, translation: &str, romaji: &str) -> Self {
+        let parse = |text: &str| Some(Lrc::parse(text)).filter(|lrc| !lrc.lines.is_empty());
+        Self::new(
+            &Lrc::parse(original),
+            parse(translation).as_ref(),
+            parse(romaji).as_ref(),
+        )
+    }
+
+    /// Get the index of the entry being sung at TIME, `None` if it's before the first one.
+    pub fn line_at(&self, time: u64) -> Option<usize> {
+        self.entries
+            .partition_point(|entry| entry.time <= time)
+            .checked_sub(1)
+    }
+}
+
+/// A word of a word timed lyric, the times are in milliseconds.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LyricWord {
+    pub start: u64,
+    pub duration: u64,
+    pub text: String,
+}
+
+/// A line of a word timed lyric.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct WordLine {
+    pub start: u64,
+    pub duration: u64,
+    pub words: Vec<LyricWord>,
+}
+
+impl WordLine {
+    pub fn text(&self) -> String {
+        self.words.iter().map(|word| word.text.as_str()).collect()
+    }
+}
+
+/// A word timed lyric, parsed from the `yrc` or the legacy `klyric`.
+/// The credits in JSON lines are skipped since they have no word timings.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct WordLyric {
+    pub lines: Vec<WordLine>,
+}
+
+impl WordLyric {
+    /// Parse the yrc, each line is like `[start,duration](start,duration,0)word...`,
+    /// where the word times are absolute.
+    pub fn parse_yrc(text: &str) -> Self {
+        Self::parse(text, |_, times| Some((*times.first()?, *times.get(1)?)))
+    }
+
+    /// Parse the klyric, each line is like `[start,duration](0,duration)word...`,
+    /// where the words follow each other from the start of the line.
+    pub fn parse_klyric(text: &str) -> Self {
+        Self::parse(text, |next, times| Some((next, *times.get(1)?)))
+    }
+
+    /// Parse the lines with WORD_TIME, which gets the start and duration of a word from
+    /// the end of the previous word and the numbers in parentheses.
+    fn parse<F>(text: &str, word_time: F) -> Self
+    where
+        F: Fn(u64, &[u64]) -> Option<(u64, u64)>,
+    {
+        let mut lines = Vec::new();
+        for line in text.lines().map(str::trim) {
+            let (times, mut rest) = match split_times(line, '[', ']') {
+                Some(split) => split,
+                None => continue,
+            };
+            let (start, duration) = match times[..] {
+                [start, duration, ..] => (start, duration),
+                _ => continue,
+            };
+
+            let mut words = Vec::<LyricWord>::new();
+            let mut next = start;
+            while let Some((times, after)) = split_times(rest, '(', ')') {
+                let text_end = after.find('(').unwrap_or(after.len());
+                let text = &after[..text_end];
+                rest = &after[text_end..];
+
+                let (word_start, word_duration) = match word_time(next, &times) {
+                    Some(time) => time,
+                    None => continue,
+                };
+                next = word_start + word_duration;
+                words.push(LyricWord {
+                    start: word_start,
+                    duration: word_duration,
+                    text: text.to_owned(),
+                });
+            }
+
+            lines.push(WordLine {
+                start,
+                duration,
+                words,
+            });
+        }
+        lines.sort_by_key(|line| line.start);
+        Self { lines }
+    }
+
+    /// Get the indexes of the line and the word being sung at TIME.
+    /// The word is `None` if TIME is before its first word, and the result is `None`
+    /// if TIME isn't within any line.
+    pub fn word_at(&self, time: u64) -> Option<(usize, Option<usize>)> {
+        let line_index = self
+            .lines
+            .partition_point(|line| line.start <= time)
+            .checked_sub(1)?;
+        let line = &self.lines[line_index];
+        if time >= line.start + line.duration {
+            return None;
+        }
+
+        let word_index = line
+            .words
+            .partition_point(|word| word.start <= time)
+            .checked_sub(1);
+        Some((line_index, word_index))
+    }
+}
+
+/// Split the numbers in the leading OPEN and CLOSE off TEXT, like `[1,2]rest`.
+fn split_times(text: &str, open: char, close: char) -> Option<(Vec<u64>, &str)> {
+    let text = text.strip_prefix(open)?;
+    let end = text.find(close)?;
+    let times = text[..end]
+        .split(',')
+        .map(|time| time.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some((times, &text[end + close.len_utf8()..]))
+}
+
+/// How the translation is laid out when exporting the lyrics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum LyricLayout {
+    /// Only the original lines.
+    Original,
+    /// Only the translations, the original line is used if it has no translation.
+    Translation,
+    /// The translation below the original line.
+    #[default]
+    Stacked,
+    /// The translation after the original line, separated by " / ".
+    Inline,
+}
+
+/// How long the last line lasts when the duration of the song is unknown.
+const LAST_LINE_DURATION: u64 = 5000;
+
+impl Lyrics {
+    /// The lines of ENTRY shown in LAYOUT.
+    fn layout_lines(entry: &LyricEntry, layout: LyricLayout) -> Vec<String> {
+        let original = entry.original.to_owned();
+        match (layout, entry.translation.to_owned()) {
+            (LyricLayout::Original, _) | (_, None) => vec![original],
+            (LyricLayout::Translation, Some(translation)) => vec![translation],
+            (LyricLayout::Stacked, Some(translation)) => vec![original, translation],
+            (LyricLayout::Inline, Some(translation)) => {
+                vec![format!("{} / {}", original, translation)]
+            }
+        }
+    }
+
+    /// The lines shown in LAYOUT without timestamps.
+    pub fn to_text(&self, layout: LyricLayout) -> String {
+        self.entries
+            .iter()
+            .flat_map(|entry| Self::layout_lines(entry, layout))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The time and the text of each entry shown in LAYOUT.
+    pub fn timed_lines(&self, layout: LyricLayout) -> Vec<(u64, String)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.time, Self::layout_lines(entry, layout).join("\n")))
+            .collect()
+    }
+
+    /// Export as LRC. The original lines are written in the enhanced LRC format with
+    /// word timestamps if WORDS has a line starting at the same time.
+    /// The stacked translation is written as another line with the same timestamp.
+    pub fn to_lrc(&self, layout: LyricLayout, words: Option<&WordLyric>) -> String {
+        let mut lrc = String::new();
+        for (key, value) in self.metadata.iter() {
+            lrc.push_str(&format!("[{}:{}]\n", key, value));
+        }
+
+        for entry in self.entries.iter() {
+            let time = lrc_time(entry.time);
+            let word_line = words.and_then(|words| {
+                words
+                    .lines
+                    .iter()
+                    .find(|line| line.start.abs_diff(entry.time) <= ALIGN_TOLERANCE)
+                    .filter(|line| !line.words.is_empty())
+            });
+
+            for (index, line) in Self::layout_lines(entry, layout).iter().enumerate() {
+                let original = index == 0 && layout != LyricLayout::Translation;
+                match word_line {
+                    Some(word_line) if original && layout != LyricLayout::Inline => {
+                        lrc.push_str(&format!("[{}]", time));
+                        for word in word_line.words.iter() {
+                            lrc.push_str(&format!("<{}>{}", lrc_time(word.start), word.text));
+                        }
+                        lrc.push_str(&format!(
+                            "<{}>\n",
+                            lrc_time(word_line.start + word_line.duration)
+                        ));
+                    }
+                    _ => lrc.push_str(&format!("[{}]{}\n", time, line)),
+                }
+            }
+        }
+        lrc
+    }
+
+    /// The cues of the subtitles, the lines without text only end the previous cue.
+    /// DURATION is the length of the song in milliseconds.
+    fn cues(&self, layout: LyricLayout, duration: Option<u64>) -> Vec<(u64, u64, String)> {
+        let mut cues = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.original.is_empty() {
+                continue;
+            }
+            let end = match self.entries.get(index + 1) {
+                Some(next) => next.time,
+                None => duration
+                    .filter(|duration| *duration > entry.time)
+                    .unwrap_or(entry.time + LAST_LINE_DURATION),
+            };
+            if end <= entry.time {
+                continue;
+            }
+            cues.push((
+                entry.time,
+                end,
+                Self::layout_lines(entry, layout).join("\n"),
+            ));
+        }
+        cues
+    }
+
+    /// Export as SRT subtitles, DURATION is the length of the song in milliseconds.
+    pub fn to_srt(&self, layout: LyricLayout, duration: Option<u64>) -> String {
+        self.cues(layout, duration)
+            .iter()
+            .enumerate()
+            .map(|(index, (start, end, text))| {
+                format!(
+                    "{}\n{} --> {}\n{}\n\n",
+                    index + 1,
+                    subtitle_time(*start, ','),
+                    subtitle_time(*end, ','),
+                    text
+                )
+            })
+            .collect()
+    }
+
+    /// Export as WebVTT subtitles, DURATION is the length of the song in milliseconds.
+    pub fn to_vtt(&self, layout: LyricLayout, duration: Option<u64>) -> String {
+        let mut vtt = String::from("WEBVTT\n\n");
+        for (start, end, text) in self.cues(layout, duration).iter() {
+            vtt.push_str(&format!(
+                "{} --> {}\n{}\n\n",
+                subtitle_time(*start, '.'),
+                subtitle_time(*end, '.'),
+                text
+            ));
+        }
+        vtt
+    }
+}
+
+/// Format TIME in milliseconds like mm:ss.xx.
+fn lrc_time(time: u64) -> String {
+    format!(
+        "{:02}:{:02}.{:02}",
+        time / 60000,
+        time / 1000 % 60,
+        time % 1000 / 10
+    )
+}
+
+/// Format TIME in milliseconds like hh:mm:ss,mmm, SEPARATOR is the one before mmm.
+fn subtitle_time(time: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        time / 3600000,
+        time / 60000 % 60,
+        time / 1000 % 60,
+        separator,
+        time % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lrc, LyricLayout, Lyrics, WordLyric};
+
+    #[test]
+    fn test_parse_lrc() {
+        let lrc = Lrc::parse(concat!(
+            "{\"t\":0,\"c\":[{\"tx\":\"作词: \"},{\"tx\":\"someone\"}]}\n",
+            "[ti:title]\n",
+            "[offset:+500]\n",
+            "[00:12.50][01:02.500]chorus\n",
+            "[00:05.1]first\n",
+            "[00:08]\n",
+        ));
+
+        assert_eq!(lrc.metadata, vec![("ti".to_owned(), "title".to_owned())]);
+        assert_eq!(lrc.offset, 500);
+        let lines = lrc
+            .lines
+            .iter()
+            .map(|line| (line.time, line.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (0, "作词: someone"),
+                (4600, "first"),
+                (7500, ""),
+                (12000, "chorus"),
+                (62000, "chorus"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_time() {
+        let lrc = Lrc::parse("[00:01.12é]hello\n[00:02.1x5]bad\n[00:03.00]ok");
+        assert_eq!(
+            lrc.lines
+                .iter()
+                .map(|line| (line.time, line.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(3000, "ok")]
+        );
+    }
+
+    #[test]
+    fn test_align_and_line_at() {
+        let lyrics = Lyrics::parse(
+            "[00:01.00]one\n[00:02.00]two\n[00:03.00]three",
+            "[00:01.01]一\n[00:03.00]三",
+            "",
+        );
+
+        assert_eq!(lyrics.entries[0].translation.as_deref(), Some("一"));
+        assert_eq!(lyrics.entries[1].translation, None);
+        assert_eq!(lyrics.entries[2].translation.as_deref(), Some("三"));
+        assert_eq!(lyrics.entries[0].romaji, None);
+
+        assert_eq!(lyrics.line_at(500), None);
+        assert_eq!(lyrics.line_at(1000), Some(0));
+        assert_eq!(lyrics.line_at(2999), Some(1));
+        assert_eq!(lyrics.line_at(60000), Some(2));
+    }
+
+    #[test]
+    fn test_export() {
+        let lyrics = Lyrics::parse(
+            "[ti:song]\n[00:01.00]one\n[00:02.50]\n[01:03.00]two",
+            "[00:01.00]一",
+            "",
+        );
+
+        assert_eq!(
+            lyrics.to_lrc(LyricLayout::Stacked, None),
+            "[ti:song]\n[00:01.00]one\n[00:01.00]一\n[00:02.50]\n[01:03.00]two\n"
+        );
+        assert_eq!(
+            lyrics.to_srt(LyricLayout::Inline, Some(65000)),
+            concat!(
+                "1\n00:00:01,000 --> 00:00:02,500\none / 一\n\n",
+                "2\n00:01:03,000 --> 00:01:05,000\ntwo\n\n",
+            )
+        );
+        assert!(lyrics
+            .to_vtt(LyricLayout::Translation, None)
+            .ends_with("00:01:03.000 --> 00:01:08.000\ntwo\n\n"));
+
+        let words = WordLyric::parse_yrc("[1000,1000](1000,400,0)o(1400,600,0)ne");
+        assert_eq!(
+            lyrics
+                .to_lrc(LyricLayout::Original, Some(&words))
+                .lines()
+                .nth(1),
+            Some("[00:01.00]<00:01.00>o<00:01.40>ne<00:02.00>")
+        );
+    }
+
+    #[test]
+    fn test_parse_yrc() {
+        let yrc = WordLyric::parse_yrc(concat!(
+            "{\"t\":0,\"c\":[{\"tx\":\"作曲: \"}]}\n",
+            "[1000,1500](1000,500,0)Hel(1500,500,0)lo (2000,500,0)world\n",
+        ));
+
+        assert_eq!(yrc.lines.len(), 1);
+        assert_eq!(yrc.lines[0].text(), "Hello world");
+        assert_eq!(yrc.lines[0].words[2].start, 2000);
+        assert_eq!(yrc.word_at(999), None);
+        assert_eq!(yrc.word_at(1499), Some((0, Some(0))));
+        assert_eq!(yrc.word_at(2100), Some((0, Some(2))));
+        assert_eq!(yrc.word_at(2500), None);
+    }
+
+    #[test]
+    fn test_parse_klyric() {
+        let klyric = WordLyric::parse_klyric("[1000,900](0,300)a(0,600)b");
+
+        let words = &klyric.lines[0].words;
+        assert_eq!((words[0].start, words[0].duration), (1000, 300));
+        assert_eq!((words[1].start, words[1].duration), (1300, 600));
+        assert_eq!(klyric.word_at(1400), Some((0, Some(1))));
+    }
+}
@@ -0,0 +1,278 @@
+//! Score how likely a song is the track described by a title, artist, album and
+//! duration, used to match the tracks from other services.
+
+use crate::{playlist_file::PlaylistEntry, types::Song};
+
+/// The songs scoring lower than this aren't considered a match.
+pub const MATCH_THRESHOLD: f64 = 0.6;
+/// The matches scoring at least this are confident enough to be used without review.
+pub const HIGH_CONFIDENCE: f64 = 0.85;
+
+/// The durations differing at most this many milliseconds are considered the same.
+const DURATION_TOLERANCE: u64 = 3000;
+/// The duration score drops to 0 when the difference exceeds the tolerance by this much.
+const DURATION_FALLOFF: u64 = 12000;
+/// The bonus when the album is also the same.
+const ALBUM_BONUS: f64 = 0.05;
+
+const TITLE_WEIGHT: f64 = 0.5;
+const ARTIST_WEIGHT: f64 = 0.3;
+const DURATION_WEIGHT: f64 = 0.2;
+
+/// The separators between the artists, in lower case.
+const ARTIST_SEPARATORS: &[&str] = &[",", "&", "/", "、", ";", " feat.", " feat ", " ft.", " x "];
+
+/// The words of a version suffix, like "Song - Remastered 2009".
+const VERSION_KEYWORDS: &[&str] = &[
+    "remaster", "live", "version", "edit", "mix", "remix", "mono", "stereo", "acoustic", "demo",
+];
+
+/// Whether WORD is one of the version keywords, or one of them ending with "ed" or "s",
+/// like "remastered" and "demos".
+fn is_version_keyword(word: &str) -> bool {
+    VERSION_KEYWORDS.iter().any(|keyword| {
+        word.strip_prefix(keyword)
+            .is_some_and(|rest| ["", "ed", "s"].contains(&rest))
+    })
+}
+
+/// Normalize TEXT for comparing: the parts in brackets, like "(Live)" and "[Remastered]",
+/// the version suffixes after " - ", which have a version keyword as a whole word, and
+/// everything after "feat." are removed, then only
+/// the lowercase letters and digits are kept.
+pub fn normalize(text: &str) -> String {
+    let text = text.to_lowercase();
+    let mut text = [" feat.", " feat ", " ft."]
+        .iter()
+        .fold(text.as_str(), |text, feat| {
+            text.split(feat).next().unwrap_or(text)
+        })
+        .to_string();
+    if let Some((title, suffix)) = text.rsplit_once(" - ") {
+        if suffix
+            .split(|c: char| !c.is_alphanumeric())
+            .any(is_version_keyword)
+        {
+            text = title.to_string();
+        }
+    }
+
+    let mut normalized = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' | '[' | '（' | '【' => depth += 1,
+            ')' | ']' | '）' | '】' => depth = (depth - 1).max(0),
+            c if depth == 0 && c.is_alphanumeric() => normalized.push(c),
+            _ => (),
+        }
+    }
+    // keep the text if it's all in brackets
+    if normalized.is_empty() {
+        text.chars().filter(|c| c.is_alphanumeric()).collect()
+    } else {
+        normalized
+    }
+}
+
+/// The similarity between 0 and 1 of two normalized strings, by the edit distance.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let length = a.len().max(b.len());
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / length as f64
+}
+
+/// Split ARTIST into the normalized names of the artists.
+fn split_artists(artist: &str) -> Vec<String> {
+    let artist = artist.to_lowercase();
+    ARTIST_SEPARATORS
+        .iter()
+        .fold(vec![artist], |parts, separator| {
+            parts
+                .iter()
+                .flat_map(|part| part.split(separator))
+                .map(str::to_string)
+                .collect()
+        })
+        .iter()
+        .map(|name| normalize(name))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The average similarity of each artist in ARTIST to its most similar artist of SONG.
+fn artist_similarity(artist: &str, song: &Song) -> Option<f64> {
+    let artists = split_artists(artist);
+    if artists.is_empty() {
+        return None;
+    }
+    let song_artists = song
+        .artists
+        .iter()
+        .filter_map(|artist| artist.name.as_deref())
+        .map(normalize)
+        .collect::<Vec<_>>();
+
+    let total = artists
+        .iter()
+        .map(|artist| {
+            song_artists
+                .iter()
+                .map(|song_artist| similarity(artist, song_artist))
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>();
+    Some(total / artists.len() as f64)
+}
+
+fn duration_similarity(duration: u64, song: &Song) -> f64 {
+    let difference = (duration as i64 - song.duration as i64).unsigned_abs();
+    if difference <= DURATION_TOLERANCE {
+        1.0
+    } else {
+        (1.0 - (difference - DURATION_TOLERANCE) as f64 / DURATION_FALLOFF as f64).max(0.0)
+    }
+}
+
+/// Score between 0 and 1 how likely SONG is the track ENTRY.
+/// The title, artist and duration are weighted, the ones ENTRY doesn't have are left out,
+/// and the same album adds a bonus.
+pub fn match_score(entry: &PlaylistEntry, song: &Song) -> f64 {
+    let mut scores = vec![(
+        similarity(&normalize(&entry.title), &normalize(&song.name)),
+        TITLE_WEIGHT,
+    )];
+    if let Some(artist) = artist_similarity(&entry.artist, song) {
+        scores.push((artist, ARTIST_WEIGHT));
+    }
+    if let Some(duration) = entry.duration.filter(|_| song.duration > 0) {
+        scores.push((duration_similarity(duration, song), DURATION_WEIGHT));
+    }
+    let weights = scores.iter().map(|(_, weight)| weight).sum::<f64>();
+    let mut score = scores
+        .iter()
+        .map(|(score, weight)| score * weight)
+        .sum::<f64>()
+        / weights;
+
+    let same_album = match (&entry.album, &song.album.name) {
+        (Some(album), Some(song_album)) => normalize(album) == normalize(song_album),
+        _ => false,
+    };
+    if same_album {
+        score += ALBUM_BONUS;
+    }
+    score.min(1.0)
+}
+
+/// The index and the score of the best match of ENTRY in SONGS,
+/// `None` if none of them scores at least `MATCH_THRESHOLD`.
+pub fn best_match(entry: &PlaylistEntry, songs: &[Song]) -> Option<(usize, f64)> {
+    songs
+        .iter()
+        .map(|song| match_score(entry, song))
+        .enumerate()
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        .fold(
+            None,
+            |best: Option<(usize, f64)>, (index, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((index, score)),
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_match, match_score, normalize, similarity, HIGH_CONFIDENCE};
+    use crate::{
+        playlist_file::PlaylistEntry,
+        types::{Album, Artist, Song},
+    };
+
+    fn song(id: usize, name: &str, artists: &[&str], duration: usize) -> Song {
+        Song {
+            id,
+            name: name.to_string(),
+            artists: artists
+                .iter()
+                .map(|artist| Artist {
+                    id: 0,
+                    name: Some(artist.to_string()),
+                })
+                .collect(),
+            album: Album {
+                name: Some("Abbey Road".to_string()),
+                ..Default::default()
+            },
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Something (2019 Remaster)"), "something");
+        assert_eq!(normalize("Stay feat. Justin Bieber"), "stay");
+        assert_eq!(normalize("Help! - Remastered 2009"), "help");
+        assert_eq!(normalize("Run - Boy - Run"), "runboyrun");
+        assert_eq!(normalize("Jack - Oliver"), "jackoliver");
+        assert_eq!(normalize("Hey Jude - Remixed"), "heyjude");
+        assert_eq!(normalize("晴天【Live】"), "晴天");
+        assert_eq!(normalize("(Intro)"), "intro");
+        assert_eq!(similarity("abcd", "abce"), 0.75);
+    }
+
+    #[test]
+    fn test_match_score() {
+        let entry = PlaylistEntry {
+            title: "Come Together - Remastered".to_string(),
+            artist: "The Beatles".to_string(),
+            album: Some("Abbey Road".to_string()),
+            duration: Some(259000),
+            ..Default::default()
+        };
+        let exact = song(1, "Come Together", &["The Beatles"], 260000);
+        let cover = song(2, "Come Together", &["Aerosmith"], 230000);
+        let other = song(3, "Something", &["The Beatles"], 182000);
+
+        assert!(match_score(&entry, &exact) >= HIGH_CONFIDENCE);
+        assert!(match_score(&entry, &cover) < match_score(&entry, &exact));
+        assert_eq!(
+            best_match(&entry, &[other.to_owned(), cover, exact]),
+            Some((
+                2,
+                match_score(&entry, &song(1, "Come Together", &["The Beatles"], 260000))
+            ))
+        );
+        assert_eq!(best_match(&entry, &[other]), None);
+    }
+
+    #[test]
+    fn test_match_multiple_artists() {
+        let entry = PlaylistEntry {
+            title: "Stay".to_string(),
+            artist: "Justin Bieber & The Kid LAROI".to_string(),
+            ..Default::default()
+        };
+        let stay = song(1, "Stay", &["The Kid LAROI", "Justin Bieber"], 141000);
+        assert_eq!(match_score(&entry, &stay), 1.0);
+    }
+}
@@ -0,0 +1,290 @@
+use std::future::Future;
+
+use futures::stream::{self, Stream};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    types::{
+        Album, AlbumSublistResp, Artist, ArtistSongsResp, ArtistSublistResp, Comment,
+        HotCommentsResp, LyricSong, Playlist, PlaylistSubscribersResp, Podcast,
+        ResourceCommentsResp, SearchAlbumResp, SearchArtistResp, SearchLyricResp, SearchMVResp,
+        SearchPlaylistResp, SearchPodcastResp, SearchSongResp, SearchUserResp, SearchVideoResp,
+        Song, TopPlaylistResp, UserPlaylistResp, UserProfile, Video, MV,
+    },
+    TResult,
+};
+
+/// A page of items returned by a paginated api.
+///
+/// `total` is `None` when the api doesn't tell how many items there are.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub offset: usize,
+    pub limit: usize,
+    pub total: Option<usize>,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// `more` is the flag returned by the api, it's combined with `total`
+    /// since some apis only return one of them.
+    pub fn new(
+        items: Vec<T>,
+        offset: usize,
+        limit: usize,
+        total: Option<usize>,
+        more: bool,
+    ) -> Self {
+        let has_more = more || total.is_some_and(|total| offset + items.len() < total);
+        Self {
+            items,
+            offset,
+            limit,
+            total,
+            has_more,
+        }
+    }
+
+    /// The offset of the next page, `None` if this is the last one.
+    pub fn next_offset(&self) -> Option<usize> {
+        if self.has_more && !self.items.is_empty() {
+            Some(self.offset + self.limit)
+        } else {
+            None
+        }
+    }
+}
+
+/// A page of items returned by an api paginated by a cursor instead of an offset.
+///
+/// `cursor` is passed to the api to get the next page.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub cursor: Option<u64>,
+    pub has_more: bool,
+}
+
+impl<T> CursorPage<T> {
+    pub fn new(items: Vec<T>, cursor: Option<u64>, more: bool) -> Self {
+        let has_more = more && cursor.is_some() && !items.is_empty();
+        Self {
+            items,
+            cursor,
+            has_more,
+        }
+    }
+
+    /// The cursor of the next page, `None` if this is the last one.
+    pub fn next_cursor(&self) -> Option<u64> {
+        if self.has_more {
+            self.cursor
+        } else {
+            None
+        }
+    }
+}
+
+/// Responses which can be turned into a `Page`.
+pub trait Paginated: DeserializeOwned {
+    type Item;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Self::Item>;
+}
+
+/// Walk all pages, starting at offset 0 with `limit` items per page.
+/// The stream stops after the last page or the first error, a zero `limit` is an error.
+pub(crate) fn paginate<'a, T, F, Fut>(
+    limit: usize,
+    fetch: F,
+) -> impl Stream<Item = TResult<Page<T>>> + 'a
+where
+    T: 'a,
+    F: Fn(usize, usize) -> Fut + 'a,
+    Fut: Future<Output = TResult<Page<T>>> + 'a,
+{
+    stream::unfold((Some(0), fetch), move |(offset, fetch)| async move {
+        let offset = offset?;
+        if limit == 0 {
+            return Some((
+                Err("the page limit should be positive".into()),
+                (None, fetch),
+            ));
+        }
+        let page = fetch(offset, limit).await;
+        let next = match page {
+            Ok(ref page) => page.next_offset(),
+            Err(_) => None,
+        };
+
+        Some((page, (next, fetch)))
+    })
+}
+
+/// Walk all pages of a cursor paginated api, starting without a cursor.
+/// The stream stops after the last page or the first error.
+pub(crate) fn paginate_cursor<'a, T, F, Fut>(
+    fetch: F,
+) -> impl Stream<Item = TResult<CursorPage<T>>> + 'a
+where
+    T: 'a,
+    F: Fn(Option<u64>) -> Fut + 'a,
+    Fut: Future<Output = TResult<CursorPage<T>>> + 'a,
+{
+    stream::unfold((Some(None), fetch), move |(cursor, fetch)| async move {
+        let cursor = cursor?;
+        let page = fetch(cursor).await;
+        let next = match page {
+            Ok(ref page) => page.next_cursor().map(Some),
+            Err(_) => None,
+        };
+
+        Some((page, (next, fetch)))
+    })
+}
+
+macro_rules! search_paginated {
+    ($resp:ty, $item:ty, $items:ident, $count:ident) => {
+        impl Paginated for $resp {
+            type Item = $item;
+
+            fn into_page(self, offset: usize, limit: usize) -> Page<$item> {
+                let result = self.result.unwrap_or_default();
+                Page::new(result.$items, offset, limit, Some(result.$count), false)
+            }
+        }
+    };
+}
+
+search_paginated!(SearchSongResp, Song, songs, song_count);
+search_paginated!(SearchAlbumResp, Album, albums, album_count);
+search_paginated!(SearchArtistResp, Artist, artists, artist_count);
+search_paginated!(SearchPlaylistResp, Playlist, playlists, playlist_count);
+search_paginated!(SearchUserResp, UserProfile, userprofiles, userprofile_count);
+search_paginated!(SearchMVResp, MV, mvs, mv_count);
+search_paginated!(SearchLyricResp, LyricSong, songs, song_count);
+search_paginated!(SearchPodcastResp, Podcast, dj_radios, dj_radios_count);
+search_paginated!(SearchVideoResp, Video, videos, video_count);
+
+impl Paginated for UserPlaylistResp {
+    type Item = Playlist;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Playlist> {
+        Page::new(self.playlist, offset, limit, None, self.more)
+    }
+}
+
+impl Paginated for PlaylistSubscribersResp {
+    type Item = UserProfile;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<UserProfile> {
+        Page::new(self.subscribers, offset, limit, Some(self.total), self.more)
+    }
+}
+
+impl Paginated for TopPlaylistResp {
+    type Item = Playlist;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Playlist> {
+        Page::new(self.playlists, offset, limit, Some(self.total), self.more)
+    }
+}
+
+impl Paginated for HotCommentsResp {
+    type Item = Comment;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Comment> {
+        Page::new(
+            self.hot_comments,
+            offset,
+            limit,
+            Some(self.total),
+            self.has_more,
+        )
+    }
+}
+
+impl Paginated for ArtistSongsResp {
+    type Item = Song;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Song> {
+        Page::new(self.songs, offset, limit, Some(self.total), self.more)
+    }
+}
+
+impl Paginated for AlbumSublistResp {
+    type Item = Album;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Album> {
+        Page::new(self.data, offset, limit, Some(self.count), self.has_more)
+    }
+}
+
+impl Paginated for ArtistSublistResp {
+    type Item = Artist;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Artist> {
+        Page::new(self.data, offset, limit, None, self.has_more)
+    }
+}
+
+impl Paginated for ResourceCommentsResp {
+    type Item = Comment;
+
+    fn into_page(self, offset: usize, limit: usize) -> Page<Comment> {
+        let data = self.data;
+        Page::new(
+            data.comments,
+            offset,
+            limit,
+            Some(data.total_count),
+            data.has_more,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, StreamExt};
+
+    use super::{paginate, CursorPage, Page};
+
+    #[test]
+    fn test_has_more_from_total() {
+        let page = Page::new(vec![1, 2], 0, 2, Some(3), false);
+        assert!(page.has_more);
+        assert_eq!(page.next_offset(), Some(2));
+
+        let page = Page::new(vec![3], 2, 2, Some(3), false);
+        assert!(!page.has_more);
+        assert_eq!(page.next_offset(), None);
+    }
+
+    #[test]
+    fn test_empty_page_is_last() {
+        let page: Page<usize> = Page::new(vec![], 30, 30, None, true);
+        assert_eq!(page.next_offset(), None);
+    }
+
+    #[test]
+    fn test_cursor_page() {
+        let page = CursorPage::new(vec![1, 2], Some(1650000000000), true);
+        assert_eq!(page.next_cursor(), Some(1650000000000));
+
+        let page = CursorPage::new(vec![1, 2], Some(1650000000000), false);
+        assert_eq!(page.next_cursor(), None);
+    }
+
+    #[test]
+    fn test_paginate_zero_limit() {
+        let pages = block_on(
+            paginate(0, |offset, limit| async move {
+                Ok(Page::new(vec![offset], offset, limit, None, true))
+            })
+            .collect::<Vec<_>>(),
+        );
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! Read and write playlists in the portable playlist formats.
+
+use regex::Regex;
+
+use crate::types::{PlaylistDetail, Song};
+
+/// A track read from a playlist file, `duration` is in milliseconds.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration: Option<u64>,
+    pub location: Option<String>,
+}
+
+/// Split "Artist - Title" into the artist and the title.
+fn split_artist_title(text: &str) -> (String, String) {
+    match text.split_once(" - ") {
+        Some((artist, title)) => (artist.trim().to_string(), title.trim().to_string()),
+        None => (String::new(), text.trim().to_string()),
+    }
+}
+
+/// Parse a duration like "3:35", "215" in seconds or "215000" in milliseconds.
+fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.contains(':') {
+        return text
+            .split(':')
+            .try_fold(0, |total, part| {
+                Some(total * 60 + part.trim().parse::<u64>().ok()?)
+            })
+            .map(|seconds| seconds * 1000);
+    }
+    let value = text.parse::<f64>().ok().filter(|value| *value > 0.0)?;
+    // no track lasts ten thousand seconds, so a larger value is in milliseconds
+    Some(if value >= 10000.0 {
+        value
+    } else {
+        value * 1000.0
+    } as u64)
+}
+
+/// Parse a M3U or extended M3U playlist. The tracks without `#EXTINF` get their artist
+/// and title from their file names, like "Artist - Title.mp3".
+pub fn parse_m3u(text: &str) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut info: Option<PlaylistEntry> = None;
+    for line in text.trim_start_matches('\u{feff}').lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            let (duration, display) = extinf.split_once(',').unwrap_or((extinf, ""));
+            // the duration may be followed by attributes, and -1 means unknown
+            let duration = duration
+                .split_whitespace()
+                .next()
+                .and_then(|duration| duration.parse::<i64>().ok())
+                .filter(|duration| *duration > 0)
+                .map(|duration| duration as u64 * 1000);
+            let (artist, title) = split_artist_title(display);
+            info = Some(PlaylistEntry {
+                title,
+                artist,
+                duration,
+                ..Default::default()
+            });
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let mut entry = info.take().unwrap_or_else(|| {
+                let name = line.rsplit(['/', '\\']).next().unwrap_or(line);
+                let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+                let (artist, title) = split_artist_title(stem);
+                PlaylistEntry {
+                    title,
+                    artist,
+                    ..Default::default()
+                }
+            });
+            entry.location = Some(line.to_string());
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse a XSPF playlist.
+pub fn parse_xspf(text: &str) -> Vec<PlaylistEntry> {
+    let track = Regex::new(r"(?s)<track>(.*?)</track>").unwrap();
+    let field = |track: &str, name: &str| {
+        Regex::new(&format!(r"(?s)<{0}>(.*?)</{0}>", name))
+            .unwrap()
+            .captures(track)
+            .map(|captures| unescape_xml(captures[1].trim()))
+            .filter(|value| !value.is_empty())
+    };
+
+    track
+        .captures_iter(text)
+        .map(|captures| {
+            let track = &captures[1];
+            PlaylistEntry {
+                title: field(track, "title").unwrap_or_default(),
+                artist: field(track, "creator").unwrap_or_default(),
+                album: field(track, "album"),
+                // the duration of XSPF is always in milliseconds
+                duration: field(track, "duration").and_then(|duration| duration.parse().ok()),
+                location: field(track, "location"),
+            }
+        })
+        .collect()
+}
+
+/// Split a CSV line into fields, the fields can be quoted with double quotes.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+        .into_iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// Parse a CSV playlist. If the first line is a header naming the artist, title, album
+/// and duration columns, they can be in any order, otherwise they're in that order.
+pub fn parse_csv(text: &str) -> Vec<PlaylistEntry> {
+    let mut lines = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+    let mut columns = ["artist", "title", "album", "duration"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect::<Vec<_>>();
+    if let Some(header) = lines.peek() {
+        let header = split_csv_line(header)
+            .iter()
+            .map(|column| column.to_lowercase())
+            .collect::<Vec<_>>();
+        if header.iter().any(|column| column == "title") {
+            columns = header;
+            lines.next();
+        }
+    }
+
+    lines
+        .map(|line| {
+            let mut entry = PlaylistEntry::default();
+            for (column, value) in columns.iter().zip(split_csv_line(line)) {
+                match column.as_str() {
+                    "artist" => entry.artist = value,
+                    "title" => entry.title = value,
+                    "album" => entry.album = Some(value).filter(|album| !album.is_empty()),
+                    "duration" => entry.duration = parse_duration(&value),
+                    _ => (),
+                }
+            }
+            entry
+        })
+        .filter(|entry| !entry.title.is_empty())
+        .collect()
+}
+
+/// The web page of the song whose id is `id`.
+fn song_page(id: usize) -> String {
+    format!("https://music.163.com/song?id={}", id)
+}
+
+/// The web page of the playlist whose id is `id`.
+fn playlist_page(id: usize) -> String {
+    format!("https://music.163.com/playlist?id={}", id)
+}
+
+fn artist_names(song: &Song) -> String {
+    song.artists
+        .iter()
+        .filter_map(|artist| artist.name.to_owned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Export as extended M3U8. `locations` are the file paths or urls of `playlist.tracks`,
+/// the tracks without a location are left out since M3U can't hold them.
+pub fn to_m3u8(playlist: &PlaylistDetail, locations: &[Option<String>]) -> String {
+    let mut m3u = format!("#EXTM3U\n#PLAYLIST:{}\n", playlist.name);
+    for (song, location) in playlist.tracks.iter().zip(locations.iter()) {
+        if let Some(location) = location {
+            m3u.push_str(&format!(
+                "#EXTINF:{},{} - {}\n{}\n",
+                song.duration / 1000,
+                artist_names(song),
+                song.name,
+                location
+            ));
+        }
+    }
+    m3u
+}
+
+/// Export as XSPF with the metadata of the playlist and the tracks.
+/// `locations` are the same as `to_m3u8`, but the tracks without a location are kept
+/// with their identifiers, so that they can be matched on other services.
+pub fn to_xspf(playlist: &PlaylistDetail, locations: &[Option<String>]) -> String {
+    let mut xspf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    let element = |indent: usize, name: &str, value: &str| {
+        format!(
+            "{}<{}>{}</{}>\n",
+            "  ".repeat(indent),
+            name,
+            escape_xml(value),
+            name
+        )
+    };
+
+    xspf.push_str(&element(1, "title", &playlist.name));
+    if let Some(ref creator) = playlist.creator {
+        xspf.push_str(&element(1, "creator", &creator.nickname));
+    }
+    if let Some(description) = playlist.description.as_deref().filter(|d| !d.is_empty()) {
+        xspf.push_str(&element(1, "annotation", description));
+    }
+    xspf.push_str(&element(1, "info", &playlist_page(playlist.id)));
+    if !playlist.cover_img_url.is_empty() {
+        xspf.push_str(&element(1, "image", &playlist.cover_img_url));
+    }
+
+    xspf.push_str("  <trackList>\n");
+    for (index, song) in playlist.tracks.iter().enumerate() {
+        xspf.push_str("    <track>\n");
+        if let Some(Some(location)) = locations.get(index) {
+            xspf.push_str(&element(3, "location", location));
+        }
+        xspf.push_str(&element(3, "identifier", &song_page(song.id)));
+        xspf.push_str(&element(3, "title", &song.name));
+        xspf.push_str(&element(3, "creator", &artist_names(song)));
+        if let Some(ref album) = song.album.name {
+            xspf.push_str(&element(3, "album", album));
+        }
+        if song.no > 0 {
+            xspf.push_str(&element(3, "trackNum", &song.no.to_string()));
+        }
+        xspf.push_str(&element(3, "duration", &song.duration.to_string()));
+        if !song.album.pic_url.is_empty() {
+            xspf.push_str(&element(3, "image", &song.album.pic_url));
+        }
+        xspf.push_str("    </track>\n");
+    }
+    xspf.push_str("  </trackList>\n</playlist>\n");
+    xspf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_csv, parse_m3u, parse_xspf, to_m3u8, to_xspf, PlaylistEntry};
+    use crate::types::{Album, Artist, PlaylistDetail, Song};
+
+    fn playlist() -> PlaylistDetail {
+        let song = |id: usize, name: &str| Song {
+            id,
+            name: name.to_string(),
+            artists: vec![
+                Artist {
+                    id: 1,
+                    name: Some("A".to_string()),
+                },
+                Artist {
+                    id: 2,
+                    name: Some("B".to_string()),
+                },
+            ],
+            album: Album {
+                name: Some("Album".to_string()),
+                ..Default::default()
+            },
+            duration: 215000,
+            ..Default::default()
+        };
+        PlaylistDetail {
+            id: 10,
+            name: "Rock & Roll".to_string(),
+            tracks: vec![song(1, "One"), song(2, "<Two>")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_m3u8() {
+        let m3u = to_m3u8(&playlist(), &[Some("/music/one.mp3".to_string()), None]);
+        assert_eq!(
+            m3u,
+            "#EXTM3U\n#PLAYLIST:Rock & Roll\n#EXTINF:215,A, B - One\n/music/one.mp3\n"
+        );
+    }
+
+    #[test]
+    fn test_xspf() {
+        let xspf = to_xspf(&playlist(), &[None, Some("http://a/2.mp3".to_string())]);
+        assert!(xspf.contains("  <title>Rock &amp; Roll</title>\n"));
+        assert!(xspf.contains("      <title>&lt;Two&gt;</title>\n"));
+        assert_eq!(xspf.matches("<location>").count(), 1);
+        assert_eq!(xspf.matches("<duration>215000</duration>").count(), 2);
+
+        let entries = parse_xspf(&xspf);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].title, "<Two>");
+        assert_eq!(entries[1].artist, "A, B");
+        assert_eq!(entries[1].duration, Some(215000));
+        assert_eq!(entries[1].location.as_deref(), Some("http://a/2.mp3"));
+    }
+
+    #[test]
+    fn test_parse_m3u() {
+        let entries = parse_m3u(
+            "#EXTM3U\n#EXTINF:215,A, B - One\n/music/one.mp3\n\nD:\\music\\C - Three.flac\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                PlaylistEntry {
+                    title: "One".to_string(),
+                    artist: "A, B".to_string(),
+                    duration: Some(215000),
+                    location: Some("/music/one.mp3".to_string()),
+                    ..Default::default()
+                },
+                PlaylistEntry {
+                    title: "Three".to_string(),
+                    artist: "C".to_string(),
+                    location: Some("D:\\music\\C - Three.flac".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let entries = parse_csv("Title,Artist,Duration\n\"Hello, World\",\"A \"\"B\"\"\",3:35\n");
+        assert_eq!(entries[0].title, "Hello, World");
+        assert_eq!(entries[0].artist, "A \"B\"");
+        assert_eq!(entries[0].duration, Some(215000));
+
+        let entries = parse_csv("A,One,Album,215\nB,Two,,215000\n");
+        assert_eq!(entries[0].album.as_deref(), Some("Album"));
+        assert_eq!(entries[1].album, None);
+        assert_eq!(entries[1].duration, Some(215000));
+    }
+}
@@ -0,0 +1,278 @@
+//! Compare, sync and merge the songs of a playlist, identified by their ids.
+//!
+//! The local playlists are text files with a song id on each line, everything after
+//! `#` is a comment, so that they're readable and diff well in version control.
+
+use std::collections::HashSet;
+
+use crate::types::Song;
+
+/// Parse the song ids of a local playlist file, the duplicates are dropped.
+pub fn parse_id_list(text: &str) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    text.lines()
+        .filter_map(|line| line.split('#').next()?.trim().parse::<usize>().ok())
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+/// Write a local playlist file of SONGS, with their titles and artists as comments.
+pub fn to_id_list(name: &str, songs: &[Song]) -> String {
+    let mut text = format!("# {}\n", name);
+    for song in songs.iter() {
+        let artists = song
+            .artists
+            .iter()
+            .filter_map(|artist| artist.name.to_owned())
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push_str(&format!("{} # {} - {}\n", song.id, song.name, artists));
+    }
+    text
+}
+
+/// The changes turning a playlist into the target one.
+///
+/// NetEase puts the added songs at the top of a playlist, so the order is only
+/// updated when the target isn't the added songs followed by the kept ones.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SyncPlan {
+    pub add: Vec<usize>,
+    pub remove: Vec<usize>,
+    pub order: Option<Vec<usize>>,
+}
+
+impl SyncPlan {
+    /// Plan the changes from CURRENT to TARGET.
+    pub fn new(current: &[usize], target: &[usize]) -> Self {
+        let current_set = current.iter().collect::<HashSet<_>>();
+        let target_set = target.iter().collect::<HashSet<_>>();
+        let add = target
+            .iter()
+            .filter(|id| !current_set.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        let remove = current
+            .iter()
+            .filter(|id| !target_set.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let expected = add
+            .iter()
+            .chain(current.iter().filter(|id| target_set.contains(id)))
+            .copied()
+            .collect::<Vec<_>>();
+        let order = Some(target.to_vec()).filter(|target| *target != expected);
+        Self { add, remove, order }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty() && self.order.is_none()
+    }
+
+    /// The songs to add in batches of at most SIZE, in the order to send them.
+    /// Every batch goes to the top, so the last one is sent first to keep the added songs
+    /// in the target order.
+    pub fn add_batches(&self, size: usize) -> impl Iterator<Item = &[usize]> {
+        self.add.chunks(size).rev()
+    }
+}
+
+/// The result of a three-way merge, `conflicts` are the songs whose positions were
+/// changed differently on both sides, the local order is used for them.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    pub merged: Vec<usize>,
+    pub conflicts: Vec<usize>,
+}
+
+/// The lengths of the longest common subsequences of A and every prefix of B.
+fn lcs_lengths(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut lengths = vec![0; b.len() + 1];
+    for x in a.iter() {
+        // the length of the previous row at j
+        let mut diagonal = 0;
+        for (j, y) in b.iter().enumerate() {
+            let above = lengths[j + 1];
+            lengths[j + 1] = if x == y {
+                diagonal + 1
+            } else {
+                above.max(lengths[j])
+            };
+            diagonal = above;
+        }
+    }
+    lengths
+}
+
+/// The longest common subsequence of A and B, by Hirschberg's algorithm, which only
+/// needs linear space since the playlists can have tens of thousands of songs.
+fn longest_common_subsequence(a: &[usize], b: &[usize]) -> Vec<usize> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    if a.len() == 1 {
+        return if b.contains(&a[0]) {
+            vec![a[0]]
+        } else {
+            Vec::new()
+        };
+    }
+
+    // split B where the halves of A have the longest common subsequences
+    let (top, bottom) = a.split_at(a.len() / 2);
+    let forward = lcs_lengths(top, b);
+    let reversed = |list: &[usize]| list.iter().rev().copied().collect::<Vec<_>>();
+    let backward = lcs_lengths(&reversed(bottom), &reversed(b));
+    let split = (0..=b.len())
+        .max_by_key(|j| forward[*j] + backward[b.len() - j])
+        .unwrap_or_default();
+
+    let mut common = longest_common_subsequence(top, &b[..split]);
+    common.extend(longest_common_subsequence(bottom, &b[split..]));
+    common
+}
+
+/// Merge the LOCAL and REMOTE changes since BASE, the last synced songs.
+/// A song is kept if neither side removed it, and added if either side added it.
+/// The order changed by only one side is used, otherwise the local order is used and
+/// the songs ordered differently on both sides are reported as conflicts.
+pub fn merge(base: &[usize], local: &[usize], remote: &[usize]) -> MergeResult {
+    let base_set = base.iter().collect::<HashSet<_>>();
+    let local_set = local.iter().collect::<HashSet<_>>();
+    let remote_set = remote.iter().collect::<HashSet<_>>();
+    let keep = |id: &usize| {
+        if base_set.contains(id) {
+            local_set.contains(id) && remote_set.contains(id)
+        } else {
+            true
+        }
+    };
+
+    // the relative order of the songs on all the sides
+    let common = |list: &[usize]| {
+        list.iter()
+            .filter(|id| base_set.contains(id) && local_set.contains(id) && remote_set.contains(id))
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    let (base_common, local_common, remote_common) = (common(base), common(local), common(remote));
+    let local_moved = local_common != base_common;
+    let remote_moved = remote_common != base_common;
+
+    let (primary, secondary) = if remote_moved && !local_moved {
+        (remote, local)
+    } else {
+        (local, remote)
+    };
+    let conflicts = if local_moved && remote_moved && local_common != remote_common {
+        let kept = longest_common_subsequence(&local_common, &remote_common)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        local_common
+            .iter()
+            .filter(|id| !kept.contains(id))
+            .copied()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut merged = primary
+        .iter()
+        .filter(|id| keep(id))
+        .copied()
+        .collect::<Vec<_>>();
+    // put the songs only on the other side after their predecessors there
+    for (index, id) in secondary.iter().enumerate() {
+        if !keep(id) || merged.contains(id) {
+            continue;
+        }
+        let position = secondary[..index]
+            .iter()
+            .rev()
+            .find_map(|previous| merged.iter().position(|merged| merged == previous))
+            .map_or(0, |position| position + 1);
+        merged.insert(position, *id);
+    }
+
+    MergeResult { merged, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{longest_common_subsequence, merge, parse_id_list, SyncPlan};
+
+    #[test]
+    fn test_parse_id_list() {
+        assert_eq!(
+            parse_id_list("# Road trip\n1 # One - A\n\n2\n1 # duplicate\nnot a song\n"),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_sync_plan() {
+        assert!(SyncPlan::new(&[1, 2, 3], &[1, 2, 3]).is_empty());
+
+        let plan = SyncPlan::new(&[1, 2, 3], &[4, 1, 3]);
+        assert_eq!(
+            (plan.add, plan.remove, plan.order),
+            (vec![4], vec![2], None)
+        );
+
+        let plan = SyncPlan::new(&[1, 2, 3], &[3, 1, 4]);
+        assert_eq!(plan.order, Some(vec![3, 1, 4]));
+    }
+
+    #[test]
+    fn test_add_batches() {
+        let current = vec![1, 2, 3];
+        let target = (1001..1251).chain([1, 3]).collect::<Vec<_>>();
+        let plan = SyncPlan::new(&current, &target);
+        assert_eq!(plan.order, None);
+
+        // every batch is added to the top like NetEase does
+        let mut playlist = current;
+        playlist.retain(|id| !plan.remove.contains(id));
+        for batch in plan.add_batches(100) {
+            playlist.splice(0..0, batch.iter().copied());
+        }
+        assert_eq!(playlist, target);
+    }
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        assert_eq!(
+            longest_common_subsequence(&[1, 2, 3, 4, 5, 6], &[2, 4, 1, 3, 5, 6]).len(),
+            4
+        );
+        assert_eq!(
+            longest_common_subsequence(&[1, 2, 3], &[1, 2, 3]),
+            vec![1, 2, 3]
+        );
+        assert!(longest_common_subsequence(&[1, 2, 3], &[4, 5]).is_empty());
+        assert_eq!(
+            longest_common_subsequence(&[7, 1, 8, 2, 9, 3], &[1, 2, 3, 7, 8, 9]).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        // local removes 2 and adds 5 at the end, remote adds 4 after 1
+        let result = merge(&[1, 2, 3], &[1, 3, 5], &[1, 4, 2, 3]);
+        assert_eq!(result.merged, vec![1, 4, 3, 5]);
+        assert!(result.conflicts.is_empty());
+
+        // only remote reorders, the local addition stays after 3
+        let result = merge(&[1, 2, 3], &[1, 2, 3, 4], &[3, 2, 1]);
+        assert_eq!(result.merged, vec![3, 4, 2, 1]);
+
+        // both reorder differently
+        let result = merge(&[1, 2, 3, 4], &[2, 1, 3, 4], &[1, 2, 4, 3]);
+        assert_eq!(result.merged, vec![2, 1, 3, 4]);
+        assert_eq!(result.conflicts.len(), 2);
+    }
+}
@@ -0,0 +1,300 @@
+//! Smart playlists, whose songs are chosen from some sources by a rule.
+//!
+//! The definitions are kept in a JSON file, so the playlists can be refreshed later.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::{date::DAY_MILLISECONDS, matching::normalize, types::Song, TResult};
+
+/// An artist or album, by its id or its name.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Matcher {
+    Id(usize),
+    Name(String),
+}
+
+impl Matcher {
+    fn matches(&self, id: usize, name: Option<&str>) -> bool {
+        match self {
+            Matcher::Id(matcher) => *matcher == id,
+            Matcher::Name(matcher) => {
+                name.is_some_and(|name| normalize(name) == normalize(matcher))
+            }
+        }
+    }
+}
+
+/// Where the songs of a smart playlist come from.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    Playlist(usize),
+    /// The liked songs playlist.
+    Liked,
+    /// The songs played most by the user.
+    Played,
+}
+
+/// The conditions on the songs, the durations are in seconds.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+    Artist(Vec<Matcher>),
+    /// By an artist the user subscribes to.
+    FollowedArtist,
+    Album(Vec<Matcher>),
+    Duration(Option<u64>, Option<u64>),
+    Liked,
+    PlayCount(Option<usize>, Option<usize>),
+    /// In one of the playlists.
+    Playlist(Vec<usize>),
+    /// Added to its source playlist in this many days.
+    AddedWithin(u64),
+}
+
+/// What the rules need to know besides the songs.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Facts {
+    pub liked: HashSet<usize>,
+    pub followed_artists: HashSet<usize>,
+    pub play_counts: HashMap<usize, usize>,
+    /// The songs of the playlists in the rules.
+    pub playlists: HashMap<usize, HashSet<usize>>,
+    /// The current time in milliseconds.
+    pub now: u64,
+}
+
+/// A song from a source, `added` is when it was added to the source playlist.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub song: Song,
+    pub added: Option<u64>,
+}
+
+impl Rule {
+    pub fn matches(&self, candidate: &Candidate, facts: &Facts) -> bool {
+        let song = &candidate.song;
+        let within = |value: u64, min: &Option<u64>, max: &Option<u64>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+        match self {
+            Rule::All(rules) => rules.iter().all(|rule| rule.matches(candidate, facts)),
+            Rule::Any(rules) => rules.iter().any(|rule| rule.matches(candidate, facts)),
+            Rule::Not(rule) => !rule.matches(candidate, facts),
+            Rule::Artist(matchers) => song.artists.iter().any(|artist| {
+                matchers
+                    .iter()
+                    .any(|matcher| matcher.matches(artist.id, artist.name.as_deref()))
+            }),
+            Rule::FollowedArtist => song
+                .artists
+                .iter()
+                .any(|artist| facts.followed_artists.contains(&artist.id)),
+            Rule::Album(matchers) => matchers
+                .iter()
+                .any(|matcher| matcher.matches(song.album.id, song.album.name.as_deref())),
+            Rule::Duration(min, max) => within(song.duration as u64 / 1000, min, max),
+            Rule::Liked => facts.liked.contains(&song.id),
+            Rule::PlayCount(min, max) => within(
+                facts.play_counts.get(&song.id).copied().unwrap_or_default() as u64,
+                &min.map(|min| min as u64),
+                &max.map(|max| max as u64),
+            ),
+            Rule::Playlist(pids) => pids.iter().any(|pid| {
+                facts
+                    .playlists
+                    .get(pid)
+                    .is_some_and(|songs| songs.contains(&song.id))
+            }),
+            Rule::AddedWithin(days) => candidate
+                .added
+                .is_some_and(|added| added + days * DAY_MILLISECONDS >= facts.now),
+        }
+    }
+
+    /// Call F with each rule in this rule, including itself.
+    fn visit(&self, f: &mut dyn FnMut(&Rule)) {
+        f(self);
+        match self {
+            Rule::All(rules) | Rule::Any(rules) => rules.iter().for_each(|rule| rule.visit(f)),
+            Rule::Not(rule) => rule.visit(f),
+            _ => (),
+        }
+    }
+
+    /// Whether this rule uses a rule RULE satisfies, to fetch only the needed facts.
+    pub fn uses(&self, rule: impl Fn(&Rule) -> bool) -> bool {
+        let mut used = false;
+        self.visit(&mut |r| used = used || rule(r));
+        used
+    }
+
+    /// The playlists used by the rule.
+    pub fn playlists(&self) -> Vec<usize> {
+        let mut pids = Vec::new();
+        self.visit(&mut |rule| {
+            if let Rule::Playlist(ids) = rule {
+                pids.extend(ids.iter());
+            }
+        });
+        pids
+    }
+}
+
+/// How the songs of a smart playlist are ordered, `Source` keeps the order of the sources.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    #[default]
+    Source,
+    /// The latest added first.
+    Added,
+    /// The most played first.
+    Plays,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylist {
+    /// The playlist the songs are written to.
+    pub pid: usize,
+    pub name: String,
+    pub sources: Vec<Source>,
+    pub rule: Rule,
+    #[serde(default)]
+    pub order: Order,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl SmartPlaylist {
+    /// The ids of the songs in CANDIDATES matching the rule, in order and without duplicates.
+    pub fn evaluate(&self, candidates: &[Candidate], facts: &Facts) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut songs = candidates
+            .iter()
+            .filter(|candidate| seen.insert(candidate.song.id))
+            .filter(|candidate| self.rule.matches(candidate, facts))
+            .collect::<Vec<_>>();
+        match self.order {
+            Order::Source => (),
+            Order::Added => songs.sort_by_key(|candidate| std::cmp::Reverse(candidate.added)),
+            Order::Plays => songs.sort_by_key(|candidate| {
+                std::cmp::Reverse(facts.play_counts.get(&candidate.song.id).copied())
+            }),
+        }
+        songs
+            .iter()
+            .map(|candidate| candidate.song.id)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Load the smart playlists in FILE, none if it doesn't exist.
+pub fn load_smart_playlists(file: &Path) -> TResult<Vec<SmartPlaylist>> {
+    if !file.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(file)?)?)
+}
+
+pub fn save_smart_playlists(file: &Path, playlists: &[SmartPlaylist]) -> TResult<()> {
+    std::fs::write(file, serde_json::to_string_pretty(playlists)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Candidate, Facts, Matcher, Order, Rule, SmartPlaylist, Source};
+    use crate::date::DAY_MILLISECONDS;
+    use crate::types::{Artist, Song};
+
+    fn candidate(
+        id: usize,
+        artist: (usize, &str),
+        seconds: usize,
+        added_days_ago: u64,
+    ) -> Candidate {
+        Candidate {
+            song: Song {
+                id,
+                name: format!("Song {}", id),
+                artists: vec![Artist {
+                    id: artist.0,
+                    name: Some(artist.1.to_string()),
+                }],
+                duration: seconds * 1000,
+                ..Default::default()
+            },
+            added: Some(100 * DAY_MILLISECONDS - added_days_ago * DAY_MILLISECONDS),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let candidates = vec![
+            candidate(1, (10, "Followed"), 200, 5),
+            candidate(2, (10, "Followed"), 400, 5),
+            candidate(3, (20, "Other"), 200, 5),
+            candidate(4, (10, "Followed"), 250, 40),
+            candidate(5, (10, "Followed"), 180, 1),
+            candidate(1, (10, "Followed"), 200, 5),
+        ];
+        let mut facts = Facts {
+            now: 100 * DAY_MILLISECONDS,
+            ..Default::default()
+        };
+        facts.liked.extend([1, 2, 3, 4, 5]);
+        facts.followed_artists.insert(10);
+        facts.play_counts.insert(1, 3);
+        facts.play_counts.insert(5, 10);
+
+        let mut playlist = SmartPlaylist {
+            pid: 0,
+            name: "Fresh".to_string(),
+            sources: vec![Source::Liked],
+            rule: Rule::All(vec![
+                Rule::Liked,
+                Rule::FollowedArtist,
+                Rule::AddedWithin(30),
+                Rule::Duration(None, Some(300)),
+            ]),
+            order: Order::Source,
+            limit: None,
+        };
+        assert_eq!(playlist.evaluate(&candidates, &facts), vec![1, 5]);
+
+        playlist.order = Order::Plays;
+        assert_eq!(playlist.evaluate(&candidates, &facts), vec![5, 1]);
+        playlist.limit = Some(1);
+        assert_eq!(playlist.evaluate(&candidates, &facts), vec![5]);
+
+        playlist.rule = Rule::Not(Box::new(Rule::Artist(vec![Matcher::Name(
+            "followed".to_string(),
+        )])));
+        assert_eq!(playlist.evaluate(&candidates, &facts), vec![3]);
+        assert!(!playlist.rule.uses(|rule| *rule == Rule::Liked));
+    }
+
+    #[test]
+    fn test_rule_json() {
+        let rule = Rule::Any(vec![
+            Rule::Artist(vec![Matcher::Id(1), Matcher::Name("A".to_string())]),
+            Rule::PlayCount(Some(3), None),
+            Rule::Playlist(vec![7]),
+        ]);
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(
+            json,
+            r#"{"any":[{"artist":[1,"A"]},{"play-count":[3,null]},{"playlist":[7]}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Rule>(&json).unwrap(), rule);
+        assert_eq!(rule.playlists(), vec![7]);
+    }
+}
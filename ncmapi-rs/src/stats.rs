@@ -0,0 +1,361 @@
+//! Statistics of the listening history: the top songs, artists and albums,
+//! the listening time and the streaks of days with plays.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::date::{civil_from_days, DAY_MILLISECONDS};
+use crate::types::{Album, Artist, Song};
+
+/// A play of a song, `played_at` is in milliseconds since the unix epoch and `listened`
+/// is how many milliseconds of the song were played.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Play {
+    pub song: Song,
+    pub played_at: u64,
+    pub listened: u64,
+}
+
+/// An item with its number of plays and listening time in milliseconds.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ranked<T> {
+    pub item: T,
+    pub plays: usize,
+    pub listened: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub plays: usize,
+    /// The listening time in milliseconds.
+    pub listened: u64,
+    /// The number of days with plays.
+    pub days: usize,
+    pub top_songs: Vec<Ranked<Song>>,
+    pub top_artists: Vec<Ranked<Artist>>,
+    pub top_albums: Vec<Ranked<Album>>,
+}
+
+/// The days in a row with plays, `current` ends today or yesterday.
+#[derive(Default, Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Streaks {
+    pub current: usize,
+    pub longest: usize,
+}
+
+/// The day since the unix epoch of TIME in milliseconds, in the time zone OFFSET seconds
+/// from UTC.
+pub fn day(time: u64, offset: i64) -> i64 {
+    (time as i64 + offset * 1000).div_euclid(DAY_MILLISECONDS as i64)
+}
+
+/// The date of DAY since the unix epoch as YYYY-MM-DD.
+pub fn format_date(day: i64) -> String {
+    let (year, month, day) = civil_from_days(day);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Count the plays and listening time of each key, the most played first and the ones
+/// played first among the ties.
+fn rank<T: Clone, K: std::hash::Hash + Eq>(
+    items: impl Iterator<Item = (K, T, u64)>,
+    limit: usize,
+) -> Vec<Ranked<T>> {
+    let mut indexes = HashMap::new();
+    let mut ranked = Vec::<Ranked<T>>::new();
+    for (key, item, listened) in items {
+        let index = *indexes.entry(key).or_insert_with(|| {
+            ranked.push(Ranked {
+                item,
+                plays: 0,
+                listened: 0,
+            });
+            ranked.len() - 1
+        });
+        ranked[index].plays += 1;
+        ranked[index].listened += listened;
+    }
+    ranked.sort_by(|a, b| b.plays.cmp(&a.plays).then(b.listened.cmp(&a.listened)));
+    ranked.truncate(limit);
+    ranked
+}
+
+/// The statistics of PLAYS, with at most LIMIT top songs, artists and albums.
+/// The days are in the time zone OFFSET seconds from UTC.
+pub fn statistics(plays: &[Play], limit: usize, offset: i64) -> Stats {
+    let key = |id: usize, name: &Option<String>| (id, name.to_owned().unwrap_or_default());
+    Stats {
+        plays: plays.len(),
+        listened: plays.iter().map(|play| play.listened).sum(),
+        days: plays
+            .iter()
+            .map(|play| day(play.played_at, offset))
+            .collect::<BTreeSet<_>>()
+            .len(),
+        top_songs: rank(
+            plays
+                .iter()
+                .map(|play| (play.song.id, play.song.to_owned(), play.listened)),
+            limit,
+        ),
+        top_artists: rank(
+            plays.iter().flat_map(|play| {
+                play.song.artists.iter().map(move |artist| {
+                    (
+                        key(artist.id, &artist.name),
+                        artist.to_owned(),
+                        play.listened,
+                    )
+                })
+            }),
+            limit,
+        ),
+        top_albums: rank(
+            plays.iter().map(|play| {
+                let album = &play.song.album;
+                (key(album.id, &album.name), album.to_owned(), play.listened)
+            }),
+            limit,
+        ),
+    }
+}
+
+/// The streaks of PLAYS at NOW in milliseconds, the days are in the time zone OFFSET
+/// seconds from UTC.
+pub fn streaks(plays: &[Play], now: u64, offset: i64) -> Streaks {
+    let days = plays
+        .iter()
+        .map(|play| day(play.played_at, offset))
+        .collect::<BTreeSet<_>>();
+    let mut streaks = Streaks::default();
+    let mut run = 0;
+    let mut previous = None;
+    for day in days.iter() {
+        run = if previous == Some(day - 1) {
+            run + 1
+        } else {
+            1
+        };
+        streaks.longest = streaks.longest.max(run);
+        previous = Some(*day);
+    }
+    let today = day(now, offset);
+    if previous.is_some_and(|last| last >= today - 1) {
+        streaks.current = run;
+    }
+    streaks
+}
+
+/// A report of the plays from the day `from` to the day `to`.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub from: String,
+    pub to: String,
+    pub stats: Stats,
+    pub streaks: Streaks,
+}
+
+fn format_duration(milliseconds: u64) -> String {
+    let minutes = milliseconds / 60000;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+fn artist_names(artists: &[Artist]) -> String {
+    artists
+        .iter()
+        .filter_map(|artist| artist.name.to_owned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Report {
+    /// Render the report as an Org document.
+    pub fn to_org(&self) -> String {
+        let stats = &self.stats;
+        let mut org = format!("#+TITLE: Listening report {} to {}\n\n", self.from, self.to);
+        org.push_str("* Summary\n");
+        org.push_str(&format!("- Plays :: {}\n", stats.plays));
+        org.push_str(&format!(
+            "- Listening time :: {}\n",
+            format_duration(stats.listened)
+        ));
+        org.push_str(&format!("- Days with plays :: {}\n", stats.days));
+        org.push_str(&format!(
+            "- Streak :: {} days (longest {} days)\n",
+            self.streaks.current, self.streaks.longest
+        ));
+
+        let mut table = |title: &str, header: &str, rows: Vec<(String, usize, u64)>| {
+            org.push_str(&format!(
+                "\n* {}\n| # | {} | Plays | Time |\n|---\n",
+                title, header
+            ));
+            for (index, (name, plays, listened)) in rows.iter().enumerate() {
+                org.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    index + 1,
+                    name.replace('|', "/"),
+                    plays,
+                    format_duration(*listened)
+                ));
+            }
+        };
+        table(
+            "Top songs",
+            "Song",
+            stats
+                .top_songs
+                .iter()
+                .map(|ranked| {
+                    (
+                        format!(
+                            "{} - {}",
+                            ranked.item.name,
+                            artist_names(&ranked.item.artists)
+                        ),
+                        ranked.plays,
+                        ranked.listened,
+                    )
+                })
+                .collect(),
+        );
+        table(
+            "Top artists",
+            "Artist",
+            stats
+                .top_artists
+                .iter()
+                .map(|ranked| {
+                    (
+                        ranked.item.name.to_owned().unwrap_or_default(),
+                        ranked.plays,
+                        ranked.listened,
+                    )
+                })
+                .collect(),
+        );
+        table(
+            "Top albums",
+            "Album",
+            stats
+                .top_albums
+                .iter()
+                .map(|ranked| {
+                    (
+                        ranked.item.name.to_owned().unwrap_or_default(),
+                        ranked.plays,
+                        ranked.listened,
+                    )
+                })
+                .collect(),
+        );
+        org
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{day, format_date, statistics, streaks, Play, Report, Streaks};
+    use crate::date::DAY_MILLISECONDS;
+    use crate::types::{Album, Artist, Song};
+
+    const DAY: u64 = DAY_MILLISECONDS;
+
+    fn play(id: usize, artist: (usize, &str), album: (usize, &str), at: u64) -> Play {
+        Play {
+            song: Song {
+                id,
+                name: format!("Song {}", id),
+                artists: vec![Artist {
+                    id: artist.0,
+                    name: Some(artist.1.to_string()),
+                }],
+                album: Album {
+                    id: album.0,
+                    name: Some(album.1.to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            played_at: at,
+            listened: 180000,
+        }
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(20744), "2026-10-18");
+        assert_eq!(format_date(-1), "1969-12-31");
+        // 2026-10-18 23:30 UTC is already the next day in UTC+8
+        let time = 20744 * DAY + 23 * 3600 * 1000 + 30 * 60 * 1000;
+        assert_eq!(day(time, 0), 20744);
+        assert_eq!(day(time, 8 * 3600), 20745);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let plays = vec![
+            play(1, (10, "A"), (100, "X"), DAY),
+            play(2, (20, "B"), (200, "Y"), DAY + 1000),
+            play(2, (20, "B"), (200, "Y"), 2 * DAY),
+            play(3, (20, "B"), (100, "X"), 4 * DAY),
+            play(1, (10, "A"), (100, "X"), 5 * DAY),
+            play(2, (20, "B"), (200, "Y"), 6 * DAY),
+        ];
+        let stats = statistics(&plays, 2, 0);
+        assert_eq!(stats.plays, 6);
+        assert_eq!(stats.listened, 6 * 180000);
+        assert_eq!(stats.days, 5);
+        assert_eq!(
+            stats
+                .top_songs
+                .iter()
+                .map(|r| r.item.id)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        assert_eq!(stats.top_songs[0].plays, 3);
+        assert_eq!(
+            stats
+                .top_artists
+                .iter()
+                .map(|r| r.item.id)
+                .collect::<Vec<_>>(),
+            vec![20, 10]
+        );
+        assert_eq!(stats.top_artists[0].plays, 4);
+        assert_eq!(
+            stats
+                .top_albums
+                .iter()
+                .map(|r| r.item.id)
+                .collect::<Vec<_>>(),
+            vec![100, 200]
+        );
+
+        assert_eq!(
+            streaks(&plays, 7 * DAY, 0),
+            Streaks {
+                current: 3,
+                longest: 3
+            }
+        );
+        assert_eq!(streaks(&plays, 8 * DAY, 0).current, 0);
+        assert_eq!(streaks(&[], 8 * DAY, 0), Streaks::default());
+
+        let report = Report {
+            from: format_date(1),
+            to: format_date(7),
+            stats,
+            streaks: streaks(&plays, 7 * DAY, 0),
+        };
+        let org = report.to_org();
+        assert!(org.starts_with("#+TITLE: Listening report 1970-01-02 to 1970-01-08\n"));
+        assert!(org.contains("- Listening time :: 0h 18m\n"));
+        assert!(org.contains("| 1 | Song 2 - B | 3 | 0h 09m |\n"));
+    }
+}
@@ -0,0 +1,490 @@
+//! Write metadata into audio files, MP3 files get ID3v2.4 tags and FLAC files get
+//! Vorbis comments and a PICTURE block.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use id3::{
+    frame::{
+        ExtendedText, Lyrics as Id3Lyrics, Picture, PictureType, SynchronisedLyrics,
+        SynchronisedLyricsType, TimestampFormat,
+    },
+    Tag, TagLike, Timestamp, Version,
+};
+
+use crate::{
+    date::{civil_from_days, DAY_MILLISECONDS},
+    lyric::{LyricEntry, LyricLayout, Lyrics},
+    types::Song,
+    TResult,
+};
+
+/// The description of the ID3 TXXX frame and the name of the Vorbis comment
+/// which store the NetEase song id.
+pub const SONG_ID_KEY: &str = "NETEASE_SONG_ID";
+
+/// The language of the lyric frames, it's unknown.
+const LYRIC_LANG: &str = "und";
+
+const FLAC_MAGIC: &[u8] = b"fLaC";
+const BLOCK_PADDING: u8 = 1;
+const BLOCK_VORBIS_COMMENT: u8 = 4;
+const BLOCK_PICTURE: u8 = 6;
+/// The padding left after the metadata, so that later edits don't rewrite the file.
+const PADDING_SIZE: usize = 4096;
+/// The max length of a FLAC metadata block.
+const MAX_BLOCK_SIZE: usize = (1 << 24) - 1;
+
+/// The type and the data of a FLAC metadata block.
+type FlacBlock = (u8, Vec<u8>);
+
+/// The Vorbis comments written by `write_tags`, the existing ones are replaced.
+const VORBIS_KEYS: &[&str] = &[
+    "TITLE",
+    "ARTIST",
+    "ALBUM",
+    "TRACKNUMBER",
+    "DATE",
+    "LYRICS",
+    "UNSYNCEDLYRICS",
+    SONG_ID_KEY,
+];
+
+/// The metadata of a track.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Tags {
+    pub song_id: usize,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub track: Option<u32>,
+    pub year: Option<i32>,
+    /// The JPEG or PNG cover image.
+    pub cover: Option<Vec<u8>>,
+    pub lyrics: Option<Lyrics>,
+}
+
+impl Tags {
+    /// The tags of `song`, without the cover and the lyrics.
+    pub fn from_song(song: &Song) -> Self {
+        Self {
+            song_id: song.id,
+            title: song.name.to_owned(),
+            artists: song
+                .artists
+                .iter()
+                .filter_map(|artist| artist.name.to_owned())
+                .collect(),
+            album: song.album.name.to_owned().unwrap_or_default(),
+            track: Some(song.no as u32).filter(|no| *no > 0),
+            year: Some(song.publish_time)
+                .filter(|time| *time > 0)
+                .map(year_from_timestamp),
+            cover: None,
+            lyrics: None,
+        }
+    }
+}
+
+/// The year of the timestamp `time` in milliseconds, in UTC.
+fn year_from_timestamp(time: i64) -> i32 {
+    civil_from_days(time.div_euclid(DAY_MILLISECONDS as i64)).0 as i32
+}
+
+fn image_mime(image: &[u8]) -> &'static str {
+    if image.starts_with(b"\x89PNG") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    }
+}
+
+fn is_flac(path: &Path) -> TResult<bool> {
+    let mut magic = [0; 4];
+    let read = File::open(path)?.read(&mut magic)?;
+    Ok(magic[..read] == *FLAC_MAGIC)
+}
+
+/// Write `tags` into the MP3 or FLAC file at `path`, the other tags of the file are kept.
+pub fn write_tags(path: &Path, tags: &Tags) -> TResult<()> {
+    if is_flac(path)? {
+        write_flac(path, tags)
+    } else {
+        write_id3(path, tags)
+    }
+}
+
+/// Read the tags of the MP3 or FLAC file at `path`, the cover isn't read.
+/// Return `None` if the file has no NetEase song id, which is written by `write_tags`.
+pub fn read_tags(path: &Path) -> TResult<Option<Tags>> {
+    let tags = if is_flac(path)? {
+        read_flac(path)?
+    } else {
+        read_id3(path)?
+    };
+    Ok(tags.filter(|tags| tags.song_id > 0))
+}
+
+/// Read the NetEase song id written by `write_tags`.
+pub fn read_song_id(path: &Path) -> TResult<Option<usize>> {
+    Ok(read_tags(path)?.map(|tags| tags.song_id))
+}
+
+fn read_id3(path: &Path) -> TResult<Option<Tags>> {
+    let tag = match id3::no_tag_ok(Tag::read_from_path(path))? {
+        Some(tag) => tag,
+        None => return Ok(None),
+    };
+    let lyrics = tag.synchronised_lyrics().next().map(|lyrics| Lyrics {
+        metadata: Vec::new(),
+        entries: lyrics
+            .content
+            .iter()
+            .map(|(time, text)| LyricEntry {
+                time: *time as u64,
+                original: text.to_owned(),
+                ..Default::default()
+            })
+            .collect(),
+    });
+    let song_id = tag
+        .extended_texts()
+        .find(|text| text.description == SONG_ID_KEY)
+        .and_then(|text| text.value.parse().ok())
+        .unwrap_or_default();
+
+    Ok(Some(Tags {
+        song_id,
+        title: tag.title().unwrap_or_default().to_string(),
+        artists: tag
+            .artists()
+            .unwrap_or_default()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        album: tag.album().unwrap_or_default().to_string(),
+        track: tag.track(),
+        year: tag
+            .date_recorded()
+            .map(|date| date.year)
+            .or_else(|| tag.year()),
+        cover: None,
+        lyrics,
+    }))
+}
+
+fn read_flac(path: &Path) -> TResult<Option<Tags>> {
+    let bytes = fs::read(path)?;
+    let (blocks, _) = read_flac_blocks(&bytes)?;
+    let comments = match blocks
+        .iter()
+        .find(|(kind, _)| *kind == BLOCK_VORBIS_COMMENT)
+    {
+        Some((_, data)) => read_vorbis_comment(data).1,
+        None => return Ok(None),
+    };
+
+    let mut tags = Tags::default();
+    for comment in comments.iter() {
+        let (key, value) = match comment.split_once('=') {
+            Some((key, value)) => (key.to_uppercase(), value.to_string()),
+            None => continue,
+        };
+        match key.as_str() {
+            "TITLE" => tags.title = value,
+            "ARTIST" => tags.artists.push(value),
+            "ALBUM" => tags.album = value,
+            "TRACKNUMBER" => tags.track = value.parse().ok(),
+            "DATE" => tags.year = value.get(..4).and_then(|year| year.parse().ok()),
+            "LYRICS" => tags.lyrics = Some(Lyrics::parse(&value, "", "")),
+            key if key == SONG_ID_KEY => tags.song_id = value.parse().unwrap_or_default(),
+            _ => (),
+        }
+    }
+    Ok(Some(tags))
+}
+
+fn write_id3(path: &Path, tags: &Tags) -> TResult<()> {
+    let mut tag = id3::no_tag_ok(Tag::read_from_path(path))?.unwrap_or_default();
+    tag.set_title(tags.title.to_owned());
+    // ID3v2.4 separates multiple values with null characters
+    tag.set_artist(tags.artists.join("\0"));
+    tag.set_album(tags.album.to_owned());
+    match tags.track {
+        Some(track) => tag.set_track(track),
+        None => tag.remove_track(),
+    }
+    // ID3v2.4 replaces the year frame TYER with the recording time TDRC
+    tag.remove_year();
+    match tags.year {
+        Some(year) => tag.set_date_recorded(Timestamp {
+            year,
+            ..Default::default()
+        }),
+        None => tag.remove_date_recorded(),
+    }
+
+    tag.remove_extended_text(Some(SONG_ID_KEY), None);
+    tag.add_frame(ExtendedText {
+        description: SONG_ID_KEY.to_string(),
+        value: tags.song_id.to_string(),
+    });
+
+    if let Some(ref cover) = tags.cover {
+        tag.remove_picture_by_type(PictureType::CoverFront);
+        tag.add_frame(Picture {
+            mime_type: image_mime(cover).to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: cover.to_owned(),
+        });
+    }
+
+    if let Some(ref lyrics) = tags.lyrics {
+        tag.remove_all_lyrics();
+        tag.remove_all_synchronised_lyrics();
+        tag.add_frame(Id3Lyrics {
+            lang: LYRIC_LANG.to_string(),
+            description: String::new(),
+            text: lyrics.to_text(LyricLayout::Stacked),
+        });
+        tag.add_frame(SynchronisedLyrics {
+            lang: LYRIC_LANG.to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: lyrics
+                .timed_lines(LyricLayout::Stacked)
+                .into_iter()
+                .map(|(time, text)| (time as u32, text))
+                .collect(),
+        });
+    }
+
+    tag.write_to_path(path, Version::Id3v24)?;
+    Ok(())
+}
+
+/// Split the FLAC file into its metadata blocks and the offset of the audio frames.
+fn read_flac_blocks(bytes: &[u8]) -> TResult<(Vec<FlacBlock>, usize)> {
+    if !bytes.starts_with(FLAC_MAGIC) {
+        return Err("not a flac file".into());
+    }
+    let mut blocks = Vec::new();
+    let mut offset = FLAC_MAGIC.len();
+    loop {
+        let header = bytes
+            .get(offset..offset + 4)
+            .ok_or("truncated flac metadata")?;
+        let last = header[0] & 0x80 != 0;
+        let kind = header[0] & 0x7f;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let data = bytes
+            .get(offset + 4..offset + 4 + length)
+            .ok_or("truncated flac metadata")?;
+        blocks.push((kind, data.to_vec()));
+        offset += 4 + length;
+        if last {
+            return Ok((blocks, offset));
+        }
+    }
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Parse a Vorbis comment block into the vendor string and the `KEY=value` comments.
+fn read_vorbis_comment(data: &[u8]) -> (String, Vec<String>) {
+    let mut comments = Vec::new();
+    let vendor_length = read_u32_le(data, 0).unwrap_or_default();
+    let vendor = data
+        .get(4..4 + vendor_length)
+        .map(|vendor| String::from_utf8_lossy(vendor).to_string())
+        .unwrap_or_default();
+    let mut offset = 4 + vendor_length;
+    let count = read_u32_le(data, offset).unwrap_or_default();
+    offset += 4;
+    for _ in 0..count {
+        let length = match read_u32_le(data, offset) {
+            Some(length) => length,
+            None => break,
+        };
+        match data.get(offset + 4..offset + 4 + length) {
+            Some(comment) => comments.push(String::from_utf8_lossy(comment).to_string()),
+            None => break,
+        }
+        offset += 4 + length;
+    }
+    (vendor, comments)
+}
+
+fn vorbis_comment_block(vendor: &str, comments: &[String]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    data.extend_from_slice(vendor.as_bytes());
+    data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments.iter() {
+        data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        data.extend_from_slice(comment.as_bytes());
+    }
+    data
+}
+
+fn picture_block(image: &[u8]) -> Vec<u8> {
+    let mime = image_mime(image);
+    let mut data = Vec::new();
+    data.extend_from_slice(&3u32.to_be_bytes()); // front cover
+    data.extend_from_slice(&(mime.len() as u32).to_be_bytes());
+    data.extend_from_slice(mime.as_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes()); // description
+                                                 // width, height, color depth and the number of colors, 0 means unknown
+    data.extend_from_slice(&[0; 16]);
+    data.extend_from_slice(&(image.len() as u32).to_be_bytes());
+    data.extend_from_slice(image);
+    data
+}
+
+fn write_flac(path: &Path, tags: &Tags) -> TResult<()> {
+    let bytes = fs::read(path)?;
+    let (mut blocks, audio) = read_flac_blocks(&bytes)?;
+
+    let (vendor, mut comments) = blocks
+        .iter()
+        .find(|(kind, _)| *kind == BLOCK_VORBIS_COMMENT)
+        .map(|(_, data)| read_vorbis_comment(data))
+        .unwrap_or_default();
+    comments.retain(|comment| {
+        let key = comment.split('=').next().unwrap_or_default();
+        !VORBIS_KEYS.iter().any(|own| own.eq_ignore_ascii_case(key))
+    });
+    comments.push(format!("TITLE={}", tags.title));
+    comments.extend(
+        tags.artists
+            .iter()
+            .map(|artist| format!("ARTIST={}", artist)),
+    );
+    comments.push(format!("ALBUM={}", tags.album));
+    if let Some(track) = tags.track {
+        comments.push(format!("TRACKNUMBER={}", track));
+    }
+    if let Some(year) = tags.year {
+        comments.push(format!("DATE={}", year));
+    }
+    if let Some(ref lyrics) = tags.lyrics {
+        // there's no synchronized lyric field, LYRICS holding LRC is widely supported
+        comments.push(format!(
+            "LYRICS={}",
+            lyrics.to_lrc(LyricLayout::Stacked, None)
+        ));
+        comments.push(format!(
+            "UNSYNCEDLYRICS={}",
+            lyrics.to_text(LyricLayout::Stacked)
+        ));
+    }
+    comments.push(format!("{}={}", SONG_ID_KEY, tags.song_id));
+
+    blocks.retain(|(kind, _)| {
+        *kind != BLOCK_PADDING
+            && *kind != BLOCK_VORBIS_COMMENT
+            && !(*kind == BLOCK_PICTURE && tags.cover.is_some())
+    });
+    blocks.push((
+        BLOCK_VORBIS_COMMENT,
+        vorbis_comment_block(&vendor, &comments),
+    ));
+    if let Some(ref cover) = tags.cover {
+        blocks.push((BLOCK_PICTURE, picture_block(cover)));
+    }
+    blocks.push((BLOCK_PADDING, vec![0; PADDING_SIZE]));
+
+    let mut output = FLAC_MAGIC.to_vec();
+    for (index, (kind, data)) in blocks.iter().enumerate() {
+        if data.len() > MAX_BLOCK_SIZE {
+            return Err("flac metadata block too large".into());
+        }
+        let last = if index + 1 == blocks.len() { 0x80 } else { 0 };
+        output.push(kind | last);
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+        output.extend_from_slice(data);
+    }
+    output.extend_from_slice(&bytes[audio..]);
+
+    // write to a temporary file first so that the file isn't broken if failed
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tagging");
+    fs::write(&temporary, output)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_flac_blocks, read_song_id, read_tags, write_tags, year_from_timestamp, Tags};
+    use crate::lyric::Lyrics;
+
+    fn tags() -> Tags {
+        Tags {
+            song_id: 1901371647,
+            title: "song".to_string(),
+            artists: vec!["a".to_string(), "b".to_string()],
+            album: "album".to_string(),
+            track: Some(3),
+            year: Some(2021),
+            cover: Some(b"\x89PNG fake".to_vec()),
+            lyrics: Some(Lyrics::parse("[00:01.00]one", "[00:01.00]一", "")),
+        }
+    }
+
+    #[test]
+    fn test_year_from_timestamp() {
+        assert_eq!(year_from_timestamp(0), 1970);
+        assert_eq!(year_from_timestamp(1609459200000), 2021);
+        assert_eq!(year_from_timestamp(1609459199000), 2020);
+    }
+
+    #[test]
+    fn test_flac_tags() {
+        let path = std::env::temp_dir().join("ncmapi_test_flac_tags.flac");
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        flac.extend_from_slice(&[0; 0x22]);
+        flac.extend_from_slice(b"AUDIO");
+        std::fs::write(&path, flac).unwrap();
+
+        write_tags(&path, &tags()).unwrap();
+        write_tags(&path, &tags()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let (blocks, audio) = read_flac_blocks(&bytes).unwrap();
+        assert_eq!(&bytes[audio..], b"AUDIO");
+        assert_eq!(
+            blocks.iter().map(|(kind, _)| *kind).collect::<Vec<_>>(),
+            vec![0, 4, 6, 1]
+        );
+        assert_eq!(read_song_id(&path).unwrap(), Some(1901371647));
+        let read = read_tags(&path).unwrap().unwrap();
+        assert_eq!(read.artists, tags().artists);
+        assert_eq!((read.track, read.year), (Some(3), Some(2021)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_id3_tags() {
+        let path = std::env::temp_dir().join("ncmapi_test_id3_tags.mp3");
+        std::fs::write(&path, b"AUDIO").unwrap();
+        assert_eq!(read_song_id(&path).unwrap(), None);
+
+        write_tags(&path, &tags()).unwrap();
+        assert_eq!(read_song_id(&path).unwrap(), Some(1901371647));
+        let read = read_tags(&path).unwrap().unwrap();
+        assert_eq!(
+            (read.title.as_str(), read.artists, read.year),
+            ("song", tags().artists, Some(2021))
+        );
+        assert_eq!(read.lyrics.unwrap().entries[0].original, "one\n一");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
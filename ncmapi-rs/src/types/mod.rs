@@ -1,10 +1,20 @@
 // This module was generated at https://transform.tools/json-to-rust-serde
 // However, some fields of struct was stripped for concision.
+use std::collections::HashMap;
+
 pub type SearchSongResp = ResultResp<SearchResultSong>;
 pub type SearchArtistResp = ResultResp<SearchResultArtist>;
 pub type SearchPodcastResp = ResultResp<SearchResultPodcast>;
 pub type SearchPlaylistResp = ResultResp<SearchResultPlaylist>;
 pub type SearchAlbumResp = ResultResp<SearchResultAlbum>;
+pub type SearchUserResp = ResultResp<SearchResultUser>;
+pub type SearchMVResp = ResultResp<SearchResultMV>;
+pub type SearchLyricResp = ResultResp<SearchResultLyric>;
+pub type SearchVideoResp = ResultResp<SearchResultVideo>;
+pub type SearchAllResp = ResultResp<SearchResultAll>;
+pub type SearchSuggestResp = ResultResp<SearchSuggest>;
+pub type SearchMultimatchResp = ResultResp<SearchMultimatch>;
+pub type SearchHotResp = ResultResp<SearchHot>;
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,10 +28,11 @@ pub struct ResultResp<T> {
 pub struct SearchResultSong {
     pub songs: Vec<Song>,
     pub has_more: bool,
+    pub song_count: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Song {
     pub id: usize,
     pub name: String,
@@ -34,8 +45,10 @@ pub struct Song {
     pub fee: usize,
     #[serde(alias = "popularity")]
     pub pop: f32,
+    /// The track number in the album.
+    pub no: usize,
+    pub publish_time: i64,
     // pub resource_state: bool,
-    // pub publish_time: i64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -46,13 +59,13 @@ pub struct Artist {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Album {
     pub id: usize,
     pub name: Option<String>,
-    #[serde(default)]
     pub pic_url: String,
     pub pic: usize,
+    pub artist: Option<Artist>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -90,6 +103,8 @@ pub struct PodcastAudiosResp {
 pub struct UserProfile {
     pub user_id: usize,
     pub nickname: String,
+    #[serde(default)]
+    pub avatar_url: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -104,6 +119,8 @@ pub struct UserPlaylistResp {
     pub code: usize,
     #[serde(default)]
     pub playlist: Vec<Playlist>,
+    #[serde(default)]
+    pub more: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -118,24 +135,78 @@ pub struct Playlist {
     pub id: usize,
     pub name: String,
     pub description: Option<String>,
+    /// 5 for the liked songs playlist.
+    #[serde(default)]
+    pub special_type: usize,
+    #[serde(default)]
+    pub cover_img_url: String,
+    #[serde(default)]
+    pub creator: Option<UserProfile>,
+    #[serde(default)]
+    pub track_count: usize,
+    #[serde(default)]
+    pub play_count: usize,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub update_time: u64,
 }
 
+/// `tracks` are only the first part of the songs, all the songs are in `track_ids`.
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct PlaylistDetail {
     pub id: usize,
     pub name: String,
     pub description: Option<String>,
-    #[serde(default)]
     pub tracks: Vec<Song>,
-    #[serde(default)]
     pub track_ids: Vec<Id>,
     pub user_id: usize,
+    pub creator: Option<UserProfile>,
+    pub cover_img_url: String,
+    pub tags: Vec<String>,
+    pub track_count: usize,
+    pub play_count: usize,
+    pub subscribed_count: usize,
+    pub create_time: u64,
+    pub update_time: u64,
+    /// 10 for the private playlists.
+    pub privacy: usize,
+    /// 5 for the liked songs playlist.
+    pub special_type: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Id {
     pub id: usize,
+    /// When the song was added to the playlist, in milliseconds.
+    #[serde(default)]
+    pub at: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SongDetailResp {
+    pub code: usize,
+    pub songs: Vec<Song>,
+    pub privileges: Vec<Privilege>,
+}
+
+/// The privilege of the current user to a song.
+/// `st` < 0 means the song is unavailable, `pl` is the max bitrate can be played,
+/// it's 0 when the song can't be played.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Privilege {
+    pub id: usize,
+    pub fee: usize,
+    pub payed: usize,
+    pub st: i64,
+    pub pl: usize,
+    pub dl: usize,
+    pub maxbr: usize,
+    pub fl: usize,
+    pub cp: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -145,11 +216,32 @@ pub struct SongUrlResp {
     pub data: Vec<SongUrl>,
 }
 
+/// `url` is `None` when the song can't be played, `free_trial_info` is the
+/// segment of the song when only a preview is available.
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct SongUrl {
     pub id: usize,
-    pub url: String,
+    pub url: Option<String>,
     pub br: usize,
+    pub size: usize,
+    pub md5: Option<String>,
+    pub code: usize,
+    #[serde(rename = "type")]
+    pub file_type: Option<String>,
+    pub level: Option<String>,
+    pub encode_type: Option<String>,
+    pub expi: usize,
+    pub fee: usize,
+    pub free_trial_info: Option<FreeTrialInfo>,
+}
+
+/// The start and end of the preview segment, in seconds.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FreeTrialInfo {
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -190,14 +282,60 @@ pub struct RecommendedSongsResp {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Comment {
+    pub comment_id: usize,
     pub user: UserProfile,
-    #[serde(default)]
     pub content: String,
     pub time: u64,
     pub liked_count: usize,
     pub liked: bool,
+    pub ip_location: IpLocation,
+    /// The comments replied to, only the direct one is returned.
+    pub be_replied: Vec<BeReplied>,
+    pub parent_comment_id: usize,
+    pub show_floor_comment: Option<FloorSummary>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IpLocation {
+    pub location: String,
+}
+
+/// The quote of the comment replied to, `content` is `None` if it has been deleted.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BeReplied {
+    pub be_replied_comment_id: usize,
+    pub user: UserProfile,
+    pub content: Option<String>,
+    pub ip_location: IpLocation,
+}
+
+/// The summary of the replies to a comment.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FloorSummary {
+    pub reply_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommentFloorResp {
+    pub code: usize,
+    pub data: CommentFloor,
+}
+
+/// The replies to a comment, `time` is the cursor of the next page.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommentFloor {
+    pub owner_comment: Option<Comment>,
+    pub comments: Vec<Comment>,
+    pub total_count: usize,
+    pub has_more: bool,
+    pub time: i64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -227,15 +365,21 @@ pub struct HotCommentsResp {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct LyricResp {
     pub code: usize,
     pub sgc: bool,
     pub sfy: bool,
     pub qfy: bool,
+    pub nolyric: bool,
     pub lrc: Option<Lyric>,
     pub klyric: Option<Lyric>,
     pub tlyric: Option<Lyric>,
+    pub romalrc: Option<Lyric>,
+    /// The word timed lyric, only returned by the new lyric api.
+    pub yrc: Option<Lyric>,
+    pub ytlrc: Option<Lyric>,
+    pub yromlrc: Option<Lyric>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -271,6 +415,51 @@ pub struct SimiSongsResp {
     pub songs: Vec<Song>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntelligenceListResp {
+    pub code: usize,
+    pub data: Vec<IntelligenceSong>,
+}
+
+/// A song in the heartbeat mode, `recommended` is false for the songs from the playlist.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntelligenceSong {
+    pub id: usize,
+    pub recommended: bool,
+    pub alg: String,
+    pub song_info: Song,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimiArtistsResp {
+    pub code: usize,
+    pub artists: Vec<Artist>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimiPlaylistsResp {
+    pub code: usize,
+    pub playlists: Vec<Playlist>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimiMVsResp {
+    pub code: usize,
+    pub mvs: Vec<MV>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SimiUsersResp {
+    pub code: usize,
+    pub userprofiles: Vec<UserProfile>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ArtistSongsResp {
@@ -294,7 +483,40 @@ pub struct ArtistSublistResp {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlbumSublistResp {
+    pub code: usize,
+    pub data: Vec<Album>,
+    pub has_more: bool,
+    pub count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserRecordResp {
+    pub code: usize,
+    pub week_data: Vec<PlayRecord>,
+    pub all_data: Vec<PlayRecord>,
+}
+
+/// `score` is the play count relative to the most played song, which scores 100.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayRecord {
+    pub play_count: usize,
+    pub score: usize,
+    pub song: Song,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LikeListResp {
+    pub code: usize,
+    pub ids: Vec<usize>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Podcast {
     pub id: usize,
     pub name: String,
@@ -309,6 +531,8 @@ pub struct Podcast {
 pub struct SearchResultArtist {
     #[serde(default)]
     pub artists: Vec<Artist>,
+    #[serde(default)]
+    pub artist_count: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -316,6 +540,8 @@ pub struct SearchResultArtist {
 pub struct SearchResultPodcast {
     #[serde(default)]
     pub dj_radios: Vec<Podcast>,
+    #[serde(default)]
+    pub dj_radios_count: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -323,6 +549,8 @@ pub struct SearchResultPodcast {
 pub struct SearchResultPlaylist {
     #[serde(default)]
     pub playlists: Vec<Playlist>,
+    #[serde(default)]
+    pub playlist_count: usize,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -330,4 +558,350 @@ pub struct SearchResultPlaylist {
 pub struct SearchResultAlbum {
     #[serde(default)]
     pub albums: Vec<Album>,
+    #[serde(default)]
+    pub album_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchResultUser {
+    pub userprofiles: Vec<UserProfile>,
+    pub userprofile_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MV {
+    pub id: usize,
+    pub name: String,
+    pub artist_name: String,
+    pub duration: usize,
+    pub play_count: usize,
+    pub cover: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchResultMV {
+    pub mvs: Vec<MV>,
+    pub mv_count: usize,
+}
+
+/// The matched part of the lyrics, `range` is the highlighted spans in `txt`.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LyricHighlight {
+    pub txt: String,
+    pub range: Vec<HighlightRange>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HighlightRange {
+    pub first: usize,
+    pub second: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LyricSong {
+    #[serde(flatten)]
+    pub song: Song,
+    pub lyrics: Option<LyricHighlight>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchResultLyric {
+    pub songs: Vec<LyricSong>,
+    pub song_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VideoCreator {
+    pub user_id: usize,
+    pub user_name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Video {
+    pub vid: String,
+    pub title: String,
+    pub creator: Vec<VideoCreator>,
+    #[serde(rename = "durationms")]
+    pub duration: usize,
+    pub play_time: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchResultVideo {
+    pub videos: Vec<Video>,
+    pub video_count: usize,
+}
+
+/// Result of the composite search (type 1018), `order` is the order of sections
+/// shown by the official client.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchResultAll {
+    pub song: SearchAllSongs,
+    pub play_list: SearchAllPlaylists,
+    pub artist: SearchAllArtists,
+    pub album: SearchAllAlbums,
+    pub user: SearchAllUsers,
+    pub video: SearchAllVideos,
+    pub order: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllSongs {
+    pub songs: Vec<Song>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllPlaylists {
+    pub play_lists: Vec<Playlist>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllArtists {
+    pub artists: Vec<Artist>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllAlbums {
+    pub albums: Vec<Album>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllUsers {
+    pub users: Vec<UserProfile>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchAllVideos {
+    pub videos: Vec<Video>,
+    pub more: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchSuggest {
+    pub songs: Vec<Song>,
+    pub artists: Vec<Artist>,
+    pub albums: Vec<Album>,
+    pub playlists: Vec<Playlist>,
+    pub order: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchMultimatch {
+    pub artist: Vec<Artist>,
+    pub album: Vec<Album>,
+    pub orders: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchHot {
+    pub hots: Vec<HotKeyword>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotKeyword {
+    pub first: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchHotDetailResp {
+    pub code: usize,
+    pub data: Vec<HotSearch>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotSearch {
+    pub search_word: String,
+    pub score: usize,
+    pub content: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchDefaultResp {
+    pub code: usize,
+    pub data: SearchDefaultKeyword,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchDefaultKeyword {
+    pub show_keyword: String,
+    #[serde(alias = "realkeyword")]
+    pub real_keyword: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlaylistSubscribersResp {
+    pub code: usize,
+    pub total: usize,
+    pub more: bool,
+    pub subscribers: Vec<UserProfile>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlaylistCatlistResp {
+    pub code: usize,
+    pub all: PlaylistCategory,
+    pub sub: Vec<PlaylistCategory>,
+    /// The names of the top-level categories, keyed by their index.
+    pub categories: HashMap<String, String>,
+}
+
+/// A playlist tag, `category` is the index of the top-level category it belongs to.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlaylistCategory {
+    pub name: String,
+    pub category: usize,
+    pub hot: bool,
+    pub resource_count: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NosTokenResp {
+    pub code: usize,
+    pub result: NosToken,
+}
+
+/// The token allocated for uploading a file to the nos storage.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NosToken {
+    pub object_key: String,
+    pub token: String,
+    pub doc_id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UploadImageResp {
+    pub code: usize,
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlaylistHotResp {
+    pub code: usize,
+    pub tags: Vec<PlaylistCategory>,
+}
+
+/// The playlists of a category, `lasttime` is the cursor of the high quality playlists.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TopPlaylistResp {
+    pub code: usize,
+    pub playlists: Vec<Playlist>,
+    pub total: usize,
+    pub more: bool,
+    pub cat: String,
+    pub lasttime: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RelatedPlaylist {
+    pub id: usize,
+    pub name: String,
+    pub cover_img_url: String,
+    pub creator: UserProfile,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToplistResp {
+    pub code: usize,
+    pub list: Vec<Toplist>,
+}
+
+/// An official chart, `tracks` is only returned by the toplist detail api.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Toplist {
+    pub id: usize,
+    pub name: String,
+    pub description: Option<String>,
+    pub update_frequency: String,
+    pub cover_img_url: String,
+    pub update_time: u64,
+    pub play_count: usize,
+    pub tracks: Vec<ToplistTrack>,
+}
+
+/// The song name and artist of a track previewed in the toplist detail.
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToplistTrack {
+    pub first: String,
+    pub second: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToplistArtistResp {
+    pub code: usize,
+    pub list: ToplistArtists,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToplistArtists {
+    pub artists: Vec<ToplistArtist>,
+    pub update_time: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ToplistArtist {
+    pub id: usize,
+    pub name: String,
+    pub score: usize,
+    pub last_rank: Option<usize>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TopSongResp {
+    pub code: usize,
+    pub data: Vec<Song>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TopAlbumResp {
+    pub code: usize,
+    pub week_data: Vec<Album>,
+    pub month_data: Vec<Album>,
 }
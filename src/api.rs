@@ -5,10 +5,22 @@
 use emacs::Result as EResult;
 use emacs::Value as EValue;
 use emacs::{defun, Env, FromLisp, IntoLisp};
-use ncmapi::NcmApi;
+use futures::TryStreamExt;
+use ncmapi::types::{
+    Album, Artist, LyricResp, LyricSong, Playlist, PlaylistCatlistResp, PlaylistDetailResp,
+    Podcast, Privilege, ResultResp, SearchAlbumResp, SearchArtistResp, SearchLyricResp,
+    SearchMVResp, SearchPlaylistResp, SearchPodcastResp, SearchResultAll, SearchSongResp,
+    SearchUserResp, SearchVideoResp, Song, SongUrl, SongUrlResp, UserProfile, Video, MV,
+};
+use ncmapi::{ApiResponse, NcmApi, Page, Quality, SearchType};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value as JValue;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::library::{self, remember_playlists, remember_songs};
 
 static mut API: Option<NcmApi> = None;
 
@@ -39,36 +51,6 @@ pub struct UserInfo {
 
 impl SpecialJsonStructure for UserInfo {}
 
-/// The structure used for deserializing playlists json
-#[derive(Serialize, Deserialize, Debug)]
-pub struct PlaylistsInfo {
-    #[serde(default)]
-    pub more: JValue,
-
-    #[serde(default)]
-    pub playlist: JValue,
-
-    #[serde(default)]
-    pub code: usize,
-}
-
-impl SpecialJsonStructure for PlaylistsInfo {}
-
-/// The structure used for deserializing lyrics json
-#[derive(Serialize, Deserialize, Debug)]
-pub struct LyricsInfo {
-    #[serde(default)]
-    pub lrc: JValue,
-
-    #[serde(default)]
-    pub tlyric: JValue,
-
-    #[serde(default)]
-    pub code: usize,
-}
-
-impl SpecialJsonStructure for LyricsInfo {}
-
 /// The structure used for deserializing playlist json
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PlaylistInfo {
@@ -106,7 +88,7 @@ pub fn init_api() {
 }
 
 /// Return API reference
-fn get_api<'a>() -> &'a NcmApi {
+pub(crate) fn get_api<'a>() -> &'a NcmApi {
     unsafe {
         match API {
             None => panic!("API hasn't been initialized!"),
@@ -159,6 +141,18 @@ async fn have_logged() -> bool {
     }
 }
 
+/// The special type of the liked songs playlist of a user.
+pub(crate) const LIKED_PLAYLIST_TYPE: usize = 5;
+
+/// The logged in user, an error message if nobody has logged in.
+pub(crate) async fn current_user() -> Result<UserProfile, String> {
+    get_api()
+        .current_user()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Please login first!".to_string())
+}
+
 /// Check if you've loginned. If that's true, return t. Otherwise return nil.
 #[defun]
 #[tokio::main]
@@ -230,7 +224,7 @@ pub async fn delete_playlist(pid: i64) -> EResult<bool> {
 
 /// Convert Lisp list into Vec.
 /// The functions only can be used for list which has same atoms.
-fn list_to_vec<'a, T>(list: EValue<'a>) -> EResult<Vec<T>>
+pub(crate) fn list_to_vec<'a, T>(list: EValue<'a>) -> EResult<Vec<T>>
 where
     T: FromLisp<'a>,
 {
@@ -301,6 +295,186 @@ pub async fn update_playlist_order(pid: i64, sids: EValue<'_>) -> EResult<bool>
     }
 }
 
+/// Update the description of the playlist whose id is PID to DESC.
+#[defun]
+#[tokio::main]
+pub async fn update_playlist_description(pid: i64, desc: String) -> EResult<bool> {
+    let api = get_api();
+    let result = api
+        .playlist_desc_update(pid as usize, &desc)
+        .await
+        .unwrap()
+        .deserialize_to_implict();
+    Ok(result.code == 200)
+}
+
+/// The official playlist tags, fetched once from the category list.
+static PLAYLIST_TAGS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// The max number of tags a playlist can have.
+const PLAYLIST_TAGS_LIMIT: usize = 3;
+
+/// Get the official playlist tags.
+async fn official_playlist_tags() -> Result<Vec<String>, String> {
+    if let Some(tags) = PLAYLIST_TAGS.lock().unwrap().as_ref() {
+        return Ok(tags.to_owned());
+    }
+
+    let api = get_api();
+    let catlist = api
+        .playlist_catlist()
+        .await
+        .map_err(|e| e.to_string())?
+        .deserialize::<PlaylistCatlistResp>()
+        .map_err(|e| e.to_string())?;
+    if catlist.code != 200 {
+        return Err(format!("code {}", catlist.code));
+    }
+    let tags = catlist
+        .sub
+        .into_iter()
+        .map(|category| category.name)
+        .collect::<Vec<_>>();
+    if !tags.is_empty() {
+        *PLAYLIST_TAGS.lock().unwrap() = Some(tags.to_owned());
+    }
+    Ok(tags)
+}
+
+/// Check TAGS against the official tags, return the error message if they're invalid.
+async fn check_playlist_tags(tags: &[String]) -> Option<String> {
+    if tags.len() > PLAYLIST_TAGS_LIMIT {
+        return Some(format!(
+            "A playlist can have at most {} tags!",
+            PLAYLIST_TAGS_LIMIT
+        ));
+    }
+
+    let official = match official_playlist_tags().await {
+        Ok(official) => official,
+        Err(e) => return Some(format!("Failed to get the official playlist tags: {}", e)),
+    };
+    let invalid = tags
+        .iter()
+        .filter(|tag| !official.contains(tag))
+        .map(|tag| tag.as_str())
+        .collect::<Vec<_>>();
+    if invalid.is_empty() {
+        None
+    } else {
+        Some(format!("Invalid playlist tags: {}", invalid.join(", ")))
+    }
+}
+
+/// Update the tags of the playlist whose id is PID to TAGS, a list of strings.
+/// Only the official tags can be used, and at most 3 of them.
+#[defun]
+#[tokio::main]
+pub async fn update_playlist_tags(pid: i64, tags: EValue<'_>) -> EResult<EValue<'_>> {
+    let env = tags.env;
+    let tags = list_to_vec::<String>(tags)?;
+    if let Some(message) = check_playlist_tags(&tags).await {
+        return error(env, message);
+    }
+
+    let api = get_api();
+    let tags = tags.iter().map(|tag| tag.as_str()).collect::<Vec<_>>();
+    let result = api
+        .playlist_tags_update(pid as usize, &tags)
+        .await
+        .unwrap()
+        .deserialize_to_implict();
+    (result.code == 200).into_lisp(env)
+}
+
+/// Update the NAME, DESC and TAGS of the playlist whose id is PID at once.
+/// TAGS is a list of the official tags.
+#[defun]
+#[tokio::main]
+pub async fn update_playlist(
+    pid: i64,
+    name: String,
+    desc: String,
+    tags: EValue<'_>,
+) -> EResult<EValue<'_>> {
+    let env = tags.env;
+    let tags = list_to_vec::<String>(tags)?;
+    if let Some(message) = check_playlist_tags(&tags).await {
+        return error(env, message);
+    }
+
+    let api = get_api();
+    let tags = tags.iter().map(|tag| tag.as_str()).collect::<Vec<_>>();
+    let result = api
+        .playlist_update(pid as usize, &name, &desc, &tags)
+        .await
+        .unwrap()
+        .deserialize_to_implict();
+    (result.code == 200).into_lisp(env)
+}
+
+/// Update the cover of the playlist whose id is PID with the image FILE.
+/// SIZE is the side length of the square cropped from the top-left corner of the image,
+/// usually it's the shorter side.
+#[defun]
+#[tokio::main]
+pub async fn update_playlist_cover(
+    env: &Env,
+    pid: i64,
+    file: String,
+    size: i64,
+) -> EResult<EValue<'_>> {
+    let image = match std::fs::read(&file) {
+        Ok(image) => image,
+        Err(e) => return error(env, format!("Failed to read {}: {}", file, e)),
+    };
+    let ext = std::path::Path::new(&file)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "jpg".to_string());
+
+    let api = get_api();
+    match api
+        .playlist_cover_update(pid as usize, image, &ext, size as usize)
+        .await
+    {
+        Ok(result) => (result.deserialize_to_implict().code == 200).into_lisp(env),
+        Err(e) => error(env, format!("Failed to update the cover: {}", e)),
+    }
+}
+
+/// Subscribe the playlist whose id is PID, or unsubscribe it if UNSUBSCRIBE is non-nil.
+#[defun]
+#[tokio::main]
+pub async fn subscribe_playlist(pid: i64, unsubscribe: EValue<'_>) -> EResult<bool> {
+    let api = get_api();
+    let result = api
+        .playlist_subscribe(pid as usize, !unsubscribe.is_not_nil())
+        .await
+        .unwrap()
+        .deserialize_to_implict();
+    Ok(result.code == 200)
+}
+
+/// Get the subscribers of the playlist whose id is PID.
+/// LIMIT is the number of users in each page, PAGE is the current page.
+/// Return (((id nickname) ...) total has-more).
+#[defun]
+#[tokio::main]
+pub async fn playlist_subscribers(
+    env: &Env,
+    pid: i64,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let page = api
+        .playlist_subscribers_page(pid as usize, page_offset(limit, page), limit as usize)
+        .await
+        .unwrap();
+    page_to_lisp(env, &page, user_to_lisp)
+}
+
 /// Extract songs' main info from json data
 fn extract_songs_info(env: &Env, json_data: JValue) -> Result<EValue<'_>, ()> {
     let mut result = Vec::<EValue<'_>>::new();
@@ -367,32 +541,59 @@ pub async fn recommend_playlists(env: &Env) -> EResult<EValue<'_>> {
 }
 
 // Fundemantal functions
-/// Search song
+/// Convert the PAGE number (starting from 1) into the offset of items.
+pub(crate) fn page_offset(limit: i64, page: i64) -> usize {
+    ((page.max(1) - 1) * limit) as usize
+}
+
+/// Search song, the library is searched instead when failed.
 pub async fn search_song<'a>(
     env: &'a Env,
     content: String,
     limit: i64,
     page: i64,
 ) -> EResult<EValue<'a>> {
+    match search_song_page(&content, page_offset(limit, page), limit as usize).await {
+        Ok(songs) => items_to_lisp(env, &songs.items, song_to_lisp),
+        Err(e) => error(env, format!("Failed to search: {}", e)),
+    }
+}
+
+/// Search the songs with `cloud_search`, fall back to the library when it fails.
+async fn search_song_page(
+    content: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<Song>, String> {
     let api = get_api();
-    let result = api
-        .cloud_search(
-            &content,
-            Some(json!({ "limit": limit, "offset": page - 1  })),
-        )
+    match api
+        .cloud_search_page::<SearchSongResp>(content, SearchType::Song, offset, limit)
         .await
-        .unwrap()
-        .deserialize_to_implict();
-
-    if result.code == 200 {
-        let result = result.result;
-        if result.get("songCount").unwrap().to_string() == "0" {
-            return Ok(().into_lisp(env)?);
+    {
+        Ok(page) => {
+            remember_songs(&page.items);
+            Ok(page)
         }
+        Err(e) => library::search_songs(content, offset, limit).ok_or_else(|| e.to_string()),
+    }
+}
 
-        Ok(extract_songs_info(env, result.get("songs").unwrap().to_owned()).unwrap())
-    } else {
-        Ok(().into_lisp(env)?)
+/// Search the playlists with `cloud_search`, fall back to the library when it fails.
+async fn search_playlist_page(
+    content: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<Playlist>, String> {
+    let api = get_api();
+    match api
+        .cloud_search_page::<SearchPlaylistResp>(content, SearchType::Collection, offset, limit)
+        .await
+    {
+        Ok(page) => {
+            remember_playlists(&page.items);
+            Ok(page)
+        }
+        Err(e) => library::search_playlists(content, offset, limit).ok_or_else(|| e.to_string()),
     }
 }
 
@@ -402,29 +603,9 @@ pub async fn search_playlist<'a>(
     limit: i64,
     page: i64,
 ) -> EResult<EValue<'a>> {
-    let api = get_api();
-    let playlists = api
-        .cloud_search(
-            &content,
-            Some(json!({ "limit": limit,
-                     "offset": page - 1,
-                     "type": 1000i16
-            })),
-        )
-        .await
-        .unwrap()
-        .deserialize_to_implict();
-
-    if playlists.code == 200 {
-        let playlists = playlists.result.get("playlists").unwrap();
-
-        if playlists.as_array().unwrap().len() == 0 {
-            Ok(().into_lisp(env)?)
-        } else {
-            Ok(extract_playlists_info(env, playlists.to_owned()).unwrap())
-        }
-    } else {
-        Ok(().into_lisp(env)?)
+    match search_playlist_page(&content, page_offset(limit, page), limit as usize).await {
+        Ok(playlists) => items_to_lisp(env, &playlists.items, playlist_to_lisp),
+        Err(e) => error(env, format!("Failed to search: {}", e)),
     }
 }
 
@@ -444,68 +625,418 @@ pub async fn search<'a>(
     let env = playlistp.env;
 
     if playlistp.is_not_nil() {
-        let result = search_playlist(env, search_content, limit, page).await;
-        match result {
-            Err(_) => Ok(().into_lisp(env)?),
-            Ok(a) => Ok(a),
-        }
+        search_playlist(env, search_content, limit, page).await
     } else {
-        let result = search_song(env, search_content, limit, page).await;
-        match result {
-            Err(_) => Ok(().into_lisp(env)?),
-            Ok(a) => Ok(a),
+        search_song(env, search_content, limit, page).await
+    }
+}
+
+/// Convert the symbol TYPE into `SearchType`.
+fn symbol_to_search_type(symbol: EValue<'_>) -> EResult<Option<SearchType>> {
+    let name = symbol
+        .env
+        .call("symbol-name", [symbol])?
+        .into_rust::<String>()?;
+    Ok(match name.as_str() {
+        "song" => Some(SearchType::Song),
+        "album" => Some(SearchType::Album),
+        "artist" => Some(SearchType::Artist),
+        "playlist" => Some(SearchType::Collection),
+        "user" => Some(SearchType::User),
+        "mv" => Some(SearchType::MV),
+        "lyric" => Some(SearchType::Lyric),
+        "podcast" => Some(SearchType::Podcast),
+        "video" => Some(SearchType::Video),
+        "all" => Some(SearchType::All),
+        _ => None,
+    })
+}
+
+/// Get the `result` field of a search response, an empty result is returned when failed.
+fn search_result<T>(response: &ApiResponse) -> T
+where
+    T: DeserializeOwned + Default,
+{
+    response
+        .deserialize::<ResultResp<T>>()
+        .unwrap_or_default()
+        .result
+        .unwrap_or_default()
+}
+
+/// Report MESSAGE with `netease-cloud-music-error'.
+pub(crate) fn error<'a>(env: &'a Env, message: String) -> EResult<EValue<'a>> {
+    env.call("netease-cloud-music-error", [message.into_lisp(env)?])
+}
+
+/// Return the name of the first artist.
+pub(crate) fn first_artist_name(artists: &[Artist]) -> String {
+    artists
+        .first()
+        .and_then(|artist| artist.name.to_owned())
+        .unwrap_or_default()
+}
+
+/// Convert ITEMS into a Lisp list with CONVERTER.
+pub(crate) fn items_to_lisp<'a, T, F>(
+    env: &'a Env,
+    items: &[T],
+    converter: F,
+) -> EResult<EValue<'a>>
+where
+    F: Fn(&'a Env, &T) -> EResult<EValue<'a>>,
+{
+    let mut result = Vec::<EValue<'_>>::new();
+    for item in items.iter() {
+        result.push(converter(env, item)?);
+    }
+    env.list(&result)
+}
+
+/// (id name artist)
+pub(crate) fn song_to_lisp<'a>(env: &'a Env, song: &Song) -> EResult<EValue<'a>> {
+    env.list((
+        song.id as i64,
+        song.name.to_owned(),
+        first_artist_name(&song.artists),
+    ))
+}
+
+/// (id name artist)
+pub(crate) fn album_to_lisp<'a>(env: &'a Env, album: &Album) -> EResult<EValue<'a>> {
+    env.list((
+        album.id as i64,
+        album.name.to_owned().unwrap_or_default(),
+        album
+            .artist
+            .as_ref()
+            .and_then(|artist| artist.name.to_owned())
+            .unwrap_or_default(),
+    ))
+}
+
+/// (id name)
+pub(crate) fn artist_to_lisp<'a>(env: &'a Env, artist: &Artist) -> EResult<EValue<'a>> {
+    env.list((artist.id as i64, artist.name.to_owned().unwrap_or_default()))
+}
+
+/// (id name)
+pub(crate) fn playlist_to_lisp<'a>(env: &'a Env, playlist: &Playlist) -> EResult<EValue<'a>> {
+    env.list((playlist.id as i64, playlist.name.to_owned()))
+}
+
+/// (id nickname)
+pub(crate) fn user_to_lisp<'a>(env: &'a Env, user: &UserProfile) -> EResult<EValue<'a>> {
+    env.list((user.user_id as i64, user.nickname.to_owned()))
+}
+
+/// (id name artist duration)
+pub(crate) fn mv_to_lisp<'a>(env: &'a Env, mv: &MV) -> EResult<EValue<'a>> {
+    env.list((
+        mv.id as i64,
+        mv.name.to_owned(),
+        mv.artist_name.to_owned(),
+        mv.duration as i64,
+    ))
+}
+
+/// (id name artist snippet ((start end) ...)), the ranges are the highlighted parts of snippet.
+fn lyric_song_to_lisp<'a>(env: &'a Env, song: &LyricSong) -> EResult<EValue<'a>> {
+    let lyrics = song.lyrics.to_owned().unwrap_or_default();
+    let mut ranges = Vec::<EValue<'_>>::new();
+    for range in lyrics.range.iter() {
+        ranges.push(env.list((range.first as i64, range.second as i64))?);
+    }
+    env.list((
+        song.song.id as i64,
+        song.song.name.to_owned(),
+        first_artist_name(&song.song.artists),
+        lyrics.txt,
+        env.list(&ranges)?,
+    ))
+}
+
+/// (id name dj)
+fn podcast_to_lisp<'a>(env: &'a Env, podcast: &Podcast) -> EResult<EValue<'a>> {
+    env.list((
+        podcast.id as i64,
+        podcast.name.to_owned(),
+        podcast.dj.nickname.to_owned(),
+    ))
+}
+
+/// (vid title creator duration)
+fn video_to_lisp<'a>(env: &'a Env, video: &Video) -> EResult<EValue<'a>> {
+    env.list((
+        video.vid.to_owned(),
+        video.title.to_owned(),
+        video
+            .creator
+            .first()
+            .map(|creator| creator.user_name.to_owned())
+            .unwrap_or_default(),
+        video.duration as i64,
+    ))
+}
+
+/// Group the composite search result by section, in the order given by the server.
+fn search_all_to_lisp<'a>(env: &'a Env, result: &SearchResultAll) -> EResult<EValue<'a>> {
+    let mut order = result.order.to_owned();
+    if order.is_empty() {
+        order = ["song", "playList", "artist", "album", "user", "video"]
+            .iter()
+            .map(|section| section.to_string())
+            .collect();
+    }
+    remember_songs(&result.song.songs);
+    remember_playlists(&result.play_list.play_lists);
+
+    let mut sections = Vec::<EValue<'_>>::new();
+    for section in order.iter() {
+        let (name, items) = match section.as_str() {
+            "song" => (
+                "song",
+                items_to_lisp(env, &result.song.songs, song_to_lisp)?,
+            ),
+            "playList" => (
+                "playlist",
+                items_to_lisp(env, &result.play_list.play_lists, playlist_to_lisp)?,
+            ),
+            "artist" => (
+                "artist",
+                items_to_lisp(env, &result.artist.artists, artist_to_lisp)?,
+            ),
+            "album" => (
+                "album",
+                items_to_lisp(env, &result.album.albums, album_to_lisp)?,
+            ),
+            "user" => (
+                "user",
+                items_to_lisp(env, &result.user.users, user_to_lisp)?,
+            ),
+            "video" => (
+                "video",
+                items_to_lisp(env, &result.video.videos, video_to_lisp)?,
+            ),
+            _ => continue,
+        };
+        if items.is_not_nil() {
+            sections.push(env.list((env.intern(name)?, items))?);
         }
     }
+    env.list(&sections)
 }
 
-// TODO: Notice format about the let result ....
-/// Get the playlists of the user whose user id is UID.
+/// Convert PAGE into (items total has-more), total is nil when it's unknown.
+pub(crate) fn page_to_lisp<'a, T, F>(
+    env: &'a Env,
+    page: &Page<T>,
+    converter: F,
+) -> EResult<EValue<'a>>
+where
+    F: Fn(&'a Env, &T) -> EResult<EValue<'a>>,
+{
+    env.list((
+        items_to_lisp(env, &page.items, converter)?,
+        page.total.map(|total| total as i64),
+        page.has_more,
+    ))
+}
+
+/// Search CONTENT with SEARCH_TYPE and convert the typed result into Lisp.
+async fn search_by_type<'a>(
+    env: &'a Env,
+    content: String,
+    search_type: SearchType,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let api = get_api();
+    let offset = page_offset(limit, page);
+    let limit = limit as usize;
+
+    match search_type {
+        SearchType::Song => match search_song_page(&content, offset, limit).await {
+            Ok(page) => page_to_lisp(env, &page, song_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Album => match api
+            .cloud_search_page::<SearchAlbumResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, album_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Artist => match api
+            .cloud_search_page::<SearchArtistResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, artist_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Collection => match search_playlist_page(&content, offset, limit).await {
+            Ok(page) => page_to_lisp(env, &page, playlist_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::User => match api
+            .cloud_search_page::<SearchUserResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, user_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::MV => match api
+            .cloud_search_page::<SearchMVResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, mv_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Lyric => match api
+            .cloud_search_page::<SearchLyricResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, lyric_song_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Podcast => match api
+            .cloud_search_page::<SearchPodcastResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, podcast_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::Video => match api
+            .cloud_search_page::<SearchVideoResp>(&content, search_type, offset, limit)
+            .await
+        {
+            Ok(page) => page_to_lisp(env, &page, video_to_lisp),
+            Err(e) => error(env, format!("Failed to search: {}", e)),
+        },
+        SearchType::All => {
+            let response = api
+                .cloud_search(
+                    &content,
+                    Some(json!({ "limit": limit,
+                             "offset": offset,
+                             "type": search_type
+                    })),
+                )
+                .await;
+            match response {
+                Ok(response) => {
+                    let result = search_result::<SearchResultAll>(&response);
+                    env.list((search_all_to_lisp(env, &result)?, (), false))
+                }
+                Err(e) => error(env, format!("Failed to search: {}", e)),
+            }
+        }
+    }
+}
+
+/// Search SEARCH_CONTENT with SEARCH_TYPE, which is one of the symbols:
+/// song, album, artist, playlist, user, mv, lyric, podcast, video and all.
+/// LIMIT is the limitation of each search, PAGE is the current search page.
+///
+/// Return (items total has-more), total is nil when the server doesn't tell it.
+/// Each kind has its own items:
+/// song:     (id name artist)
+/// album:    (id name artist)
+/// artist:   (id name)
+/// playlist: (id name)
+/// user:     (id nickname)
+/// mv:       (id name artist duration)
+/// lyric:    (id name artist snippet ((start end) ...))
+/// podcast:  (id name dj)
+/// video:    (vid title creator duration)
+/// all:      ((section items) ...), where section is one of the symbols above,
+///           the composite result isn't paginated.
+#[defun]
+#[tokio::main]
+pub async fn search_with_type<'a>(
+    search_content: String,
+    search_type: EValue<'a>,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let env = search_type.env;
+    match symbol_to_search_type(search_type)? {
+        None => error(env, "Unknown search type!".to_string()),
+        Some(search_type) => search_by_type(env, search_content, search_type, limit, page).await,
+    }
+}
+
+/// Get all the playlists of the user whose user id is UID.
 #[defun]
 #[tokio::main]
 pub async fn user_playlist(env: &Env, uid: i64) -> EResult<EValue<'_>> {
     let api = get_api();
-    let result =
-        PlaylistsInfo::from_data(api.user_playlist(uid as usize, None).await.unwrap().data());
-    let playlists = result.playlist.as_array().unwrap();
-
-    if playlists.len() == 0 {
-        env.call(
-            "netease-cloud-music-error",
-            ["The uid cannot be found!".to_string().into_lisp(env)?],
-        )
+    let pages: Vec<Page<Playlist>> = api
+        .user_playlist_pages(uid as usize, 100)
+        .try_collect()
+        .await
+        .unwrap();
+    let playlists = pages
+        .into_iter()
+        .flat_map(|page| page.items)
+        .collect::<Vec<_>>();
+
+    if playlists.is_empty() {
+        error(env, "The uid cannot be found!".to_string())
     } else {
-        // NOTE: Maybe now I'll not use `more` to know whether there're other results.
-        Ok(
-            // result.more
-            extract_playlists_info(env, result.playlist).unwrap(),
-        )
+        remember_playlists(&playlists);
+        items_to_lisp(env, &playlists, playlist_to_lisp)
     }
 }
 
+/// Get the songs of the artist whose id is AID.
+/// If BY-TIME is non-nil, sort the songs by time. Otherwise sort them by popularity.
+/// LIMIT is the number of songs in each page, PAGE is the current page.
+/// Return (songs total has-more).
+#[defun]
+#[tokio::main]
+pub async fn artist_songs<'a>(
+    aid: i64,
+    by_time: EValue<'a>,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let env = by_time.env;
+    let order = if by_time.is_not_nil() { "time" } else { "hot" };
+    let api = get_api();
+    let songs = api
+        .artist_songs_page(
+            aid as usize,
+            order,
+            page_offset(limit, page),
+            limit as usize,
+        )
+        .await
+        .unwrap();
+
+    remember_songs(&songs.items);
+    page_to_lisp(env, &songs, song_to_lisp)
+}
+
 /// Get lyrics of SID.
+/// Return (lyric translation), translation is an empty string if there's none.
+/// If the song has no lyrics, return nil.
 #[defun]
 #[tokio::main]
 pub async fn get_lyrics(env: &Env, sid: i64) -> EResult<EValue<'_>> {
     let api = get_api();
-    let lyrics = LyricsInfo::from_data(api.lyric(sid as usize).await.unwrap().data());
+    let lyrics = api
+        .lyric(sid as usize)
+        .await
+        .unwrap()
+        .deserialize::<LyricResp>()
+        .unwrap_or_default();
     match lyrics.lrc {
-        JValue::Null => Ok(().into_lisp(env)?),
-        _ => Ok(env.list((
-            lyrics
-                .lrc
-                .get("lyric")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_owned(),
-            lyrics
-                .tlyric
-                .get("lyric")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_owned(),
-        ))?),
+        None => ().into_lisp(env),
+        Some(lrc) => env.list((
+            lrc.lyric,
+            lyrics.tlyric.map(|tlyric| tlyric.lyric).unwrap_or_default(),
+        )),
     }
 }
 
@@ -602,7 +1133,7 @@ pub async fn create_comment(sid: i64, content: String, cid: Option<i64>) -> ERes
 pub async fn song_url(sid: i64) -> EResult<Option<String>> {
     let api = get_api();
     let url = api
-        .song_url(&[sid as usize].to_vec())
+        .song_url(&[sid as usize], Quality::default())
         .await
         .unwrap()
         .deserialize_to_implict();
@@ -625,27 +1156,185 @@ pub async fn song_url(sid: i64) -> EResult<Option<String>> {
     }
 }
 
+/// Convert the symbol QUALITY into `Quality`, nil means the default quality.
+pub(crate) fn symbol_to_quality(quality: EValue<'_>) -> EResult<Option<Quality>> {
+    if !quality.is_not_nil() {
+        return Ok(Some(Quality::default()));
+    }
+    let level = quality
+        .env
+        .call("symbol-name", [quality])?
+        .into_rust::<String>()?;
+    Ok(Quality::from_level(&level))
+}
+
+/// Get the urls of songs whose ids are SIDS in one request.
+/// QUALITY is one of the symbols standard, higher, exhigh, lossless and hires,
+/// nil means lossless.
+/// Return ((id url br size type md5 expiration trial) ...), url is nil if the song can't be played.
+/// type is the file type like "mp3" or "flac", expiration is the seconds the url is valid for.
+/// trial is (start end) in seconds when only a preview of the song can be played, otherwise nil.
 #[defun]
 #[tokio::main]
-pub async fn get_playlist_songs(env: &Env, pid: i64) -> EResult<EValue<'_>> {
+pub async fn song_urls<'a>(sids: EValue<'a>, quality: EValue<'a>) -> EResult<EValue<'a>> {
+    let env = sids.env;
+    let quality = match symbol_to_quality(quality)? {
+        Some(quality) => quality,
+        None => return error(env, "Unknown quality!".to_string()),
+    };
+
+    let ids = list_to_vec::<i64>(sids)?
+        .into_iter()
+        .map(|id| id as usize)
+        .collect::<Vec<_>>();
     let api = get_api();
-    let songs = PlaylistsInfo::from_data(
-        api.playlist_detail(pid as usize, None)
+    let urls = api
+        .song_url_v1(&ids, quality)
+        .await
+        .unwrap()
+        .deserialize::<SongUrlResp>()
+        .unwrap_or_default();
+
+    let mut result = Vec::<EValue<'_>>::new();
+    for url in urls.data.iter() {
+        let trial = match url.free_trial_info {
+            Some(ref trial) => env.list((trial.start as i64, trial.end as i64))?,
+            None => ().into_lisp(env)?,
+        };
+        result.push(env.list((
+            url.id as i64,
+            url.url.to_owned(),
+            url.br as i64,
+            url.size as i64,
+            url.file_type.to_owned(),
+            url.md5.to_owned(),
+            url.expi as i64,
+            trial,
+        ))?);
+    }
+    env.list(&result)
+}
+
+/// Return why the song can't be played, or `None` if it's playable.
+/// The url refused while the privilege says the song can be played
+/// usually means the song is locked in the region.
+pub(crate) fn unavailable_reason(
+    url: Option<&SongUrl>,
+    privilege: Option<&Privilege>,
+) -> Option<&'static str> {
+    if let Some(url) = url {
+        if url.url.is_some() {
+            return url.free_trial_info.as_ref().map(|_| "trial");
+        }
+    }
+
+    match privilege {
+        Some(privilege) if privilege.st < 0 => Some("copyright"),
+        Some(privilege) if privilege.fee == 1 || privilege.fee == 4 => Some("vip"),
+        Some(privilege) if privilege.pl > 0 => Some("region"),
+        _ => Some("copyright"),
+    }
+}
+
+/// The number of songs checked by one request.
+const CHECK_CHUNK_SIZE: usize = 200;
+
+/// The urls of the songs whose ids are IDS by their ids, for checking whether they're
+/// playable. The ids are checked in chunks.
+pub(crate) async fn check_urls(ids: &[usize]) -> Result<HashMap<usize, SongUrl>, String> {
+    let mut urls = HashMap::new();
+    for chunk in ids.chunks(CHECK_CHUNK_SIZE) {
+        let result = get_api()
+            .check_music(chunk, Quality::default().br())
             .await
-            .unwrap()
-            .data(),
-    );
+            .map_err(|e| e.to_string())?
+            .deserialize::<SongUrlResp>()
+            .map_err(|e| e.to_string())?;
+        urls.extend(result.data.into_iter().map(|url| (url.id, url)));
+    }
+    Ok(urls)
+}
 
-    if songs.code != 200 {
-        return env.call(
-            "netease-cloud-music-error",
-            ["The pid can not fount!".to_string().into_lisp(env)?],
-        );
+/// Annotate TRACKS with their availability, TRACKS is a list of songs like
+/// ((id name artist) ...), and each song is appended with (playable reason).
+/// reason is nil for playable songs, otherwise one of the symbols:
+/// copyright: no copyright or the song has been removed.
+/// vip:       only VIP users or buyers of the album can play it.
+/// trial:     only a preview can be played, the song is still playable.
+/// region:    the song is locked in the current region.
+#[defun]
+#[tokio::main]
+pub async fn check_songs(tracks: EValue<'_>) -> EResult<EValue<'_>> {
+    let env = tracks.env;
+    let tracks = list_to_vec::<EValue<'_>>(tracks)?;
+    let mut ids = Vec::<usize>::new();
+    for track in tracks.iter() {
+        ids.push(env.call("car", [*track])?.into_rust::<i64>()? as usize);
+    }
+    if ids.is_empty() {
+        return ().into_lisp(env);
     }
 
-    let songs = songs.playlist.get("tracks").unwrap();
-    if songs.as_array().unwrap().len() == 0 {
+    let urls = match check_urls(&ids).await {
+        Ok(urls) => urls,
+        Err(e) => return error(env, format!("Failed to check the songs: {}", e)),
+    };
+    let details = match get_api().song_detail(&ids).await {
+        Ok(details) => details,
+        Err(e) => return error(env, format!("Failed to check the songs: {}", e)),
+    };
+    let privileges = details
+        .privileges
+        .iter()
+        .map(|privilege| (privilege.id, privilege))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = Vec::<EValue<'_>>::new();
+    for (track, id) in tracks.iter().zip(ids.iter()) {
+        let url = urls.get(id);
+        let privilege = privileges.get(id).copied();
+        let reason = unavailable_reason(url, privilege);
+        let playable = reason.is_none() || reason == Some("trial");
+        let reason = match reason {
+            Some(reason) => env.intern(reason)?,
+            None => ().into_lisp(env)?,
+        };
+        result.push(env.call("append", (*track, env.list((playable, reason))?))?);
+    }
+    env.list(&result)
+}
+
+/// Get all the songs of the playlist whose id is PID.
+/// The playlist detail only contains part of the tracks, so the complete
+/// track list is fetched with the song detail api.
+#[defun]
+#[tokio::main]
+pub async fn get_playlist_songs(env: &Env, pid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let detail = api
+        .playlist_detail(pid as usize, None)
+        .await
+        .unwrap()
+        .deserialize::<PlaylistDetailResp>()
+        .unwrap_or_default();
+
+    let playlist = match detail.playlist {
+        Some(playlist) if detail.code == 200 => playlist,
+        _ => return error(env, "The pid can not fount!".to_string()),
+    };
+
+    if playlist.track_ids.is_empty() {
         return ().into_lisp(env);
     }
-    Ok(extract_songs_info(env, songs.to_owned()).unwrap())
+    let ids = playlist
+        .track_ids
+        .iter()
+        .map(|track| track.id)
+        .collect::<Vec<_>>();
+    let songs = match api.song_detail(&ids).await {
+        Ok(songs) => songs,
+        Err(e) => return error(env, format!("Failed to get the songs: {}", e)),
+    };
+    remember_songs(&songs.songs);
+    items_to_lisp(env, &songs.songs, song_to_lisp)
 }
@@ -0,0 +1,282 @@
+// Back up an account and restore it to another one.
+
+// Copyright (C) 2022 SpringHan
+
+use std::path::Path;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env};
+use futures::{stream, StreamExt, TryStreamExt};
+use ncmapi::backup::{missing, Backup, RestoreState};
+use ncmapi::playlist_sync::SyncPlan;
+use ncmapi::types::Playlist;
+use ncmapi::{NcmApi, Page};
+
+use crate::api::{current_user, error, get_api, LIKED_PLAYLIST_TYPE};
+use crate::sync::{apply_plan, create_remote_playlist, playlist_ids};
+
+/// The number of playlists whose songs are fetched at the same time.
+const BACKUP_CONCURRENCY: usize = 4;
+
+/// The number of items in each page of the user playlists and the subscriptions.
+const PAGE_SIZE: usize = 100;
+
+/// The changes made by a restore.
+#[derive(Default)]
+struct RestoreCounts {
+    playlists: usize,
+    liked: usize,
+    albums: usize,
+    artists: usize,
+    subscribed: usize,
+}
+
+/// The playlists of the user UID, split into the created ones and the subscribed ones.
+async fn user_playlists(
+    api: &NcmApi,
+    uid: usize,
+) -> Result<(Vec<Playlist>, Vec<Playlist>), String> {
+    let pages: Vec<Page<Playlist>> = api
+        .user_playlist_pages(uid, PAGE_SIZE)
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(pages
+        .into_iter()
+        .flat_map(|page| page.items)
+        .partition(|playlist| {
+            playlist
+                .creator
+                .as_ref()
+                .is_none_or(|creator| creator.user_id == uid)
+        }))
+}
+
+async fn make_backup(api: &NcmApi) -> Result<Backup, String> {
+    let user = current_user().await?;
+    let (created, subscribed) = user_playlists(api, user.user_id).await?;
+    let playlists = stream::iter(created.iter())
+        .map(|playlist| api.playlist_with_tracks(playlist.id))
+        .buffered(BACKUP_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| format!("failed to get the playlists: {}", e))?;
+    let liked = api
+        .liked_song_ids(user.user_id)
+        .await
+        .map_err(|e| format!("failed to get the liked songs: {}", e))?;
+    let albums: Vec<Page<_>> = api
+        .album_sublist_pages(PAGE_SIZE)
+        .try_collect()
+        .await
+        .map_err(|e| format!("failed to get the subscribed albums: {}", e))?;
+    let artists: Vec<Page<_>> = api
+        .artist_sublist_pages(PAGE_SIZE)
+        .try_collect()
+        .await
+        .map_err(|e| format!("failed to get the subscribed artists: {}", e))?;
+
+    let mut backup = Backup::new(user);
+    backup.playlists = playlists;
+    backup.subscribed_playlists = subscribed;
+    backup.liked = liked;
+    backup.albums = albums.into_iter().flat_map(|page| page.items).collect();
+    backup.artists = artists.into_iter().flat_map(|page| page.items).collect();
+    Ok(backup)
+}
+
+fn check(result: ncmapi::ApiResponse, what: &str) -> Result<(), String> {
+    let code = result.deserialize_to_implict().code;
+    if code == 200 {
+        Ok(())
+    } else {
+        Err(format!("{} failed with code {}", what, code))
+    }
+}
+
+/// Restore BACKUP in DIR to the current user. Every step only makes the changes the
+/// account lacks, and the recreated playlists are remembered in the restore state,
+/// so restoring again continues an interrupted restore without duplicating anything.
+async fn restore(api: &NcmApi, dir: &Path, backup: &Backup) -> Result<RestoreCounts, String> {
+    let user = current_user().await?;
+    let mut state = RestoreState::load(dir, user.user_id);
+    let mut counts = RestoreCounts::default();
+    let (created, subscribed) = user_playlists(api, user.user_id).await?;
+
+    for playlist in backup
+        .playlists
+        .iter()
+        // the liked songs are restored by liking them
+        .filter(|playlist| playlist.special_type != LIKED_PLAYLIST_TYPE)
+    {
+        // Only the playlists recreated by a restore are synced, the existing ones of the
+        // same names belong to the user and are left alone.
+        let existing = state
+            .playlists
+            .get(&playlist.id)
+            .filter(|pid| created.iter().any(|p| p.id == **pid))
+            .copied();
+        let pid = match existing {
+            Some(pid) => pid,
+            None => {
+                let pid = create_remote_playlist(&playlist.name, playlist.privacy == 10).await?;
+                state.playlists.insert(playlist.id, pid);
+                state.save(dir).map_err(|e| e.to_string())?;
+                pid
+            }
+        };
+
+        let current = playlist_ids(pid).await?;
+        let target = playlist
+            .track_ids
+            .iter()
+            .map(|track| track.id)
+            .collect::<Vec<_>>();
+        let plan = SyncPlan::new(&current, &target);
+        if existing.is_none() || !plan.is_empty() {
+            counts.playlists += 1;
+        }
+        apply_plan(pid, &plan)
+            .await
+            .map_err(|e| format!("failed to restore the playlist {}: {}", playlist.name, e))?;
+    }
+
+    let liked = api
+        .liked_song_ids(user.user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    // The latest liked song is the first one, so like the songs from the oldest one.
+    for id in missing(&liked, &backup.liked).into_iter().rev() {
+        check(
+            api.like(id, None).await.map_err(|e| e.to_string())?,
+            "liking the song",
+        )?;
+        counts.liked += 1;
+    }
+
+    let albums: Vec<Page<_>> = api
+        .album_sublist_pages(PAGE_SIZE)
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    let albums = albums
+        .into_iter()
+        .flat_map(|page| page.items)
+        .map(|album| album.id)
+        .collect::<Vec<_>>();
+    let wanted = backup
+        .albums
+        .iter()
+        .map(|album| album.id)
+        .collect::<Vec<_>>();
+    for id in missing(&albums, &wanted).into_iter().rev() {
+        check(
+            api.album_sub(id, 1).await.map_err(|e| e.to_string())?,
+            "subscribing the album",
+        )?;
+        counts.albums += 1;
+    }
+
+    let artists: Vec<Page<_>> = api
+        .artist_sublist_pages(PAGE_SIZE)
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    let artists = artists
+        .into_iter()
+        .flat_map(|page| page.items)
+        .map(|artist| artist.id)
+        .collect::<Vec<_>>();
+    let wanted = backup
+        .artists
+        .iter()
+        .map(|artist| artist.id)
+        .collect::<Vec<_>>();
+    for id in missing(&artists, &wanted).into_iter().rev() {
+        check(
+            api.artist_sub(id, 1).await.map_err(|e| e.to_string())?,
+            "subscribing the artist",
+        )?;
+        counts.artists += 1;
+    }
+
+    let subscribed = subscribed
+        .iter()
+        .map(|playlist| playlist.id)
+        .collect::<Vec<_>>();
+    let wanted = backup
+        .subscribed_playlists
+        .iter()
+        .filter(|playlist| {
+            playlist
+                .creator
+                .as_ref()
+                .is_none_or(|creator| creator.user_id != user.user_id)
+        })
+        .map(|playlist| playlist.id)
+        .collect::<Vec<_>>();
+    for id in missing(&subscribed, &wanted).into_iter().rev() {
+        check(
+            api.playlist_subscribe(id, true)
+                .await
+                .map_err(|e| e.to_string())?,
+            "subscribing the playlist",
+        )?;
+        counts.subscribed += 1;
+    }
+
+    Ok(counts)
+}
+
+/// Back up the playlists, liked songs and subscriptions of the current user into a new
+/// directory under DIRECTORY, the older backups are kept.
+/// Return (directory playlists liked albums artists subscribed), the directory of the
+/// backup and the numbers of the backed up items.
+#[defun]
+#[tokio::main]
+pub async fn backup_account(env: &Env, directory: String) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let backup = match make_backup(api).await {
+        Ok(backup) => backup,
+        Err(e) => return error(env, format!("Failed to back up the account: {}", e)),
+    };
+    let dir = match backup.save(Path::new(&directory)) {
+        Ok(dir) => dir,
+        Err(e) => return error(env, format!("Failed to save the backup: {}", e)),
+    };
+    env.list((
+        dir.to_string_lossy().to_string(),
+        backup.playlists.len() as i64,
+        backup.liked.len() as i64,
+        backup.albums.len() as i64,
+        backup.artists.len() as i64,
+        backup.subscribed_playlists.len() as i64,
+    ))
+}
+
+/// Restore the backup in DIRECTORY to the current user, DIRECTORY is either a backup
+/// made by `backup-account' or the directory of the backups, where the latest is used.
+/// The playlists are recreated with their songs in order, the existing playlists aren't
+/// changed even if they have the same names, the songs are liked and the albums, artists
+/// and playlists are subscribed. Only the missing things are restored,
+/// so it's safe to restore again, which continues an interrupted restore.
+/// Return (playlists liked albums artists subscribed), the numbers of the restored items.
+#[defun]
+#[tokio::main]
+pub async fn restore_account(env: &Env, directory: String) -> EResult<EValue<'_>> {
+    let (dir, backup) = match Backup::load(Path::new(&directory)) {
+        Ok(backup) => backup,
+        Err(e) => return error(env, format!("Failed to load the backup: {}", e)),
+    };
+    match restore(get_api(), &dir, &backup).await {
+        Ok(counts) => env.list((
+            counts.playlists as i64,
+            counts.liked as i64,
+            counts.albums as i64,
+            counts.artists as i64,
+            counts.subscribed as i64,
+        )),
+        Err(e) => error(env, format!("Failed to restore the account: {}", e)),
+    }
+}
@@ -0,0 +1,295 @@
+// Browse playlists by category and the charts.
+
+// Copyright (C) 2022 SpringHan
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::types::{
+    Playlist, PlaylistCatlistResp, PlaylistHotResp, TopAlbumResp, TopSongResp, ToplistArtistResp,
+    ToplistResp,
+};
+use ncmapi::Area;
+
+use crate::api::{
+    album_to_lisp, error, get_api, items_to_lisp, page_offset, page_to_lisp, song_to_lisp,
+};
+use crate::library::{remember_playlists, remember_songs};
+
+/// (id name creator track-count play-count)
+fn playlist_summary_to_lisp<'a>(env: &'a Env, playlist: &Playlist) -> EResult<EValue<'a>> {
+    env.list((
+        playlist.id as i64,
+        playlist.name.to_owned(),
+        playlist
+            .creator
+            .as_ref()
+            .map(|creator| creator.nickname.to_owned()),
+        playlist.track_count as i64,
+        playlist.play_count as i64,
+    ))
+}
+
+/// Get the category tree of the playlists.
+/// Return ((category (tag hot) ...) ...), hot is non-nil for the hot tags.
+/// The first category only contains the tag meaning all the playlists.
+#[defun]
+#[tokio::main]
+pub async fn playlist_categories(env: &Env) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.playlist_catlist().await {
+        Ok(response) => response
+            .deserialize::<PlaylistCatlistResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the playlist categories: {}", e)),
+    };
+
+    let mut categories = result
+        .categories
+        .iter()
+        .filter_map(|(index, name)| Some((index.parse::<usize>().ok()?, name)))
+        .collect::<Vec<_>>();
+    categories.sort();
+
+    let mut tree = vec![env.list((
+        result.all.name.to_owned(),
+        env.list([env.list((result.all.name.to_owned(), true))?])?,
+    ))?];
+    for (index, name) in categories.iter() {
+        let mut tags = Vec::<EValue<'_>>::new();
+        for tag in result.sub.iter().filter(|tag| tag.category == *index) {
+            tags.push(env.list((tag.name.to_owned(), tag.hot))?);
+        }
+        tree.push(env.list((name.to_string(), env.list(&tags)?))?);
+    }
+    env.list(&tree)
+}
+
+/// Get the hot playlist tags, return a list of tag names.
+#[defun]
+#[tokio::main]
+pub async fn hot_playlist_tags(env: &Env) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.playlist_hot().await {
+        Ok(response) => response
+            .deserialize::<PlaylistHotResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the hot playlist tags: {}", e)),
+    };
+
+    let mut tags = Vec::<EValue<'_>>::new();
+    for tag in result.tags.iter() {
+        tags.push(tag.name.to_owned().into_lisp(env)?);
+    }
+    env.list(&tags)
+}
+
+/// Get the playlists tagged with TAG, the tag "全部" means all the playlists.
+/// If NEWEST is non-nil, sort the playlists by time. Otherwise sort them by popularity.
+/// LIMIT is the number of playlists in each page, PAGE is the current page.
+/// Return (((id name creator track-count play-count) ...) total has-more).
+#[defun]
+#[tokio::main]
+pub async fn category_playlists<'a>(
+    tag: String,
+    newest: EValue<'a>,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let env = newest.env;
+    let order = if newest.is_not_nil() { "new" } else { "hot" };
+    let api = get_api();
+    let page = match api
+        .top_playlist_page(&tag, order, page_offset(limit, page), limit as usize)
+        .await
+    {
+        Ok(page) => page,
+        Err(e) => return error(env, format!("Failed to get the playlists: {}", e)),
+    };
+    remember_playlists(&page.items);
+    page_to_lisp(env, &page, playlist_summary_to_lisp)
+}
+
+/// Get the high quality playlists tagged with TAG.
+/// LIMIT is the number of playlists in each page, BEFORE is the cursor returned with
+/// the previous page, nil for the first page.
+/// Return (((id name creator track-count play-count) ...) before has-more).
+#[defun]
+#[tokio::main]
+pub async fn highquality_playlists(
+    env: &Env,
+    tag: String,
+    limit: i64,
+    before: Option<i64>,
+) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let page = match api
+        .top_playlist_highquality_page(&tag, before.map(|before| before as u64), limit as usize)
+        .await
+    {
+        Ok(page) => page,
+        Err(e) => return error(env, format!("Failed to get the playlists: {}", e)),
+    };
+    remember_playlists(&page.items);
+    env.list((
+        items_to_lisp(env, &page.items, playlist_summary_to_lisp)?,
+        page.next_cursor().map(|cursor| cursor as i64),
+        page.has_more,
+    ))
+}
+
+/// Get the playlists related to the playlist whose id is PID.
+/// Return ((id name creator) ...).
+#[defun]
+#[tokio::main]
+pub async fn related_playlists(env: &Env, pid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let playlists = api.related_playlist(pid as usize).await.unwrap_or_default();
+
+    let mut result = Vec::<EValue<'_>>::new();
+    for playlist in playlists.iter() {
+        result.push(env.list((
+            playlist.id as i64,
+            playlist.name.to_owned(),
+            playlist.creator.nickname.to_owned(),
+        ))?);
+    }
+    env.list(&result)
+}
+
+/// Convert AREA symbol into `Area`, nil means all the areas.
+fn symbol_to_area(area: EValue<'_>) -> EResult<Option<Area>> {
+    if !area.is_not_nil() {
+        return Ok(Some(Area::All));
+    }
+    let area = area
+        .env
+        .call("symbol-name", [area])?
+        .into_rust::<String>()?;
+    Ok(match area.as_str() {
+        "all" => Some(Area::All),
+        "chinese" => Some(Area::Chinese),
+        "western" => Some(Area::Western),
+        "korean" => Some(Area::Korean),
+        "japanese" => Some(Area::Japanese),
+        _ => None,
+    })
+}
+
+/// Get all the official charts.
+/// Return ((id name update-frequency cover description) ...).
+#[defun]
+#[tokio::main]
+pub async fn toplists(env: &Env) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.toplist().await {
+        Ok(response) => response.deserialize::<ToplistResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the charts: {}", e)),
+    };
+
+    let mut toplists = Vec::<EValue<'_>>::new();
+    for toplist in result.list.iter() {
+        toplists.push(env.list((
+            toplist.id as i64,
+            toplist.name.to_owned(),
+            toplist.update_frequency.to_owned(),
+            toplist.cover_img_url.to_owned(),
+            toplist.description.to_owned(),
+        ))?);
+    }
+    env.list(&toplists)
+}
+
+/// Get all the songs of the chart whose id is ID, in the same format as the playlists.
+#[defun]
+#[tokio::main]
+pub async fn toplist_songs(env: &Env, id: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    match api.toplist_songs(id as usize).await {
+        Ok(songs) => {
+            remember_songs(&songs);
+            items_to_lisp(env, &songs, song_to_lisp)
+        }
+        Err(_) => error(env, "The toplist can not found!".to_string()),
+    }
+}
+
+/// Get the artist chart of AREA, return ((id name score last-rank) ...).
+/// AREA is one of the symbols chinese, western, korean and japanese, nil means chinese.
+#[defun]
+#[tokio::main]
+pub async fn toplist_artists(area: EValue<'_>) -> EResult<EValue<'_>> {
+    let env = area.env;
+    let area = match symbol_to_area(area)? {
+        Some(area) => area,
+        None => return error(env, "Unknown area!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api.toplist_artist(area).await {
+        Ok(response) => response
+            .deserialize::<ToplistArtistResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the artist chart: {}", e)),
+    };
+
+    let mut artists = Vec::<EValue<'_>>::new();
+    for artist in result.list.artists.iter() {
+        artists.push(env.list((
+            artist.id as i64,
+            artist.name.to_owned(),
+            artist.score as i64,
+            artist.last_rank.map(|rank| rank as i64),
+        ))?);
+    }
+    env.list(&artists)
+}
+
+/// Get the new songs of AREA, in the same format as the playlists.
+/// AREA is one of the symbols all, chinese, western, korean and japanese, nil means all.
+#[defun]
+#[tokio::main]
+pub async fn new_songs(area: EValue<'_>) -> EResult<EValue<'_>> {
+    let env = area.env;
+    let area = match symbol_to_area(area)? {
+        Some(area) => area,
+        None => return error(env, "Unknown area!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api.top_song(area).await {
+        Ok(response) => response.deserialize::<TopSongResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the new songs: {}", e)),
+    };
+    remember_songs(&result.data);
+    items_to_lisp(env, &result.data, song_to_lisp)
+}
+
+/// Get the new albums of AREA, return ((id name artist) ...).
+/// AREA is the same as `new-songs'. If HOT is non-nil, only get the hot albums.
+#[defun]
+#[tokio::main]
+pub async fn new_albums<'a>(area: EValue<'a>, hot: EValue<'a>) -> EResult<EValue<'a>> {
+    let env = area.env;
+    let area = match symbol_to_area(area)? {
+        Some(area) => area,
+        None => return error(env, "Unknown area!".to_string()),
+    };
+
+    let api = get_api();
+    let album_type = if hot.is_not_nil() { "hot" } else { "new" };
+    let result = match api
+        .top_album(area, Some(serde_json::json!({ "type": album_type })))
+        .await
+    {
+        Ok(response) => response.deserialize::<TopAlbumResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the new albums: {}", e)),
+    };
+
+    let albums = result
+        .week_data
+        .into_iter()
+        .chain(result.month_data)
+        .collect::<Vec<_>>();
+    items_to_lisp(env, &albums, album_to_lisp)
+}
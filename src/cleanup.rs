@@ -0,0 +1,128 @@
+// Find and remove the duplicate and unplayable songs of playlists.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashMap;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::cleanup::{find_removals, removal_plan, Issue};
+use ncmapi::types::Song;
+
+use crate::api::{check_urls, error, first_artist_name, get_api, list_to_vec, unavailable_reason};
+use crate::sync::{apply_plan, playlist_ids};
+
+/// The songs at each position of IDS and whether they're playable.
+async fn check_playlist(ids: &[usize]) -> Result<(Vec<Song>, Vec<bool>), String> {
+    if ids.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let details = get_api()
+        .song_detail(ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    let urls = check_urls(ids).await?;
+
+    let songs = details
+        .songs
+        .into_iter()
+        .map(|song| (song.id, song))
+        .collect::<HashMap<_, _>>();
+    let privileges = details
+        .privileges
+        .iter()
+        .map(|privilege| (privilege.id, privilege))
+        .collect::<HashMap<_, _>>();
+    Ok(ids
+        .iter()
+        .map(|id| {
+            let song = songs.get(id).cloned().unwrap_or(Song {
+                id: *id,
+                ..Default::default()
+            });
+            let reason = unavailable_reason(urls.get(id), privileges.get(id).copied());
+            (song, reason.is_none() || reason == Some("trial"))
+        })
+        .unzip())
+}
+
+fn issue_symbol(issue: Issue) -> &'static str {
+    match issue {
+        Issue::Duplicate => "duplicate",
+        Issue::NearDuplicate => "near-duplicate",
+        Issue::Unplayable => "unplayable",
+    }
+}
+
+/// Find the songs to remove from the playlist whose id is PID: the songs added more than
+/// once, the other versions of the same recording, like the live and remastered ones,
+/// and the songs that can't be played any more. Nothing is changed.
+/// Return the proposal as ((index id name artist issue original) ...), index is the
+/// position of the song in the playlist, issue is one of the symbols duplicate,
+/// near-duplicate and unplayable, original is the position of the song it duplicates.
+/// Pass the confirmed ones to `apply-playlist-cleanup'.
+#[defun]
+#[tokio::main]
+pub async fn playlist_cleanup(env: &Env, pid: i64) -> EResult<EValue<'_>> {
+    let ids = match playlist_ids(pid as usize).await {
+        Ok(ids) => ids,
+        Err(e) => return error(env, e),
+    };
+    let (songs, playable) = match check_playlist(&ids).await {
+        Ok(result) => result,
+        Err(e) => return error(env, format!("Failed to check the songs: {}", e)),
+    };
+
+    let mut proposal = Vec::<EValue<'_>>::new();
+    for removal in find_removals(&songs, &playable) {
+        let song = &songs[removal.index];
+        proposal.push(env.list((
+            removal.index as i64,
+            song.id as i64,
+            song.name.to_owned(),
+            first_artist_name(&song.artists),
+            env.intern(issue_symbol(removal.issue))?,
+            removal.original.map(|original| original as i64),
+        ))?);
+    }
+    env.list(&proposal)
+}
+
+/// Remove the songs in REMOVALS from the playlist whose id is PID, REMOVALS are the
+/// entries of the proposal returned by `playlist-cleanup', only their index and id are
+/// used. Report an error without changing anything if the playlist has changed since.
+/// Return the number of the removed songs.
+#[defun]
+#[tokio::main]
+pub async fn apply_playlist_cleanup<'a>(
+    env: &'a Env,
+    pid: i64,
+    removals: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let mut indexes = Vec::new();
+    let mut expected = Vec::new();
+    for removal in list_to_vec::<EValue<'_>>(removals)? {
+        indexes.push(env.call("nth", (0, removal))?.into_rust::<i64>()? as usize);
+        expected.push(env.call("nth", (1, removal))?.into_rust::<i64>()? as usize);
+    }
+
+    // read past the response cache, a cached snapshot would hide the changes since
+    let current = match playlist_ids(pid as usize).await {
+        Ok(ids) => ids,
+        Err(e) => return error(env, e),
+    };
+    let unchanged = indexes
+        .iter()
+        .zip(expected.iter())
+        .all(|(index, id)| current.get(*index) == Some(id));
+    if !unchanged {
+        return error(env, "The playlist has changed, check it again!".to_string());
+    }
+
+    let plan = removal_plan(&current, &indexes);
+    match apply_plan(pid as usize, &plan).await {
+        Ok(_) => (indexes.len() as i64).into_lisp(env),
+        Err(e) => error(env, format!("Failed to clean up the playlist: {}", e)),
+    }
+}
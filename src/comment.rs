@@ -0,0 +1,280 @@
+// Comments of all kinds of resources.
+
+// Copyright (C) 2022 SpringHan
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::types::{Comment, CommentFloorResp, ResourceCommentsResp};
+use ncmapi::ResourceType;
+
+use crate::api::{error, get_api, items_to_lisp, page_offset, page_to_lisp};
+
+/// Convert the resource type symbol into `ResourceType`.
+fn symbol_to_resource_type(resource_type: EValue<'_>) -> EResult<Option<ResourceType>> {
+    let resource_type = resource_type
+        .env
+        .call("symbol-name", [resource_type])?
+        .into_rust::<String>()?;
+    Ok(match resource_type.as_str() {
+        "song" => Some(ResourceType::Song),
+        "mv" => Some(ResourceType::MV),
+        "playlist" => Some(ResourceType::Collection),
+        "album" => Some(ResourceType::Album),
+        "podcast" => Some(ResourceType::Podcast),
+        "video" => Some(ResourceType::Video),
+        "event" => Some(ResourceType::Moment),
+        _ => None,
+    })
+}
+
+/// Convert the sort symbol into the sort type of the comment api.
+fn symbol_to_sort_type(sort: EValue<'_>) -> EResult<Option<usize>> {
+    if !sort.is_not_nil() {
+        return Ok(Some(1));
+    }
+    let sort = sort
+        .env
+        .call("symbol-name", [sort])?
+        .into_rust::<String>()?;
+    Ok(match sort.as_str() {
+        "recommend" => Some(1),
+        "hot" => Some(2),
+        "time" => Some(3),
+        _ => None,
+    })
+}
+
+/// (id content nickname avatar time liked-count liked location replied reply-count)
+/// replied is (id nickname content location) of the comment replied to, or nil.
+/// content of the replied comment is nil if it has been deleted.
+fn comment_to_lisp<'a>(env: &'a Env, comment: &Comment) -> EResult<EValue<'a>> {
+    let replied = match comment.be_replied.first() {
+        Some(replied) => env.list((
+            replied.be_replied_comment_id as i64,
+            replied.user.nickname.to_owned(),
+            replied.content.to_owned(),
+            replied.ip_location.location.to_owned(),
+        ))?,
+        None => ().into_lisp(env)?,
+    };
+    env.list((
+        comment.comment_id as i64,
+        comment.content.to_owned(),
+        comment.user.nickname.to_owned(),
+        comment.user.avatar_url.to_owned(),
+        comment.time as i64,
+        comment.liked_count as i64,
+        comment.liked,
+        comment.ip_location.location.to_owned(),
+        replied,
+        comment
+            .show_floor_comment
+            .as_ref()
+            .map(|floor| floor.reply_count as i64)
+            .unwrap_or_default(),
+    ))
+}
+
+/// Get the comments of the resource whose type is TYPE and id is ID.
+/// TYPE is one of the symbols song, mv, playlist, album, podcast, video and event.
+/// SORT is one of the symbols recommend, hot and time, nil means recommend.
+/// LIMIT is the number of comments in each page, PAGE is the current page.
+/// When sorted by time, CURSOR must be the cursor returned with the previous page
+/// except for the first page, otherwise it's ignored.
+/// Return (comments total has-more cursor), each comment is
+/// (id content nickname avatar time liked-count liked location replied reply-count),
+/// replied is (id nickname content location) of the comment replied to, or nil.
+#[defun]
+#[tokio::main]
+pub async fn comments<'a>(
+    env: &'a Env,
+    resource_type: EValue<'a>,
+    id: i64,
+    sort: EValue<'a>,
+    limit: i64,
+    page: i64,
+    cursor: Option<i64>,
+) -> EResult<EValue<'a>> {
+    let resource_type = match symbol_to_resource_type(resource_type)? {
+        Some(resource_type) => resource_type,
+        None => return error(env, "Unknown resource type!".to_string()),
+    };
+    let sort_type = match symbol_to_sort_type(sort)? {
+        Some(sort_type) => sort_type,
+        None => return error(env, "Unknown sort mode!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api
+        .comment(
+            id as usize,
+            resource_type,
+            limit as usize,
+            page.max(1) as usize,
+            sort_type,
+            cursor.unwrap_or_default() as usize,
+            true,
+        )
+        .await
+    {
+        Ok(response) => {
+            response
+                .deserialize::<ResourceCommentsResp>()
+                .unwrap_or_default()
+                .data
+        }
+        Err(e) => return error(env, format!("Failed to get the comments: {}", e)),
+    };
+
+    let cursor = result.comments.last().map(|comment| comment.time as i64);
+    env.list((
+        items_to_lisp(env, &result.comments, comment_to_lisp)?,
+        result.total_count as i64,
+        result.has_more,
+        cursor,
+    ))
+}
+
+/// Get the hot comments of the resource whose type is TYPE and id is ID.
+/// TYPE is the same as `comments'.
+/// LIMIT is the number of comments in each page, PAGE is the current page.
+/// Return (comments total has-more), the comments are the same as `comments'.
+#[defun]
+#[tokio::main]
+pub async fn hot_comments<'a>(
+    env: &'a Env,
+    resource_type: EValue<'a>,
+    id: i64,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let resource_type = match symbol_to_resource_type(resource_type)? {
+        Some(resource_type) => resource_type,
+        None => return error(env, "Unknown resource type!".to_string()),
+    };
+
+    let api = get_api();
+    let page = match api
+        .comment_hot_page(
+            id as usize,
+            resource_type,
+            page_offset(limit, page),
+            limit as usize,
+        )
+        .await
+    {
+        Ok(page) => page,
+        Err(e) => return error(env, format!("Failed to get the hot comments: {}", e)),
+    };
+    page_to_lisp(env, &page, comment_to_lisp)
+}
+
+/// Get the replies to the comment whose id is CID, of the resource whose type is TYPE
+/// and id is ID. TYPE is the same as `comments'.
+/// LIMIT is the number of replies in each page, BEFORE is the cursor returned with
+/// the previous page, nil for the first page.
+/// Return (comments total has-more before), the comments are the same as `comments'.
+#[defun]
+#[tokio::main]
+pub async fn comment_floor<'a>(
+    env: &'a Env,
+    resource_type: EValue<'a>,
+    id: i64,
+    cid: i64,
+    limit: i64,
+    before: Option<i64>,
+) -> EResult<EValue<'a>> {
+    let resource_type = match symbol_to_resource_type(resource_type)? {
+        Some(resource_type) => resource_type,
+        None => return error(env, "Unknown resource type!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api
+        .comment_floor(
+            cid as usize,
+            id as usize,
+            resource_type,
+            Some(serde_json::json!({"limit": limit, "time": before.unwrap_or(-1)})),
+        )
+        .await
+    {
+        Ok(response) => {
+            response
+                .deserialize::<CommentFloorResp>()
+                .unwrap_or_default()
+                .data
+        }
+        Err(e) => return error(env, format!("Failed to get the replies: {}", e)),
+    };
+
+    env.list((
+        items_to_lisp(env, &result.comments, comment_to_lisp)?,
+        result.total_count as i64,
+        result.has_more,
+        if result.has_more {
+            Some(result.time)
+        } else {
+            None
+        },
+    ))
+}
+
+/// Like the comment whose id is CID, of the resource whose type is TYPE and id is ID.
+/// If UNLIKE is non-nil, unlike it instead. TYPE is the same as `comments'.
+#[defun]
+#[tokio::main]
+pub async fn like_comment<'a>(
+    env: &'a Env,
+    resource_type: EValue<'a>,
+    id: i64,
+    cid: i64,
+    unlike: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let resource_type = match symbol_to_resource_type(resource_type)? {
+        Some(resource_type) => resource_type,
+        None => return error(env, "Unknown resource type!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api
+        .comment_like(
+            id as usize,
+            resource_type,
+            cid as usize,
+            !unlike.is_not_nil(),
+        )
+        .await
+    {
+        Ok(response) => response.deserialize_to_implict(),
+        Err(e) => return error(env, format!("Failed to like the comment: {}", e)),
+    };
+    (result.code == 200).into_lisp(env)
+}
+
+/// Delete your comment whose id is CID, of the resource whose type is TYPE and id is ID.
+/// TYPE is the same as `comments'.
+#[defun]
+#[tokio::main]
+pub async fn delete_comment<'a>(
+    env: &'a Env,
+    resource_type: EValue<'a>,
+    id: i64,
+    cid: i64,
+) -> EResult<EValue<'a>> {
+    let resource_type = match symbol_to_resource_type(resource_type)? {
+        Some(resource_type) => resource_type,
+        None => return error(env, "Unknown resource type!".to_string()),
+    };
+
+    let api = get_api();
+    let result = match api
+        .comment_del(id as usize, resource_type, cid as usize)
+        .await
+    {
+        Ok(response) => response.deserialize_to_implict(),
+        Err(e) => return error(env, format!("Failed to delete the comment: {}", e)),
+    };
+    (result.code == 200).into_lisp(env)
+}
@@ -0,0 +1,333 @@
+// Download songs in the background.
+
+// Copyright (C) 2022 SpringHan
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use futures::{stream, StreamExt};
+use ncmapi::download::file_md5;
+use ncmapi::tag::read_song_id;
+use ncmapi::types::{Song, SongUrlResp};
+use ncmapi::Quality;
+
+use crate::api::{error, get_api, list_to_vec, symbol_to_quality};
+use crate::export::song_filename;
+use crate::tags::tag_song_file;
+
+/// The number of songs downloaded at the same time.
+const DOWNLOAD_CONCURRENCY: usize = 3;
+
+/// The file name template used when the template is nil.
+const DEFAULT_TEMPLATE: &str = "{artist} - {title}.{ext}";
+
+enum DownloadState {
+    Queued,
+    Downloading,
+    Finished,
+    /// Downloaded but failed to be tagged.
+    Untagged(String),
+    Failed(String),
+}
+
+struct Download {
+    id: usize,
+    name: String,
+    state: DownloadState,
+    /// The path of the file with the extension left as {ext}, which is known only when the
+    /// url is requested, to tell whether two songs would be downloaded into the same file.
+    target: PathBuf,
+    file: Option<PathBuf>,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+static DOWNLOADS: Mutex<Vec<Download>> = Mutex::new(Vec::new());
+
+/// Apply F to the download of the song whose id is ID.
+fn update_download(id: usize, f: impl FnOnce(&mut Download)) {
+    if let Some(download) = DOWNLOADS
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .find(|download| download.id == id)
+    {
+        f(download)
+    }
+}
+
+/// Insert the id of the song before the extension of TEMPLATE, to tell apart the songs
+/// whose file names are the same.
+pub(crate) fn disambiguate(template: &str) -> String {
+    match template.rfind(".{ext}") {
+        Some(index) => format!("{} ({{id}}){}", &template[..index], &template[index..]),
+        None => format!("{} ({{id}})", template),
+    }
+}
+
+/// Whether FILE is the song whose id is ID, either tagged with the id or downloaded but
+/// not tagged yet, which is told by the MD5 returned with the url.
+fn is_downloaded(file: &Path, id: usize, md5: Option<&str>) -> bool {
+    if matches!(read_song_id(file), Ok(Some(song_id)) if song_id == id) {
+        return true;
+    }
+    match md5.filter(|md5| !md5.is_empty()) {
+        Some(md5) => file_md5(file)
+            .map(|digest| digest.eq_ignore_ascii_case(md5))
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Queue SONGS to be downloaded into DIRECTORY with the file name TEMPLATE, the songs
+/// already queued or being downloaded are skipped. The id is added to the file name of
+/// a song if another download has the same one.
+/// Return the songs queued with their file name templates.
+fn queue_downloads(songs: Vec<Song>, directory: &Path, template: &str) -> Vec<(Song, String)> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    let mut queued = Vec::<(Song, String)>::new();
+    for song in songs.into_iter() {
+        if let Some(download) = downloads.iter().find(|download| download.id == song.id) {
+            if matches!(
+                download.state,
+                DownloadState::Queued | DownloadState::Downloading
+            ) {
+                continue;
+            }
+        }
+        downloads.retain(|download| download.id != song.id);
+
+        let index = queued.len() + 1;
+        let target =
+            |template: &str| directory.join(song_filename(template, &song, index, "{ext}"));
+        let mut template = template.to_owned();
+        if downloads
+            .iter()
+            .any(|download| download.target == target(&template))
+        {
+            template = disambiguate(&template);
+        }
+        downloads.push(Download {
+            id: song.id,
+            name: song.name.to_owned(),
+            state: DownloadState::Queued,
+            target: target(&template),
+            file: None,
+            downloaded: 0,
+            total: None,
+        });
+        queued.push((song, template));
+    }
+    queued
+}
+
+/// Download SONG into DIRECTORY, return the path of the file.
+/// The url is requested right before downloading since it expires. A file already holding
+/// the song isn't downloaded again, the id is added to the file name if another file has
+/// taken it.
+async fn download_song(
+    song: &Song,
+    index: usize,
+    directory: &Path,
+    template: &str,
+    quality: Quality,
+) -> Result<PathBuf, String> {
+    let api = get_api();
+    let url = api
+        .song_url(&[song.id], quality)
+        .await
+        .map_err(|e| e.to_string())?
+        .deserialize::<SongUrlResp>()
+        .map_err(|e| e.to_string())?
+        .data
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    if url.free_trial_info.is_some() {
+        return Err("Only the trial is available".to_string());
+    }
+    let link = match url.url {
+        Some(link) => link,
+        None => return Err("Not available".to_string()),
+    };
+
+    let ext = url
+        .file_type
+        .filter(|ext| !ext.is_empty())
+        .unwrap_or_else(|| "mp3".to_string())
+        .to_lowercase();
+    let md5 = url.md5.as_deref();
+    let mut file = directory.join(song_filename(template, song, index, &ext));
+    let mut downloaded = file.exists() && is_downloaded(&file, song.id, md5);
+    if file.exists() && !downloaded {
+        file = directory.join(song_filename(&disambiguate(template), song, index, &ext));
+        downloaded = file.exists() && is_downloaded(&file, song.id, md5);
+    }
+    update_download(song.id, |download| {
+        download.state = DownloadState::Downloading;
+        download.file = Some(file.to_owned());
+    });
+    if downloaded {
+        return Ok(file);
+    }
+
+    api.download(&link, &file, md5, &mut |downloaded, total| {
+        update_download(song.id, |download| {
+            download.downloaded = downloaded;
+            download.total = total;
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+#[tokio::main]
+async fn run_downloads(songs: Vec<(Song, String)>, directory: PathBuf, quality: Quality) {
+    stream::iter(songs.iter().enumerate())
+        .for_each_concurrent(DOWNLOAD_CONCURRENCY, |(index, (song, template))| {
+            let directory = &directory;
+            async move {
+                let result = download_song(song, index + 1, directory, template, quality).await;
+                let state = match result {
+                    // The existing files are tagged again too, in case tagging them failed.
+                    Ok(file) => {
+                        let state = match tag_song_file(&file, song).await {
+                            Ok(_) => DownloadState::Finished,
+                            Err(e) => DownloadState::Untagged(format!("Failed to tag: {}", e)),
+                        };
+                        update_download(song.id, |download| download.file = Some(file));
+                        state
+                    }
+                    Err(e) => DownloadState::Failed(e),
+                };
+                update_download(song.id, |download| download.state = state);
+            }
+        })
+        .await
+}
+
+/// Queue SONGS and download them in a background thread, return the number of songs queued.
+fn start_downloads<'a>(
+    env: &'a Env,
+    songs: Vec<Song>,
+    directory: String,
+    template: EValue<'a>,
+    quality: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let quality = match symbol_to_quality(quality)? {
+        Some(quality) => quality,
+        None => return error(env, "Unknown quality!".to_string()),
+    };
+    let template = if template.is_not_nil() {
+        template.into_rust::<String>()?
+    } else {
+        DEFAULT_TEMPLATE.to_string()
+    };
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        return error(env, format!("Failed to create {}: {}", directory, e));
+    }
+
+    let directory = PathBuf::from(directory);
+    let songs = queue_downloads(songs, &directory, &template);
+    let count = songs.len() as i64;
+    if !songs.is_empty() {
+        std::thread::spawn(move || run_downloads(songs, directory, quality));
+    }
+    count.into_lisp(env)
+}
+
+/// Download the songs whose ids are SIDS into DIRECTORY in the background.
+/// TEMPLATE is the file name, nil means "{artist} - {title}.{ext}", the placeholders are
+/// {id}, {title}, {artist}, {album}, {index} and {ext}.
+/// QUALITY is the same as `song-urls'.
+/// The partial files are resumed and the files are verified with their MD5, then they are
+/// tagged like `tag-file'. The id is added to the file name of a song if another download
+/// has the same one.
+/// Return the number of songs queued, use `download-progress' to get the progress.
+#[defun]
+#[tokio::main]
+pub async fn download_songs<'a>(
+    env: &'a Env,
+    sids: EValue<'a>,
+    directory: String,
+    template: EValue<'a>,
+    quality: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let ids = list_to_vec::<i64>(sids)?
+        .into_iter()
+        .map(|id| id as usize)
+        .collect::<Vec<_>>();
+    let api = get_api();
+    let songs = match api.song_detail(&ids).await {
+        Ok(detail) => detail.songs,
+        Err(_) => return error(env, "Failed to get the songs!".to_string()),
+    };
+    start_downloads(env, songs, directory, template, quality)
+}
+
+/// Download all the songs in the playlist whose id is PID, like `download-songs'.
+#[defun]
+#[tokio::main]
+pub async fn download_playlist<'a>(
+    env: &'a Env,
+    pid: i64,
+    directory: String,
+    template: EValue<'a>,
+    quality: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let api = get_api();
+    let songs = match api.playlist_songs(pid as usize).await {
+        Ok(songs) => songs,
+        Err(_) => return error(env, "The pid can not found!".to_string()),
+    };
+    start_downloads(env, songs, directory, template, quality)
+}
+
+/// Get the progress of the downloads.
+/// Return ((id name state downloaded total file) ...), state is one of the symbols queued,
+/// downloading, finished, untagged and failed, untagged means the song is downloaded but
+/// failed to be tagged, downloading it again retries the tagging. total is nil if it's
+/// unknown. file is the error message when untagged or failed.
+#[defun]
+pub fn download_progress(env: &Env) -> EResult<EValue<'_>> {
+    let downloads = DOWNLOADS.lock().unwrap();
+    let mut result = Vec::<EValue<'_>>::new();
+    for download in downloads.iter() {
+        let file = download
+            .file
+            .as_ref()
+            .map(|file| file.to_string_lossy().to_string());
+        let (state, file) = match download.state {
+            DownloadState::Queued => ("queued", file),
+            DownloadState::Downloading => ("downloading", file),
+            DownloadState::Finished => ("finished", file),
+            DownloadState::Untagged(ref e) => ("untagged", Some(e.to_owned())),
+            DownloadState::Failed(ref e) => ("failed", Some(e.to_owned())),
+        };
+        result.push(env.list((
+            download.id as i64,
+            download.name.to_owned(),
+            env.intern(state)?,
+            download.downloaded as i64,
+            download.total.map(|total| total as i64),
+            file,
+        ))?);
+    }
+    env.list(&result)
+}
+
+/// Remove the finished and failed downloads from the progress.
+#[defun]
+pub fn clear_downloads(_: &Env) -> EResult<()> {
+    DOWNLOADS.lock().unwrap().retain(|download| {
+        matches!(
+            download.state,
+            DownloadState::Queued | DownloadState::Downloading
+        )
+    });
+    Ok(())
+}
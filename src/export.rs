@@ -0,0 +1,380 @@
+// Export lyrics and playlists to files.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use futures::{stream, StreamExt};
+use ncmapi::lyric::{LyricLayout, Lyrics, WordLyric};
+use ncmapi::playlist_file::{to_m3u8, to_xspf};
+use ncmapi::types::{LyricResp, Song};
+
+use crate::api::{check_urls, error, first_artist_name, get_api};
+use crate::download::disambiguate;
+use crate::library::with_library;
+
+/// The number of songs whose lyrics are fetched at the same time.
+const EXPORT_CONCURRENCY: usize = 4;
+
+/// Fill TEMPLATE with the info of SONG. The placeholders are {id}, {title}, {artist},
+/// {album}, {index} and {ext}, the characters not allowed in file names are replaced.
+pub(crate) fn song_filename(template: &str, song: &Song, index: usize, ext: &str) -> String {
+    let sanitize = |value: &str| {
+        value
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect::<String>()
+            .trim()
+            .to_owned()
+    };
+    template
+        .replace("{id}", &song.id.to_string())
+        .replace("{title}", &sanitize(&song.name))
+        .replace("{artist}", &sanitize(&first_artist_name(&song.artists)))
+        .replace(
+            "{album}",
+            &sanitize(&song.album.name.to_owned().unwrap_or_default()),
+        )
+        .replace("{index}", &format!("{:02}", index))
+        .replace("{ext}", ext)
+}
+
+/// The lyric file formats.
+#[derive(Copy, Clone)]
+enum LyricFormat {
+    Lrc,
+    Srt,
+    Vtt,
+}
+
+impl LyricFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lrc" => Some(LyricFormat::Lrc),
+            "srt" => Some(LyricFormat::Srt),
+            "vtt" => Some(LyricFormat::Vtt),
+            _ => None,
+        }
+    }
+
+    fn ext(&self) -> &'static str {
+        match self {
+            LyricFormat::Lrc => "lrc",
+            LyricFormat::Srt => "srt",
+            LyricFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// Convert the FORMAT symbol into `LyricFormat`, guess it from FILE if it's nil.
+fn symbol_to_format(format: EValue<'_>, file: Option<&str>) -> EResult<Option<LyricFormat>> {
+    if format.is_not_nil() {
+        let format = format
+            .env
+            .call("symbol-name", [format])?
+            .into_rust::<String>()?;
+        return Ok(LyricFormat::from_name(&format));
+    }
+    Ok(match file {
+        Some(file) => Path::new(file)
+            .extension()
+            .and_then(|ext| LyricFormat::from_name(&ext.to_string_lossy())),
+        None => Some(LyricFormat::Lrc),
+    })
+}
+
+/// Convert the LAYOUT symbol into `LyricLayout`, nil means stacked.
+fn symbol_to_layout(layout: EValue<'_>) -> EResult<Option<LyricLayout>> {
+    if !layout.is_not_nil() {
+        return Ok(Some(LyricLayout::default()));
+    }
+    let layout = layout
+        .env
+        .call("symbol-name", [layout])?
+        .into_rust::<String>()?;
+    Ok(match layout.as_str() {
+        "original" => Some(LyricLayout::Original),
+        "translation" => Some(LyricLayout::Translation),
+        "stacked" => Some(LyricLayout::Stacked),
+        "inline" => Some(LyricLayout::Inline),
+        _ => None,
+    })
+}
+
+/// Fetch the lyrics and the word timed lyrics of the song whose id is SID.
+/// Return `None` if the song has no lyrics.
+async fn fetch_lyrics(sid: usize) -> Option<(Lyrics, WordLyric)> {
+    let api = get_api();
+    let result = api
+        .lyric_new(sid)
+        .await
+        .ok()?
+        .deserialize::<LyricResp>()
+        .ok()?;
+    let text =
+        |lyric: Option<ncmapi::types::Lyric>| lyric.map(|lyric| lyric.lyric).unwrap_or_default();
+
+    let lyrics = Lyrics::parse(
+        &text(result.lrc),
+        &text(result.tlyric),
+        &text(result.romalrc),
+    );
+    if lyrics.entries.is_empty() {
+        return None;
+    }
+    Some((lyrics, WordLyric::parse_yrc(&text(result.yrc))))
+}
+
+/// Render LYRICS in FORMAT, DURATION is the length of the song in milliseconds.
+fn render(
+    lyrics: &Lyrics,
+    words: &WordLyric,
+    format: LyricFormat,
+    layout: LyricLayout,
+    duration: usize,
+) -> String {
+    let duration = Some(duration as u64).filter(|duration| *duration > 0);
+    match format {
+        LyricFormat::Lrc => lyrics.to_lrc(layout, Some(words)),
+        LyricFormat::Srt => lyrics.to_srt(layout, duration),
+        LyricFormat::Vtt => lyrics.to_vtt(layout, duration),
+    }
+}
+
+/// Export the lyrics of the song whose id is SID to FILE.
+/// FORMAT is one of the symbols lrc, srt and vtt, nil means guessing from FILE.
+/// LAYOUT is how the translation is laid out, one of the symbols:
+/// original:    only the original lyrics.
+/// translation: only the translation, the original line is kept if it's not translated.
+/// stacked:     the translation below the original line, it's the default.
+/// inline:      the translation after the original line, separated by " / ".
+/// The LRC file has the word timestamps of the enhanced LRC if the song has them.
+/// Return t if exported, or nil if the song has no lyrics.
+#[defun]
+#[tokio::main]
+pub async fn export_lyrics<'a>(
+    env: &'a Env,
+    sid: i64,
+    file: String,
+    format: EValue<'a>,
+    layout: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let format = match symbol_to_format(format, Some(&file))? {
+        Some(format) => format,
+        None => return error(env, "Unknown lyric format!".to_string()),
+    };
+    let layout = match symbol_to_layout(layout)? {
+        Some(layout) => layout,
+        None => return error(env, "Unknown lyric layout!".to_string()),
+    };
+
+    let (lyrics, words) = match fetch_lyrics(sid as usize).await {
+        Some(lyrics) => lyrics,
+        None => return ().into_lisp(env),
+    };
+    let api = get_api();
+    let duration = api
+        .song_detail(&[sid as usize])
+        .await
+        .ok()
+        .and_then(|detail| detail.songs.first().map(|song| song.duration))
+        .unwrap_or_default();
+
+    let content = render(&lyrics, &words, format, layout, duration);
+    match std::fs::write(&file, content) {
+        Ok(_) => true.into_lisp(env),
+        Err(e) => error(env, format!("Failed to write {}: {}", file, e)),
+    }
+}
+
+/// Export the lyrics of all the songs in the playlist whose id is PID into DIRECTORY.
+/// TEMPLATE is the file name, like "{artist} - {title}.{ext}", the placeholders are
+/// {id}, {title}, {artist}, {album}, {index} and {ext}. The id is added to the file name
+/// of a song if an earlier song has taken it.
+/// FORMAT and LAYOUT are the same as `export-lyrics', nil FORMAT means lrc.
+/// Return (exported ((id name) ...)), the list is the songs without lyrics.
+#[defun]
+#[tokio::main]
+pub async fn export_playlist_lyrics<'a>(
+    env: &'a Env,
+    pid: i64,
+    directory: String,
+    template: String,
+    format: EValue<'a>,
+    layout: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let format = match symbol_to_format(format, None)? {
+        Some(format) => format,
+        None => return error(env, "Unknown lyric format!".to_string()),
+    };
+    let layout = match symbol_to_layout(layout)? {
+        Some(layout) => layout,
+        None => return error(env, "Unknown lyric layout!".to_string()),
+    };
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        return error(env, format!("Failed to create {}: {}", directory, e));
+    }
+
+    let api = get_api();
+    let songs = match api.playlist_songs(pid as usize).await {
+        Ok(songs) => songs,
+        Err(_) => return error(env, "The pid can not found!".to_string()),
+    };
+
+    let results = stream::iter(songs.iter())
+        .map(|song| async move { (song, fetch_lyrics(song.id).await) })
+        .buffered(EXPORT_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut exported = 0;
+    let mut written = HashSet::new();
+    let mut missing = Vec::<EValue<'_>>::new();
+    for (index, (song, lyrics)) in results.into_iter().enumerate() {
+        let (lyrics, words) = match lyrics {
+            Some(lyrics) => lyrics,
+            None => {
+                missing.push(env.list((song.id as i64, song.name.to_owned()))?);
+                continue;
+            }
+        };
+
+        let target = |template: &str| {
+            Path::new(&directory).join(song_filename(template, song, index + 1, format.ext()))
+        };
+        let mut file = target(&template);
+        if written.contains(&file) {
+            file = target(&disambiguate(&template));
+        }
+        let content = render(&lyrics, &words, format, layout, song.duration);
+        if let Err(e) = std::fs::write(&file, content) {
+            return error(env, format!("Failed to write {}: {}", file.display(), e));
+        }
+        written.insert(file);
+        exported += 1;
+    }
+    env.list((exported, env.list(&missing)?))
+}
+
+/// Where the exported tracks point to.
+#[derive(Copy, Clone, PartialEq)]
+enum TrackLocation {
+    Local,
+    Url,
+    Any,
+}
+
+/// Get the locations of SONGS, the urls expire after a while.
+async fn track_locations(
+    songs: &[Song],
+    location: TrackLocation,
+) -> Result<Vec<Option<String>>, String> {
+    let mut locations = songs
+        .iter()
+        .map(|song| {
+            if location == TrackLocation::Url {
+                None
+            } else {
+                with_library(|library| library.song_file(song.id).ok())
+                    .flatten()
+                    .flatten()
+            }
+        })
+        .collect::<Vec<_>>();
+    if location == TrackLocation::Local {
+        return Ok(locations);
+    }
+
+    let missing = songs
+        .iter()
+        .zip(locations.iter())
+        .filter(|(_, location)| location.is_none())
+        .map(|(song, _)| song.id)
+        .collect::<Vec<_>>();
+    let mut urls = check_urls(&missing).await?;
+    for (song, location) in songs.iter().zip(locations.iter_mut()) {
+        if location.is_none() {
+            *location = urls.remove(&song.id).and_then(|url| url.url);
+        }
+    }
+    Ok(locations)
+}
+
+/// Export the playlist whose id is PID to FILE.
+/// FORMAT is one of the symbols:
+/// m3u8: extended M3U with the durations, the tracks without a location are left out.
+/// xspf: XSPF with the metadata of the playlist and all the tracks.
+/// json: the playlist detail with all the tracks, nothing is lost.
+/// nil means guessing from the extension of FILE.
+/// LOCATION is where the tracks point to, one of the symbols local, for the downloaded
+/// files in the library, and url, for the stream urls which expire after a while.
+/// nil means the downloaded file if there's one, otherwise the stream url.
+/// Return the number of the tracks in the playlist.
+#[defun]
+#[tokio::main]
+pub async fn export_playlist<'a>(
+    env: &'a Env,
+    pid: i64,
+    file: String,
+    format: EValue<'a>,
+    location: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let format = if format.is_not_nil() {
+        env.call("symbol-name", [format])?.into_rust::<String>()?
+    } else {
+        Path::new(&file)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    let location = if location.is_not_nil() {
+        match env
+            .call("symbol-name", [location])?
+            .into_rust::<String>()?
+            .as_str()
+        {
+            "local" => TrackLocation::Local,
+            "url" => TrackLocation::Url,
+            _ => return error(env, "Unknown track location!".to_string()),
+        }
+    } else {
+        TrackLocation::Any
+    };
+
+    let api = get_api();
+    let playlist = match api.playlist_with_tracks(pid as usize).await {
+        Ok(playlist) => playlist,
+        Err(_) => return error(env, "The pid can not found!".to_string()),
+    };
+    let content = match format.as_str() {
+        "m3u8" | "m3u" | "xspf" => {
+            let locations = match track_locations(&playlist.tracks, location).await {
+                Ok(locations) => locations,
+                Err(e) => return error(env, format!("Failed to get the track urls: {}", e)),
+            };
+            if format == "xspf" {
+                to_xspf(&playlist, &locations)
+            } else {
+                to_m3u8(&playlist, &locations)
+            }
+        }
+        "json" => match serde_json::to_string_pretty(&playlist) {
+            Ok(content) => content,
+            Err(e) => return error(env, format!("Failed to serialize the playlist: {}", e)),
+        },
+        _ => return error(env, "Unknown playlist format!".to_string()),
+    };
+
+    match std::fs::write(&file, content) {
+        Ok(_) => (playlist.tracks.len() as i64).into_lisp(env),
+        Err(e) => error(env, format!("Failed to write {}: {}", file, e)),
+    }
+}
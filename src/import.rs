@@ -0,0 +1,180 @@
+// Import playlists from the files of other players.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env};
+use futures::{stream, StreamExt, TryStreamExt};
+use ncmapi::matching::{best_match, HIGH_CONFIDENCE};
+use ncmapi::playlist_file::{parse_csv, parse_m3u, parse_xspf, PlaylistEntry};
+use ncmapi::playlist_sync::SyncPlan;
+use ncmapi::types::{SearchSongResp, Song};
+use ncmapi::SearchType;
+
+use crate::api::{error, first_artist_name, get_api};
+use crate::sync::{apply_plan, create_remote_playlist, playlist_ids};
+
+/// The number of search results considered for each track.
+const MATCH_CANDIDATES: usize = 10;
+
+/// The number of tracks searched at the same time.
+const MATCH_CONCURRENCY: usize = 4;
+
+/// Find the best match of ENTRY by searching its title and artist,
+/// then its title only if nothing matches.
+async fn match_entry(entry: &PlaylistEntry) -> Result<Option<(Song, f64)>, String> {
+    let mut queries = vec![format!("{} {}", entry.title, entry.artist)];
+    if !entry.artist.is_empty() {
+        queries.push(entry.title.to_owned());
+    }
+    for query in queries.iter() {
+        let songs = get_api()
+            .cloud_search_page::<SearchSongResp>(
+                query.trim(),
+                SearchType::Song,
+                0,
+                MATCH_CANDIDATES,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .items;
+        if let Some((index, score)) = best_match(entry, &songs) {
+            return Ok(Some((songs[index].to_owned(), score)));
+        }
+    }
+    Ok(None)
+}
+
+/// Add the songs whose ids are IDS to the playlist PID in batches, keeping their order,
+/// the songs already in the playlist are skipped.
+async fn add_to_playlist(pid: usize, ids: &[usize]) -> Result<(), String> {
+    let existing = playlist_ids(pid).await?.into_iter().collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    let plan = SyncPlan {
+        add: ids
+            .iter()
+            .filter(|id| !existing.contains(id) && seen.insert(**id))
+            .copied()
+            .collect(),
+        ..Default::default()
+    };
+    apply_plan(pid, &plan).await
+}
+
+/// Import the tracks in FILE into a playlist by matching them with the search results.
+/// FORMAT is one of the symbols m3u, xspf and csv, nil means guessing from the extension
+/// of FILE. The CSV columns are artist, title, album and duration, or named by a header.
+/// TARGET is the id of the playlist to append to, or the name of a new playlist, nil means
+/// a new playlist named after FILE.
+/// If DRY-RUN is non-nil, only match the tracks without touching any playlist.
+/// Return (pid matched unmatched), pid is nil for a dry run.
+/// matched is ((index title artist id name song-artist score confidence) ...), confidence
+/// is the symbol high or low, the low ones are worth reviewing.
+/// unmatched is ((index title artist) ...).
+#[defun]
+#[tokio::main]
+pub async fn import_playlist<'a>(
+    env: &'a Env,
+    file: String,
+    format: EValue<'a>,
+    target: EValue<'a>,
+    dry_run: EValue<'a>,
+) -> EResult<EValue<'a>> {
+    let format = if format.is_not_nil() {
+        env.call("symbol-name", [format])?.into_rust::<String>()?
+    } else {
+        Path::new(&file)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    let text = match std::fs::read(&file) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(e) => return error(env, format!("Failed to read {}: {}", file, e)),
+    };
+    let entries = match format.as_str() {
+        "m3u" | "m3u8" => parse_m3u(&text),
+        "xspf" => parse_xspf(&text),
+        "csv" => parse_csv(&text),
+        _ => return error(env, "Unknown playlist format!".to_string()),
+    };
+    if entries.is_empty() {
+        return error(env, format!("No tracks in {}!", file));
+    }
+
+    let matches = match stream::iter(entries.iter())
+        .map(match_entry)
+        .buffered(MATCH_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await
+    {
+        Ok(matches) => matches,
+        Err(e) => return error(env, format!("Failed to search the tracks: {}", e)),
+    };
+
+    let mut matched = Vec::<EValue<'_>>::new();
+    let mut unmatched = Vec::<EValue<'_>>::new();
+    for (index, (entry, result)) in entries.iter().zip(matches.iter()).enumerate() {
+        match result {
+            Some((song, score)) => {
+                let confidence = if *score >= HIGH_CONFIDENCE {
+                    "high"
+                } else {
+                    "low"
+                };
+                matched.push(env.list((
+                    index as i64,
+                    entry.title.to_owned(),
+                    entry.artist.to_owned(),
+                    song.id as i64,
+                    song.name.to_owned(),
+                    first_artist_name(&song.artists),
+                    *score,
+                    env.intern(confidence)?,
+                ))?)
+            }
+            None => unmatched.push(env.list((
+                index as i64,
+                entry.title.to_owned(),
+                entry.artist.to_owned(),
+            ))?),
+        }
+    }
+    if dry_run.is_not_nil() {
+        return env.list(((), env.list(&matched)?, env.list(&unmatched)?));
+    }
+
+    let pid = if env.call("integerp", [target])?.is_not_nil() {
+        target.into_rust::<i64>()? as usize
+    } else {
+        let name = if target.is_not_nil() {
+            target.into_rust::<String>()?
+        } else {
+            Path::new(&file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        match create_remote_playlist(&name, false).await {
+            Ok(pid) => pid,
+            Err(e) => return error(env, format!("Failed to create the playlist: {}", e)),
+        }
+    };
+
+    let ids = matches
+        .iter()
+        .flatten()
+        .map(|(song, _)| song.id)
+        .collect::<Vec<_>>();
+    if let Err(e) = add_to_playlist(pid, &ids).await {
+        return error(
+            env,
+            format!("Failed to import into the playlist {}: {}", pid, e),
+        );
+    }
+    env.list((pid as i64, env.list(&matched)?, env.list(&unmatched)?))
+}
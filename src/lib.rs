@@ -3,6 +3,21 @@
 // Copyright (c) 2022 SpringHan
 
 mod api;
+mod backup;
+mod browse;
+mod cleanup;
+mod comment;
+mod download;
+mod export;
+mod import;
+mod library;
+mod lyrics;
+mod radio;
+mod smart;
+mod stats;
+mod suggest;
+mod sync;
+mod tags;
 
 use emacs::{Env, Result};
 // use std::thread;
@@ -0,0 +1,156 @@
+// The local library of songs and playlists, searchable without the network.
+
+// Copyright (C) 2022 SpringHan
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::library::Library;
+use ncmapi::lyric::Lyrics;
+use ncmapi::tag::read_tags;
+use ncmapi::types::{Playlist, Song};
+
+use crate::api::{error, page_offset, page_to_lisp, playlist_to_lisp, song_to_lisp};
+
+/// The library opened by `library-open', nothing is indexed until it's opened.
+static LIBRARY: Mutex<Option<Library>> = Mutex::new(None);
+
+/// The extensions of the files indexed by `library-scan'.
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac"];
+
+/// Call F with the library, return `None` if it isn't opened.
+pub(crate) fn with_library<T>(f: impl FnOnce(&mut Library) -> T) -> Option<T> {
+    LIBRARY.lock().unwrap().as_mut().map(f)
+}
+
+/// Index SONGS, the failures are ignored since indexing is best effort.
+pub(crate) fn remember_songs(songs: &[Song]) {
+    with_library(|library| library.add_songs(songs).ok());
+}
+
+/// Index PLAYLISTS, the failures are ignored since indexing is best effort.
+pub(crate) fn remember_playlists(playlists: &[Playlist]) {
+    with_library(|library| library.add_playlists(playlists).ok());
+}
+
+/// Index the LYRICS of the song whose id is SID.
+pub(crate) fn remember_lyrics(sid: usize, lyrics: &Lyrics) {
+    with_library(|library| library.set_lyrics(sid, lyrics).ok());
+}
+
+/// Search the library for the songs matching QUERY, return `None` if it isn't opened.
+pub(crate) fn search_songs(query: &str, offset: usize, limit: usize) -> Option<ncmapi::Page<Song>> {
+    with_library(|library| library.search_songs(query, offset, limit).ok()).flatten()
+}
+
+/// Search the library for the playlists matching QUERY, return `None` if it isn't opened.
+pub(crate) fn search_playlists(
+    query: &str,
+    offset: usize,
+    limit: usize,
+) -> Option<ncmapi::Page<Playlist>> {
+    with_library(|library| library.search_playlists(query, offset, limit).ok()).flatten()
+}
+
+/// Open the library database FILE, it's created if it doesn't exist.
+/// After opening, the songs and playlists returned by the other functions, the fetched
+/// lyrics and the downloaded files are indexed.
+#[defun]
+pub fn library_open(env: &Env, file: String) -> EResult<EValue<'_>> {
+    match Library::open(Path::new(&file)) {
+        Ok(library) => {
+            *LIBRARY.lock().unwrap() = Some(library);
+            true.into_lisp(env)
+        }
+        Err(e) => error(env, format!("Failed to open the library {}: {}", file, e)),
+    }
+}
+
+/// Collect the audio files under DIRECTORY recursively.
+fn audio_files(directory: &Path, files: &mut Vec<std::path::PathBuf>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            audio_files(&path, files);
+        } else if path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str()))
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Index the MP3 and FLAC files under DIRECTORY with the tags written by `tag-file',
+/// the files without the song id are skipped. It works without the network.
+/// The files indexed before which don't exist anymore are forgotten.
+/// Return (indexed forgotten).
+#[defun]
+pub fn library_scan(env: &Env, directory: String) -> EResult<EValue<'_>> {
+    let mut files = Vec::new();
+    audio_files(Path::new(&directory), &mut files);
+
+    let result = with_library(|library| {
+        let mut indexed = 0;
+        for file in files.iter() {
+            if let Ok(Some(tags)) = read_tags(file) {
+                if library.add_file(file, &tags).is_ok() {
+                    indexed += 1;
+                }
+            }
+        }
+        (
+            indexed,
+            library.forget_missing_files().unwrap_or_default() as i64,
+        )
+    });
+    match result {
+        Some(result) => env.list(result),
+        None => error(env, "The library isn't opened!".to_string()),
+    }
+}
+
+/// Search the library for QUERY in the titles, artists, albums and lyrics of the songs,
+/// or the names and creators of the playlists if PLAYLISTP is non-nil.
+/// LIMIT is the number of items in each page, PAGE is the current page.
+/// Return (items total has-more) like `search-with-type', nil if the library isn't opened.
+#[defun]
+pub fn library_search<'a>(
+    env: &'a Env,
+    query: String,
+    playlistp: EValue<'a>,
+    limit: i64,
+    page: i64,
+) -> EResult<EValue<'a>> {
+    let offset = page_offset(limit, page);
+    if playlistp.is_not_nil() {
+        match search_playlists(&query, offset, limit as usize) {
+            Some(page) => page_to_lisp(env, &page, playlist_to_lisp),
+            None => ().into_lisp(env),
+        }
+    } else {
+        match search_songs(&query, offset, limit as usize) {
+            Some(page) => page_to_lisp(env, &page, song_to_lisp),
+            None => ().into_lisp(env),
+        }
+    }
+}
+
+/// Get the downloaded file of the song whose id is SID, nil if there's none.
+#[defun]
+pub fn library_song_file(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    with_library(|library| library.song_file(sid as usize).ok())
+        .flatten()
+        .flatten()
+        .into_lisp(env)
+}
@@ -0,0 +1,176 @@
+// Structured lyrics with translation, romanization and word timings.
+
+// Copyright (C) 2022 SpringHan
+
+use std::sync::Mutex;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::lyric::{LyricEntry, Lyrics, WordLine, WordLyric};
+use ncmapi::types::LyricResp;
+
+use crate::api::get_api;
+use crate::library::remember_lyrics;
+
+/// The lyrics returned by the last `lyrics' call, with the song id.
+static CURRENT: Mutex<Option<(i64, Lyrics)>> = Mutex::new(None);
+
+/// The word timed lyrics returned by the last `word-lyrics' call.
+static CURRENT_WORDS: Mutex<Option<WordLyric>> = Mutex::new(None);
+
+/// (time-ms original translation romaji)
+fn entry_to_lisp<'a>(env: &'a Env, entry: &LyricEntry) -> EResult<EValue<'a>> {
+    env.list((
+        entry.time as i64,
+        entry.original.to_owned(),
+        entry.translation.to_owned(),
+        entry.romaji.to_owned(),
+    ))
+}
+
+/// Get the lyrics of the song whose id is SID, and keep them for `lyric-line-at'.
+/// Return ((time-ms original translation romaji) ...) sorted by time,
+/// translation and romaji are nil if there's none for the line.
+/// If the song has no lyrics, return nil.
+#[defun]
+#[tokio::main]
+pub async fn lyrics(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = api
+        .lyric(sid as usize)
+        .await
+        .unwrap()
+        .deserialize::<LyricResp>()
+        .unwrap_or_default();
+    let text =
+        |lyric: Option<ncmapi::types::Lyric>| lyric.map(|lyric| lyric.lyric).unwrap_or_default();
+    let lyrics = Lyrics::parse(
+        &text(result.lrc),
+        &text(result.tlyric),
+        &text(result.romalrc),
+    );
+
+    remember_lyrics(sid as usize, &lyrics);
+
+    let mut entries = Vec::<EValue<'_>>::new();
+    for entry in lyrics.entries.iter() {
+        entries.push(entry_to_lisp(env, entry)?);
+    }
+    *CURRENT.lock().unwrap() = Some((sid, lyrics));
+    env.list(&entries)
+}
+
+/// Get the line of the current lyrics at MS milliseconds.
+/// Return (index time-ms original translation romaji), or nil if there's no line
+/// at that time or no lyrics has been fetched by `lyrics'.
+#[defun]
+pub fn lyric_line_at(env: &Env, ms: i64) -> EResult<EValue<'_>> {
+    let current = CURRENT.lock().unwrap();
+    let lyrics = match current.as_ref() {
+        Some((_, lyrics)) => lyrics,
+        None => return ().into_lisp(env),
+    };
+
+    match lyrics.line_at(ms.max(0) as u64) {
+        Some(index) => {
+            let entry = &lyrics.entries[index];
+            env.list((
+                index as i64,
+                entry.time as i64,
+                entry.original.to_owned(),
+                entry.translation.to_owned(),
+                entry.romaji.to_owned(),
+            ))
+        }
+        None => ().into_lisp(env),
+    }
+}
+
+/// Get the metadata of the current lyrics, return ((key . value) ...).
+/// key is the tag name in lower case, like "ti" and "ar".
+#[defun]
+pub fn lyric_metadata(env: &Env) -> EResult<EValue<'_>> {
+    let current = CURRENT.lock().unwrap();
+    let mut metadata = Vec::<EValue<'_>>::new();
+    if let Some((_, lyrics)) = current.as_ref() {
+        for (key, value) in lyrics.metadata.iter() {
+            metadata.push(env.call("cons", (key.to_owned(), value.to_owned()))?);
+        }
+    }
+    env.list(&metadata)
+}
+
+/// (start duration ((start duration text) ...))
+fn word_line_to_lisp<'a>(env: &'a Env, line: &WordLine) -> EResult<EValue<'a>> {
+    let mut words = Vec::<EValue<'_>>::new();
+    for word in line.words.iter() {
+        words.push(env.list((
+            word.start as i64,
+            word.duration as i64,
+            word.text.to_owned(),
+        ))?);
+    }
+    env.list((line.start as i64, line.duration as i64, env.list(&words)?))
+}
+
+/// Get the word timed lyrics of the song whose id is SID, and keep them for
+/// `lyric-word-at'. The yrc lyrics are preferred, and the legacy klyric is used
+/// when the song has no yrc.
+/// Return ((start duration ((start duration text) ...)) ...), the times are in
+/// milliseconds. If the song has no word timed lyrics, return nil.
+#[defun]
+#[tokio::main]
+pub async fn word_lyrics(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = api
+        .lyric_new(sid as usize)
+        .await
+        .unwrap()
+        .deserialize::<LyricResp>()
+        .unwrap_or_default();
+
+    let mut lyric = WordLyric::default();
+    if let Some(yrc) = result.yrc {
+        lyric = WordLyric::parse_yrc(&yrc.lyric);
+    }
+    if lyric.lines.is_empty() {
+        if let Some(klyric) = result.klyric {
+            lyric = WordLyric::parse_klyric(&klyric.lyric);
+        }
+    }
+
+    let mut lines = Vec::<EValue<'_>>::new();
+    for line in lyric.lines.iter() {
+        lines.push(word_line_to_lisp(env, line)?);
+    }
+    *CURRENT_WORDS.lock().unwrap() = Some(lyric);
+    env.list(&lines)
+}
+
+/// Get the word being sung at MS milliseconds in the current word timed lyrics.
+/// Return (line-index word-index start duration text), word-index and the rest are
+/// nil if the line has started but its first word hasn't.
+/// Return nil if no line is being sung or no lyrics has been fetched by `word-lyrics'.
+#[defun]
+pub fn lyric_word_at(env: &Env, ms: i64) -> EResult<EValue<'_>> {
+    let current = CURRENT_WORDS.lock().unwrap();
+    let lyric = match current.as_ref() {
+        Some(lyric) => lyric,
+        None => return ().into_lisp(env),
+    };
+
+    match lyric.word_at(ms.max(0) as u64) {
+        Some((line_index, word_index)) => {
+            let word = word_index.map(|index| &lyric.lines[line_index].words[index]);
+            env.list((
+                line_index as i64,
+                word_index.map(|index| index as i64),
+                word.map(|word| word.start as i64),
+                word.map(|word| word.duration as i64),
+                word.map(|word| word.text.to_owned()),
+            ))
+        }
+        None => ().into_lisp(env),
+    }
+}
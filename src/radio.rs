@@ -0,0 +1,283 @@
+// Similar resources, the song radio and the heartbeat mode.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env};
+use ncmapi::types::{
+    IntelligenceListResp, SimiArtistsResp, SimiMVsResp, SimiPlaylistsResp, SimiSongsResp,
+    SimiUsersResp, Song,
+};
+
+use crate::api::{
+    artist_to_lisp, error, first_artist_name, get_api, items_to_lisp, mv_to_lisp, playlist_to_lisp,
+    song_to_lisp, user_to_lisp,
+};
+use crate::library::{remember_playlists, remember_songs};
+
+/// Get the songs similar to the song whose id is SID, return ((id name artist) ...).
+#[defun]
+#[tokio::main]
+pub async fn similar_songs(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.simi_song(sid as usize, None).await {
+        Ok(response) => response.deserialize::<SimiSongsResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the similar songs: {}", e)),
+    };
+    remember_songs(&result.songs);
+    items_to_lisp(env, &result.songs, song_to_lisp)
+}
+
+/// Get the artists similar to the artist whose id is AID, return ((id name) ...).
+#[defun]
+#[tokio::main]
+pub async fn similar_artists(env: &Env, aid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.simi_artist(aid as usize).await {
+        Ok(response) => response
+            .deserialize::<SimiArtistsResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the similar artists: {}", e)),
+    };
+    items_to_lisp(env, &result.artists, artist_to_lisp)
+}
+
+/// Get the playlists containing songs similar to the song whose id is SID,
+/// return ((id name) ...).
+#[defun]
+#[tokio::main]
+pub async fn similar_playlists(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.simi_playlist(sid as usize, None).await {
+        Ok(response) => response
+            .deserialize::<SimiPlaylistsResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the similar playlists: {}", e)),
+    };
+    remember_playlists(&result.playlists);
+    items_to_lisp(env, &result.playlists, playlist_to_lisp)
+}
+
+/// Get the mvs similar to the mv whose id is MVID, return ((id name artist duration) ...).
+#[defun]
+#[tokio::main]
+pub async fn similar_mvs(env: &Env, mvid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.simi_mv(mvid as usize).await {
+        Ok(response) => response.deserialize::<SimiMVsResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the similar mvs: {}", e)),
+    };
+    items_to_lisp(env, &result.mvs, mv_to_lisp)
+}
+
+/// Get the users who listened to the song whose id is SID recently,
+/// return ((id nickname) ...).
+#[defun]
+#[tokio::main]
+pub async fn similar_users(env: &Env, sid: i64) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api.simi_user(sid as usize, None).await {
+        Ok(response) => response.deserialize::<SimiUsersResp>().unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the similar users: {}", e)),
+    };
+    items_to_lisp(env, &result.userprofiles, user_to_lisp)
+}
+
+/// The max number of songs of the same artist within the recent songs.
+const MAX_SONGS_PER_ARTIST: usize = 2;
+
+/// How many recent songs are taken into account for the artist diversity.
+const ARTIST_WINDOW: usize = 10;
+
+/// The max number of similar song requests for one batch.
+const MAX_FETCHES_PER_BATCH: usize = 10;
+
+/// The radio state.
+/// Similar songs are fetched breadth-first: each song queued is also added to the
+/// frontier, whose similar songs will be fetched when the queue runs out.
+struct Radio {
+    queue: VecDeque<Song>,
+    frontier: VecDeque<usize>,
+    seen: HashSet<usize>,
+    recent_artists: VecDeque<Vec<usize>>,
+}
+
+static RADIO: Mutex<Option<Radio>> = Mutex::new(None);
+
+impl Radio {
+    fn new(seed: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            frontier: VecDeque::from([seed]),
+            seen: HashSet::from([seed]),
+            recent_artists: VecDeque::new(),
+        }
+    }
+
+    /// Whether SONG can be played without breaking the artist diversity.
+    fn is_diverse(&self, song: &Song) -> bool {
+        song.artists.iter().all(|artist| {
+            self.recent_artists
+                .iter()
+                .filter(|artists| artists.contains(&artist.id))
+                .count()
+                < MAX_SONGS_PER_ARTIST
+        })
+    }
+
+    /// Take the first diverse song in the queue.
+    /// If RELAX is true, take the first song anyway.
+    fn take(&mut self, relax: bool) -> Option<Song> {
+        let index = if relax {
+            if self.queue.is_empty() {
+                return None;
+            }
+            0
+        } else {
+            self.queue.iter().position(|song| self.is_diverse(song))?
+        };
+
+        let song = self.queue.remove(index)?;
+        self.recent_artists
+            .push_back(song.artists.iter().map(|artist| artist.id).collect());
+        if self.recent_artists.len() > ARTIST_WINDOW {
+            self.recent_artists.pop_front();
+        }
+        Some(song)
+    }
+
+    /// Queue the SONGS which haven't been seen.
+    fn extend(&mut self, songs: Vec<Song>) {
+        for song in songs {
+            if self.seen.insert(song.id) {
+                self.frontier.push_back(song.id);
+                self.queue.push_back(song);
+            }
+        }
+    }
+
+    /// Get the next COUNT songs, fetching similar songs when needed.
+    /// Fewer songs are returned if the similar songs have run out.
+    async fn next_batch(&mut self, count: usize) -> Vec<Song> {
+        let api = get_api();
+        let mut batch = Vec::<Song>::new();
+        let mut fetches = 0;
+
+        while batch.len() < count {
+            if let Some(song) = self.take(false) {
+                batch.push(song);
+                continue;
+            }
+
+            let id = match self.frontier.pop_front() {
+                Some(id) if fetches < MAX_FETCHES_PER_BATCH => id,
+                // nothing diverse is reachable, so give up the diversity
+                // instead of stopping the radio
+                _ => match self.take(true) {
+                    Some(song) => {
+                        batch.push(song);
+                        continue;
+                    }
+                    None => break,
+                },
+            };
+            fetches += 1;
+
+            if let Ok(response) = api.simi_song(id, None).await {
+                let songs = response
+                    .deserialize::<SimiSongsResp>()
+                    .map(|result| result.songs)
+                    .unwrap_or_default();
+                self.extend(songs);
+            }
+        }
+        batch
+    }
+}
+
+/// Get the next COUNT songs of the RADIO, and put it back.
+async fn radio_batch(env: &Env, mut radio: Radio, count: i64) -> EResult<EValue<'_>> {
+    let songs = radio.next_batch(count.max(0) as usize).await;
+    *RADIO.lock().unwrap() = Some(radio);
+
+    if songs.is_empty() {
+        env.message("[Netease-Cloud-Music]: The radio has run out of songs!")?;
+    }
+    remember_songs(&songs);
+    items_to_lisp(env, &songs, song_to_lisp)
+}
+
+/// Start the radio from the song whose id is SID, return the first COUNT songs.
+/// The songs are similar songs walked breadth-first from SID, without duplicates,
+/// and at most 2 songs of the same artist appear within 10 songs.
+/// Return ((id name artist) ...).
+#[defun]
+#[tokio::main]
+pub async fn radio_start(env: &Env, sid: i64, count: i64) -> EResult<EValue<'_>> {
+    radio_batch(env, Radio::new(sid as usize), count).await
+}
+
+/// Get the next COUNT songs of the radio started by `radio-start'.
+/// Return ((id name artist) ...).
+#[defun]
+#[tokio::main]
+pub async fn radio_next(env: &Env, count: i64) -> EResult<EValue<'_>> {
+    let radio = RADIO.lock().unwrap().take();
+    match radio {
+        Some(radio) => radio_batch(env, radio, count).await,
+        None => error(env, "The radio hasn't been started!".to_string()),
+    }
+}
+
+/// Get the heartbeat mode queue seeded from the song whose id is SID in the playlist
+/// whose id is PID, usually a liked song in the liked songs playlist.
+/// START-ID is the id of the song to start from, nil means SID.
+/// Return ((id name artist source) ...), source is the symbol recommend for the
+/// recommended songs, or playlist for the songs from the playlist.
+#[defun]
+#[tokio::main]
+pub async fn intelligence_list(
+    env: &Env,
+    sid: i64,
+    pid: i64,
+    start_id: Option<i64>,
+) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = match api
+        .intelligence_list(sid as usize, pid as usize, start_id.map(|id| id as usize))
+        .await
+    {
+        Ok(response) => response
+            .deserialize::<IntelligenceListResp>()
+            .unwrap_or_default(),
+        Err(e) => return error(env, format!("Failed to get the heartbeat mode list: {}", e)),
+    };
+
+    if result.code != 200 {
+        return error(
+            env,
+            "Failed to get the heartbeat mode list, please login first!".to_string(),
+        );
+    }
+
+    let mut songs = Vec::<EValue<'_>>::new();
+    for item in result.data.iter() {
+        let song = &item.song_info;
+        let source = if item.recommended {
+            "recommend"
+        } else {
+            "playlist"
+        };
+        songs.push(env.list((
+            song.id as i64,
+            song.name.to_owned(),
+            first_artist_name(&song.artists),
+            env.intern(source)?,
+        ))?);
+    }
+    env.list(&songs)
+}
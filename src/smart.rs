@@ -0,0 +1,353 @@
+// Smart playlists defined by rules.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env};
+use futures::TryStreamExt;
+use ncmapi::playlist_sync::SyncPlan;
+use ncmapi::smart::{
+    load_smart_playlists, save_smart_playlists, Candidate, Facts, Matcher, Order, Rule,
+    SmartPlaylist, Source,
+};
+use ncmapi::types::Playlist;
+use ncmapi::Page;
+
+use crate::api::{current_user, error, get_api, list_to_vec, LIKED_PLAYLIST_TYPE};
+use crate::sync::{apply_plan, create_remote_playlist, plan_to_lisp, playlist_ids};
+
+fn symbol_name(value: EValue<'_>) -> EResult<String> {
+    value
+        .env
+        .call("symbol-name", [value])?
+        .into_rust::<String>()
+}
+
+fn is(value: EValue<'_>, predicate: &str) -> EResult<bool> {
+    Ok(value.env.call(predicate, [value])?.is_not_nil())
+}
+
+/// The integer VALUE, `None` if it's nil.
+fn optional_number(value: Option<&EValue<'_>>) -> EResult<Option<u64>> {
+    match value {
+        Some(value) if value.is_not_nil() => Ok(Some(value.into_rust::<i64>()?.max(0) as u64)),
+        _ => Ok(None),
+    }
+}
+
+fn parse_matchers(values: &[EValue<'_>]) -> EResult<Vec<Matcher>> {
+    values
+        .iter()
+        .map(|value| {
+            if is(*value, "integerp")? {
+                Ok(Matcher::Id(value.into_rust::<i64>()? as usize))
+            } else {
+                Ok(Matcher::Name(value.into_rust::<String>()?))
+            }
+        })
+        .collect()
+}
+
+/// Parse the Lisp RULE, see `create-smart-playlist' for the format.
+fn parse_rule(rule: EValue<'_>) -> EResult<Result<Rule, String>> {
+    let (head, args) = if is(rule, "consp")? {
+        let list = list_to_vec::<EValue<'_>>(rule)?;
+        (symbol_name(list[0])?, list[1..].to_vec())
+    } else if is(rule, "symbolp")? {
+        (symbol_name(rule)?, Vec::new())
+    } else {
+        return Ok(Err("A rule should be a symbol or a list!".to_string()));
+    };
+
+    let mut rules = Vec::new();
+    if ["and", "or", "not"].contains(&head.as_str()) {
+        for arg in args.iter() {
+            match parse_rule(*arg)? {
+                Ok(rule) => rules.push(rule),
+                Err(e) => return Ok(Err(e)),
+            }
+        }
+    }
+    let rule = match head.as_str() {
+        "and" => Rule::All(rules),
+        "or" => Rule::Any(rules),
+        "not" if rules.len() == 1 => Rule::Not(Box::new(rules.remove(0))),
+        "not" => return Ok(Err("not needs exactly one rule!".to_string())),
+        "artist" => Rule::Artist(parse_matchers(&args)?),
+        "followed-artist" => Rule::FollowedArtist,
+        "album" => Rule::Album(parse_matchers(&args)?),
+        "duration" => Rule::Duration(
+            optional_number(args.first())?,
+            optional_number(args.get(1))?,
+        ),
+        "liked" => Rule::Liked,
+        "play-count" => Rule::PlayCount(
+            optional_number(args.first())?.map(|count| count as usize),
+            optional_number(args.get(1))?.map(|count| count as usize),
+        ),
+        "playlist" => Rule::Playlist(
+            args.iter()
+                .map(|pid| Ok(pid.into_rust::<i64>()? as usize))
+                .collect::<EResult<Vec<_>>>()?,
+        ),
+        "added-within" => match optional_number(args.first())? {
+            Some(days) => Rule::AddedWithin(days),
+            None => return Ok(Err("added-within needs the number of days!".to_string())),
+        },
+        _ => return Ok(Err(format!("Unknown rule {}!", head))),
+    };
+    Ok(Ok(rule))
+}
+
+fn parse_sources(sources: EValue<'_>) -> EResult<Result<Vec<Source>, String>> {
+    let mut result = Vec::new();
+    for source in list_to_vec::<EValue<'_>>(sources)? {
+        if is(source, "integerp")? {
+            result.push(Source::Playlist(source.into_rust::<i64>()? as usize));
+            continue;
+        }
+        match symbol_name(source)?.as_str() {
+            "liked" => result.push(Source::Liked),
+            "played" => result.push(Source::Played),
+            name => return Ok(Err(format!("Unknown source {}!", name))),
+        }
+    }
+    Ok(Ok(result))
+}
+
+/// The liked songs playlist of the user UID.
+async fn liked_playlist(uid: usize) -> Result<usize, String> {
+    let pages: Vec<Page<Playlist>> = get_api()
+        .user_playlist_pages(uid, 100)
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    let playlists = pages
+        .into_iter()
+        .flat_map(|page| page.items)
+        .collect::<Vec<_>>();
+    playlists
+        .iter()
+        .find(|playlist| playlist.special_type == LIKED_PLAYLIST_TYPE)
+        .or_else(|| playlists.first())
+        .map(|playlist| playlist.id)
+        .ok_or_else(|| "The liked songs can not be found!".to_string())
+}
+
+async fn playlist_candidates(pid: usize) -> Result<Vec<Candidate>, String> {
+    let playlist = get_api()
+        .playlist_with_tracks(pid)
+        .await
+        .map_err(|_| format!("The playlist {} can not found!", pid))?;
+    let added = playlist
+        .track_ids
+        .iter()
+        .map(|track| (track.id, track.at))
+        .collect::<HashMap<_, _>>();
+    Ok(playlist
+        .tracks
+        .into_iter()
+        .map(|song| Candidate {
+            added: added.get(&song.id).copied().filter(|at| *at > 0),
+            song,
+        })
+        .collect())
+}
+
+/// Collect the songs from the sources of PLAYLIST and the facts its rule needs.
+async fn collect(playlist: &SmartPlaylist) -> Result<(Vec<Candidate>, Facts), String> {
+    let api = get_api();
+    let rule = &playlist.rule;
+    let needs_plays = playlist.order == Order::Plays
+        || playlist.sources.contains(&Source::Played)
+        || rule.uses(|rule| matches!(rule, Rule::PlayCount(..)));
+    let needs_user = needs_plays
+        || playlist.sources.contains(&Source::Liked)
+        || rule.uses(|r| *r == Rule::Liked);
+    let uid = if needs_user {
+        current_user().await?.user_id
+    } else {
+        0
+    };
+
+    let mut facts = Facts {
+        now: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    let records = if needs_plays {
+        api.user_play_records(uid, true)
+            .await
+            .map_err(|e| format!("Failed to get the play records: {}", e))?
+    } else {
+        Vec::new()
+    };
+    facts.play_counts = records
+        .iter()
+        .map(|record| (record.song.id, record.play_count))
+        .collect();
+    if rule.uses(|rule| *rule == Rule::Liked) {
+        facts.liked = api
+            .liked_song_ids(uid)
+            .await
+            .map_err(|e| format!("Failed to get the liked songs: {}", e))?
+            .into_iter()
+            .collect();
+    }
+    if rule.uses(|rule| *rule == Rule::FollowedArtist) {
+        let pages: Vec<Page<_>> = api
+            .artist_sublist_pages(100)
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to get the followed artists: {}", e))?;
+        facts.followed_artists = pages
+            .into_iter()
+            .flat_map(|page| page.items)
+            .map(|artist| artist.id)
+            .collect();
+    }
+    for pid in rule.playlists() {
+        let ids = playlist_ids(pid).await?;
+        facts.playlists.insert(pid, ids.into_iter().collect());
+    }
+
+    let mut candidates = Vec::new();
+    for source in playlist.sources.iter() {
+        match source {
+            Source::Playlist(pid) => candidates.extend(playlist_candidates(*pid).await?),
+            Source::Liked => {
+                candidates.extend(playlist_candidates(liked_playlist(uid).await?).await?)
+            }
+            Source::Played => candidates.extend(records.iter().map(|record| Candidate {
+                song: record.song.to_owned(),
+                added: None,
+            })),
+        }
+    }
+    Ok((candidates, facts))
+}
+
+/// Evaluate PLAYLIST and update its NetEase playlist.
+async fn materialize(playlist: &SmartPlaylist) -> Result<SyncPlan, String> {
+    let (candidates, facts) = collect(playlist).await?;
+    let target = playlist.evaluate(&candidates, &facts);
+    // uncached, the playlist may have been created or refreshed moments ago
+    let current = playlist_ids(playlist.pid).await?;
+    let plan = SyncPlan::new(&current, &target);
+    apply_plan(playlist.pid, &plan).await?;
+    Ok(plan)
+}
+
+/// Create a playlist named NAME whose songs are the ones from SOURCES matching RULE,
+/// the definition is saved in FILE for `refresh-smart-playlist'.
+/// SOURCES is a list of playlist ids and the symbols liked, the liked songs, and played,
+/// the songs played most.
+/// RULE is a symbol or a list:
+/// (and RULE...), (or RULE...), (not RULE): combine the rules.
+/// (artist ARTIST...), (album ALBUM...): by one of the artists or albums, which are
+/// ids or names.
+/// followed-artist: by an artist the user follows.
+/// (duration MIN MAX): MIN to MAX seconds long, either of them can be nil.
+/// liked: liked by the user.
+/// (play-count MIN MAX): played MIN to MAX times, from the play records.
+/// (playlist PID...): in one of the playlists.
+/// (added-within DAYS): added to its source playlist in the last DAYS days.
+/// ORDER is nil to keep the order of the sources, added for the latest added first or
+/// plays for the most played first. LIMIT is the max number of songs or nil.
+/// Return (pid count), the id of the playlist and its number of songs.
+#[defun]
+#[tokio::main]
+pub async fn create_smart_playlist<'a>(
+    env: &'a Env,
+    file: String,
+    name: String,
+    sources: EValue<'a>,
+    rule: EValue<'a>,
+    order: EValue<'a>,
+    limit: Option<i64>,
+) -> EResult<EValue<'a>> {
+    let rule = match parse_rule(rule)? {
+        Ok(rule) => rule,
+        Err(e) => return error(env, e),
+    };
+    let sources = match parse_sources(sources)? {
+        Ok(sources) => sources,
+        Err(e) => return error(env, e),
+    };
+    let order = if order.is_not_nil() {
+        match symbol_name(order)?.as_str() {
+            "added" => Order::Added,
+            "plays" => Order::Plays,
+            _ => return error(env, "Unknown order!".to_string()),
+        }
+    } else {
+        Order::Source
+    };
+    let mut playlists = match load_smart_playlists(Path::new(&file)) {
+        Ok(playlists) => playlists,
+        Err(e) => return error(env, format!("Failed to read {}: {}", file, e)),
+    };
+
+    let pid = match create_remote_playlist(&name, false).await {
+        Ok(pid) => pid,
+        Err(e) => return error(env, e),
+    };
+    let playlist = SmartPlaylist {
+        pid,
+        name,
+        sources,
+        rule,
+        order,
+        limit: limit.map(|limit| limit.max(0) as usize),
+    };
+    playlists.push(playlist.clone());
+    if let Err(e) = save_smart_playlists(Path::new(&file), &playlists) {
+        return error(env, format!("Failed to write {}: {}", file, e));
+    }
+    match materialize(&playlist).await {
+        Ok(plan) => env.list((pid as i64, plan.add.len() as i64)),
+        Err(e) => error(env, format!("Failed to fill the smart playlist: {}", e)),
+    }
+}
+
+/// Run the rule of the smart playlist whose id is PID in FILE again and update the songs.
+/// Return (added removed reordered) like `playlist-diff'.
+#[defun]
+#[tokio::main]
+pub async fn refresh_smart_playlist(env: &Env, file: String, pid: i64) -> EResult<EValue<'_>> {
+    let playlist = match load_smart_playlists(Path::new(&file)) {
+        Ok(playlists) => playlists
+            .into_iter()
+            .find(|playlist| playlist.pid == pid as usize),
+        Err(e) => return error(env, format!("Failed to read {}: {}", file, e)),
+    };
+    let playlist = match playlist {
+        Some(playlist) => playlist,
+        None => return error(env, format!("{} isn't a smart playlist!", pid)),
+    };
+    match materialize(&playlist).await {
+        Ok(plan) => plan_to_lisp(env, &plan),
+        Err(e) => error(env, format!("Failed to refresh the smart playlist: {}", e)),
+    }
+}
+
+/// Get the smart playlists in FILE as ((pid name) ...).
+#[defun]
+pub fn smart_playlists(env: &Env, file: String) -> EResult<EValue<'_>> {
+    let playlists = match load_smart_playlists(Path::new(&file)) {
+        Ok(playlists) => playlists,
+        Err(e) => return error(env, format!("Failed to read {}: {}", file, e)),
+    };
+    let playlists = playlists
+        .iter()
+        .map(|playlist| env.list((playlist.pid as i64, playlist.name.to_owned())))
+        .collect::<EResult<Vec<_>>>()?;
+    env.list(&playlists)
+}
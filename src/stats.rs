@@ -0,0 +1,224 @@
+// The listening history and its statistics.
+
+// Copyright (C) 2022 SpringHan
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::date::DAY_MILLISECONDS;
+use ncmapi::stats::{day, format_date, statistics, streaks, Play, Report};
+
+use crate::api::{current_user, error, first_artist_name, get_api};
+use crate::library::with_library;
+
+/// The plays are logged into the library, so it has to be opened.
+const NO_LIBRARY: &str = "Open the library with `library-open' first!";
+
+/// The number of the top songs, artists and albums in the reports.
+const REPORT_LIMIT: usize = 10;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// All the logged plays, an error message if the library isn't opened.
+fn logged_plays() -> Result<Vec<Play>, String> {
+    with_library(|library| library.plays(0).map_err(|e| e.to_string()))
+        .unwrap_or_else(|| Err(NO_LIBRARY.to_string()))
+}
+
+/// Log a play of the song whose id is SID into the library, LISTENED is how many
+/// seconds of it were played. The library should have been opened by `library-open'.
+#[defun]
+#[tokio::main]
+pub async fn log_play(env: &Env, sid: i64, listened: i64) -> EResult<EValue<'_>> {
+    let sid = sid as usize;
+    let song = match with_library(|library| library.song(sid).ok().flatten()) {
+        None => return error(env, NO_LIBRARY.to_string()),
+        Some(Some(song)) => song,
+        Some(None) => match get_api().song_detail(&[sid]).await {
+            Ok(detail) if !detail.songs.is_empty() => detail.songs[0].to_owned(),
+            _ => return error(env, "The sid can not found!".to_string()),
+        },
+    };
+    let listened = listened.max(0) as u64 * 1000;
+    match with_library(|library| library.add_play(&song, now(), listened)) {
+        Some(Ok(_)) => true.into_lisp(env),
+        Some(Err(e)) => error(env, format!("Failed to log the play: {}", e)),
+        None => error(env, NO_LIBRARY.to_string()),
+    }
+}
+
+/// Get the statistics of the plays logged by `log-play' in PERIOD, one of the symbols
+/// week, month and year for the last 7, 30 and 365 days, or nil for all the plays.
+/// LIMIT is the number of the top songs, artists and albums. OFFSET is the seconds of
+/// the time zone from UTC, like the car of `current-time-zone', to split the days.
+/// Return (plays seconds days songs artists albums streak longest-streak), days is the
+/// number of days with plays, songs is ((id name artist plays seconds) ...), artists
+/// and albums are ((id name plays seconds) ...), the streaks are the days in a row
+/// with plays, streak is the one ending today or yesterday.
+#[defun]
+pub fn listening_stats<'a>(
+    env: &'a Env,
+    period: EValue<'a>,
+    limit: i64,
+    offset: i64,
+) -> EResult<EValue<'a>> {
+    let days = if period.is_not_nil() {
+        match env
+            .call("symbol-name", [period])?
+            .into_rust::<String>()?
+            .as_str()
+        {
+            "week" => Some(7),
+            "month" => Some(30),
+            "year" => Some(365),
+            _ => return error(env, "Unknown period!".to_string()),
+        }
+    } else {
+        None
+    };
+    let plays = match logged_plays() {
+        Ok(plays) => plays,
+        Err(e) => return error(env, e),
+    };
+
+    let now = now();
+    let since = days.map_or(0, |days| now.saturating_sub(days * DAY_MILLISECONDS));
+    let period_plays = plays
+        .iter()
+        .filter(|play| play.played_at >= since)
+        .cloned()
+        .collect::<Vec<_>>();
+    let stats = statistics(&period_plays, limit.max(0) as usize, offset);
+    let streaks = streaks(&plays, now, offset);
+
+    let seconds = |listened: u64| (listened / 1000) as i64;
+    let songs = stats
+        .top_songs
+        .iter()
+        .map(|ranked| {
+            env.list((
+                ranked.item.id as i64,
+                ranked.item.name.to_owned(),
+                first_artist_name(&ranked.item.artists),
+                ranked.plays as i64,
+                seconds(ranked.listened),
+            ))
+        })
+        .collect::<EResult<Vec<_>>>()?;
+    let artists = stats
+        .top_artists
+        .iter()
+        .map(|ranked| {
+            env.list((
+                ranked.item.id as i64,
+                ranked.item.name.to_owned().unwrap_or_default(),
+                ranked.plays as i64,
+                seconds(ranked.listened),
+            ))
+        })
+        .collect::<EResult<Vec<_>>>()?;
+    let albums = stats
+        .top_albums
+        .iter()
+        .map(|ranked| {
+            env.list((
+                ranked.item.id as i64,
+                ranked.item.name.to_owned().unwrap_or_default(),
+                ranked.plays as i64,
+                seconds(ranked.listened),
+            ))
+        })
+        .collect::<EResult<Vec<_>>>()?;
+    env.list((
+        stats.plays as i64,
+        seconds(stats.listened),
+        stats.days as i64,
+        env.list(&songs)?,
+        env.list(&artists)?,
+        env.list(&albums)?,
+        streaks.current as i64,
+        streaks.longest as i64,
+    ))
+}
+
+/// Get the play records of the current user kept by NetEase, of the last week, or of
+/// all the time if ALL is non-nil.
+/// Return ((id name artist play-count score) ...), the most played first, score is the
+/// play count relative to the most played song, which scores 100.
+#[defun]
+#[tokio::main]
+pub async fn play_records<'a>(env: &'a Env, all: EValue<'a>) -> EResult<EValue<'a>> {
+    let user = match current_user().await {
+        Ok(user) => user,
+        Err(e) => return error(env, e),
+    };
+    let records = match get_api()
+        .user_play_records(user.user_id, all.is_not_nil())
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => return error(env, format!("Failed to get the play records: {}", e)),
+    };
+    let records = records
+        .iter()
+        .map(|record| {
+            env.list((
+                record.song.id as i64,
+                record.song.name.to_owned(),
+                first_artist_name(&record.song.artists),
+                record.play_count as i64,
+                record.score as i64,
+            ))
+        })
+        .collect::<EResult<Vec<_>>>()?;
+    env.list(&records)
+}
+
+/// Write the report of the plays in the last 7 days to FILE, FORMAT is org or json.
+/// OFFSET is the seconds of the time zone from UTC, like `listening-stats'.
+#[defun]
+pub fn weekly_report<'a>(
+    env: &'a Env,
+    file: String,
+    format: EValue<'a>,
+    offset: i64,
+) -> EResult<EValue<'a>> {
+    let format = env.call("symbol-name", [format])?.into_rust::<String>()?;
+    let plays = match logged_plays() {
+        Ok(plays) => plays,
+        Err(e) => return error(env, e),
+    };
+
+    let now = now();
+    let today = day(now, offset);
+    let week = plays
+        .iter()
+        .filter(|play| day(play.played_at, offset) > today - 7)
+        .cloned()
+        .collect::<Vec<_>>();
+    let report = Report {
+        from: format_date(today - 6),
+        to: format_date(today),
+        stats: statistics(&week, REPORT_LIMIT, offset),
+        streaks: streaks(&plays, now, offset),
+    };
+    let content = match format.as_str() {
+        "org" => report.to_org(),
+        "json" => match serde_json::to_string_pretty(&report) {
+            Ok(json) => json,
+            Err(e) => return error(env, format!("Failed to write the report: {}", e)),
+        },
+        _ => return error(env, "Unknown report format!".to_string()),
+    };
+    match std::fs::write(&file, content) {
+        Ok(_) => true.into_lisp(env),
+        Err(e) => error(env, format!("Failed to write {}: {}", file, e)),
+    }
+}
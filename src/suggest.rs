@@ -0,0 +1,279 @@
+// Search suggestions, hot searches and default keyword.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::types::{
+    SearchDefaultResp, SearchHotDetailResp, SearchMultimatchResp, SearchSuggestResp,
+};
+
+use crate::api::{error, first_artist_name, get_api};
+
+/// A query is requested once no other query follows it within this interval.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How long the suggestions of a query are kept.
+const CACHE_EXPIRATION: Duration = Duration::from_secs(5 * 60);
+
+/// A completion candidate.
+#[derive(Clone, Debug)]
+struct Suggestion {
+    candidate: String,
+    kind: &'static str,
+    id: i64,
+}
+
+struct SuggestionCache {
+    entries: HashMap<String, (Instant, Vec<Suggestion>)>,
+    /// The latest query not in the cache, waiting to be requested.
+    pending: Option<String>,
+    /// The query whose request failed with the error, reported to the next call for it.
+    failure: Option<(String, String)>,
+}
+
+static CACHE: Mutex<Option<SuggestionCache>> = Mutex::new(None);
+
+/// Apply F to the suggestion cache.
+fn with_cache<T>(f: impl FnOnce(&mut SuggestionCache) -> T) -> T {
+    f(CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(SuggestionCache::new))
+}
+
+impl SuggestionCache {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            pending: None,
+            failure: None,
+        }
+    }
+
+    /// Get the unexpired suggestions of QUERY.
+    fn get(&self, query: &str) -> Option<Vec<Suggestion>> {
+        self.entries
+            .get(query)
+            .filter(|(time, _)| time.elapsed() < CACHE_EXPIRATION)
+            .map(|(_, suggestions)| suggestions.to_owned())
+    }
+
+    fn insert(&mut self, query: String, suggestions: Vec<Suggestion>) {
+        self.entries
+            .retain(|_, (time, _)| time.elapsed() < CACHE_EXPIRATION);
+        self.entries.insert(query, (Instant::now(), suggestions));
+    }
+}
+
+/// Request the suggestions of QUERY, in the order given by the server.
+#[tokio::main]
+async fn request_suggestions(query: &str) -> Result<Vec<Suggestion>, String> {
+    let api = get_api();
+    let result = api
+        .search_suggest(query, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .deserialize::<SearchSuggestResp>()
+        .map_err(|e| e.to_string())?
+        .result
+        .unwrap_or_default();
+
+    let mut order = result.order.to_owned();
+    if order.is_empty() {
+        order = ["songs", "artists", "albums", "playlists"]
+            .iter()
+            .map(|section| section.to_string())
+            .collect();
+    }
+
+    let mut suggestions = Vec::<Suggestion>::new();
+    for section in order.iter() {
+        match section.as_str() {
+            "songs" => suggestions.extend(result.songs.iter().map(|song| Suggestion {
+                candidate: format!("{} - {}", song.name, first_artist_name(&song.artists)),
+                kind: "song",
+                id: song.id as i64,
+            })),
+            "artists" => suggestions.extend(result.artists.iter().map(|artist| Suggestion {
+                candidate: artist.name.to_owned().unwrap_or_default(),
+                kind: "artist",
+                id: artist.id as i64,
+            })),
+            "albums" => suggestions.extend(result.albums.iter().map(|album| Suggestion {
+                candidate: format!(
+                    "{} - {}",
+                    album.name.to_owned().unwrap_or_default(),
+                    album
+                        .artist
+                        .as_ref()
+                        .and_then(|artist| artist.name.to_owned())
+                        .unwrap_or_default()
+                ),
+                kind: "album",
+                id: album.id as i64,
+            })),
+            "playlists" => suggestions.extend(result.playlists.iter().map(|playlist| Suggestion {
+                candidate: playlist.name.to_owned(),
+                kind: "playlist",
+                id: playlist.id as i64,
+            })),
+            _ => (),
+        }
+    }
+    Ok(suggestions)
+}
+
+/// Request QUERY after the debounce interval unless another query has come since,
+/// the failures aren't cached.
+fn request_later(query: String) {
+    std::thread::sleep(DEBOUNCE);
+    if with_cache(|cache| cache.pending.as_ref() != Some(&query)) {
+        return;
+    }
+
+    let result = request_suggestions(&query);
+    with_cache(|cache| {
+        if cache.pending.as_ref() == Some(&query) {
+            cache.pending = None;
+        }
+        match result {
+            Ok(suggestions) => cache.insert(query, suggestions),
+            Err(e) => cache.failure = Some((query, e)),
+        }
+    })
+}
+
+/// Return the completion candidates for the partial QUERY.
+/// Each candidate is (candidate kind id), kind is one of the symbols song, artist, album
+/// and playlist.
+/// The suggestions are cached. A query not in the cache is requested in the background
+/// once no other query follows it within 300ms, and the symbol pending is returned, ask
+/// again later, like with an idle timer, to get its suggestions.
+#[defun]
+pub fn search_suggestions(env: &Env, query: String) -> EResult<EValue<'_>> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return ().into_lisp(env);
+    }
+
+    let suggestions = with_cache(|cache| {
+        if let Some(suggestions) = cache.get(&query) {
+            return Ok(Some(suggestions));
+        }
+        if let Some((_, e)) = cache.failure.take_if(|(failed, _)| *failed == query) {
+            return Err(e);
+        }
+        if cache.pending.as_ref() != Some(&query) {
+            cache.pending = Some(query.to_owned());
+            let query = query.to_owned();
+            std::thread::spawn(move || request_later(query));
+        }
+        Ok(None)
+    });
+    let suggestions = match suggestions {
+        Ok(Some(suggestions)) => suggestions,
+        Ok(None) => return env.intern("pending"),
+        Err(e) => return error(env, format!("Failed to get the suggestions: {}", e)),
+    };
+
+    let mut result = Vec::<EValue<'_>>::new();
+    for suggestion in suggestions.iter() {
+        result.push(env.list((
+            suggestion.candidate.to_owned(),
+            env.intern(suggestion.kind)?,
+            suggestion.id,
+        ))?);
+    }
+    env.list(&result)
+}
+
+/// Get the best matches of KEYWORD, return ((kind id name) ...),
+/// kind is the symbol artist or album.
+#[defun]
+#[tokio::main]
+pub async fn search_multimatch(env: &Env, keyword: String) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = api
+        .search_multimatch(&keyword)
+        .await
+        .unwrap()
+        .deserialize::<SearchMultimatchResp>()
+        .unwrap_or_default()
+        .result
+        .unwrap_or_default();
+
+    let mut matches = Vec::<EValue<'_>>::new();
+    for order in result.orders.iter() {
+        match order.as_str() {
+            "artist" => {
+                for artist in result.artist.iter() {
+                    matches.push(env.list((
+                        env.intern("artist")?,
+                        artist.id as i64,
+                        artist.name.to_owned().unwrap_or_default(),
+                    ))?);
+                }
+            }
+            "album" => {
+                for album in result.album.iter() {
+                    matches.push(env.list((
+                        env.intern("album")?,
+                        album.id as i64,
+                        album.name.to_owned().unwrap_or_default(),
+                    ))?);
+                }
+            }
+            _ => (),
+        }
+    }
+    env.list(&matches)
+}
+
+/// Get the hot searches, return ((keyword content score) ...).
+#[defun]
+#[tokio::main]
+pub async fn hot_searches(env: &Env) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = api
+        .search_hot_detail()
+        .await
+        .unwrap()
+        .deserialize::<SearchHotDetailResp>()
+        .unwrap_or_default();
+
+    let mut hots = Vec::<EValue<'_>>::new();
+    for hot in result.data.iter() {
+        hots.push(env.list((
+            hot.search_word.to_owned(),
+            hot.content.to_owned(),
+            hot.score as i64,
+        ))?);
+    }
+    env.list(&hots)
+}
+
+/// Get the default search keyword, return (shown-keyword real-keyword).
+/// If failed, return nil.
+#[defun]
+#[tokio::main]
+pub async fn default_search_keyword(env: &Env) -> EResult<EValue<'_>> {
+    let api = get_api();
+    let result = api
+        .search_default()
+        .await
+        .unwrap()
+        .deserialize::<SearchDefaultResp>()
+        .unwrap_or_default();
+
+    if result.code == 200 {
+        env.list((result.data.show_keyword, result.data.real_keyword))
+    } else {
+        ().into_lisp(env)
+    }
+}
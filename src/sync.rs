@@ -0,0 +1,260 @@
+// Sync playlists with the local playlist files.
+
+// Copyright (C) 2022 SpringHan
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::playlist_sync::{merge, parse_id_list, to_id_list, SyncPlan};
+use ncmapi::types::{PlaylistDetail, Song};
+
+use crate::api::{error, get_api, PlaylistInfo, SpecialJsonStructure};
+
+/// The number of songs added or removed in each request.
+const SYNC_BATCH_SIZE: usize = 100;
+
+/// The snapshot of the last synced songs of FILE, used by the three-way merge.
+fn snapshot_path(file: &str) -> PathBuf {
+    PathBuf::from(format!("{}.snapshot", file))
+}
+
+fn read_id_list(file: &str) -> Result<Vec<usize>, String> {
+    std::fs::read_to_string(file)
+        .map(|text| parse_id_list(&text))
+        .map_err(|e| format!("Failed to read {}: {}", file, e))
+}
+
+/// Get the playlist whose id is PID and the ids of its songs in order.
+async fn remote_playlist(pid: usize) -> Result<(PlaylistDetail, Vec<usize>), String> {
+    let api = get_api();
+    let playlist = api
+        .playlist_with_tracks(pid)
+        .await
+        .map_err(|_| "The pid can not found!".to_string())?;
+    let ids = playlist.track_ids.iter().map(|track| track.id).collect();
+    Ok((playlist, ids))
+}
+
+/// Write the songs whose ids are IDS to FILE and its snapshot.
+/// KNOWN are the songs already fetched, the others are requested for their names.
+async fn write_local(file: &str, name: &str, ids: &[usize], known: &[Song]) -> Result<(), String> {
+    let mut songs = known
+        .iter()
+        .map(|song| (song.id, song.to_owned()))
+        .collect::<HashMap<_, _>>();
+    let missing = ids
+        .iter()
+        .filter(|id| !songs.contains_key(id))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let api = get_api();
+        if let Ok(detail) = api.song_detail(&missing).await {
+            songs.extend(detail.songs.into_iter().map(|song| (song.id, song)));
+        }
+    }
+
+    let songs = ids
+        .iter()
+        .map(|id| {
+            songs.remove(id).unwrap_or_else(|| Song {
+                id: *id,
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+    std::fs::write(file, to_id_list(name, &songs))
+        .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+    write_snapshot(file, name, ids)
+}
+
+fn write_snapshot(file: &str, name: &str, ids: &[usize]) -> Result<(), String> {
+    let songs = ids
+        .iter()
+        .map(|id| Song {
+            id: *id,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    std::fs::write(snapshot_path(file), to_id_list(name, &songs))
+        .map_err(|e| format!("Failed to write the snapshot of {}: {}", file, e))
+}
+
+/// The song ids of the playlist PID in order, read past the response cache.
+pub(crate) async fn playlist_ids(pid: usize) -> Result<Vec<usize>, String> {
+    let api = get_api();
+    api.playlist_track_ids(pid).await.map_err(|e| e.to_string())
+}
+
+/// Create a playlist named NAME, return its id.
+pub(crate) async fn create_remote_playlist(name: &str, privacy: bool) -> Result<usize, String> {
+    let api = get_api();
+    let response = api
+        .create_playlist(name.to_owned(), privacy)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = PlaylistInfo::from_data(response.data());
+    if result.code != 200 {
+        return Err(format!("failed to create the playlist {}", name));
+    }
+    Ok(result.id as usize)
+}
+
+/// Apply PLAN to the playlist PID, the songs are removed first and then added in batches,
+/// and the order is updated last if needed.
+pub(crate) async fn apply_plan(pid: usize, plan: &SyncPlan) -> Result<(), String> {
+    let api = get_api();
+    let batches = plan
+        .remove
+        .chunks(SYNC_BATCH_SIZE)
+        .map(|batch| (0, batch))
+        .chain(plan.add_batches(SYNC_BATCH_SIZE).map(|batch| (1, batch)));
+    for (op, batch) in batches {
+        let result = api
+            .playlist_tracks(pid, op, batch.iter().map(|id| *id as i64).collect())
+            .await
+            .map_err(|e| e.to_string())?
+            .deserialize_to_implict();
+        if result.code != 200 {
+            return Err(format!(
+                "updating the songs failed with code {}",
+                result.code
+            ));
+        }
+    }
+
+    if let Some(ref order) = plan.order {
+        let result = api
+            .update_playlist_order(pid, order.to_owned())
+            .await
+            .map_err(|e| e.to_string())?
+            .deserialize_to_implict();
+        if result.code != 200 {
+            return Err(format!(
+                "updating the order failed with code {}",
+                result.code
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn ids_to_lisp<'a>(env: &'a Env, ids: &[usize]) -> EResult<EValue<'a>> {
+    let ids = ids
+        .iter()
+        .map(|id| (*id as i64).into_lisp(env))
+        .collect::<EResult<Vec<_>>>()?;
+    env.list(&ids)
+}
+
+/// Convert PLAN into (added removed reordered), added and removed are the song ids.
+pub(crate) fn plan_to_lisp<'a>(env: &'a Env, plan: &SyncPlan) -> EResult<EValue<'a>> {
+    env.list((
+        ids_to_lisp(env, &plan.add)?,
+        ids_to_lisp(env, &plan.remove)?,
+        plan.order.is_some(),
+    ))
+}
+
+/// Compare the local playlist FILE with the playlist whose id is PID.
+/// FILE has a song id on each line, everything after # is a comment.
+/// Return (added removed reordered), the changes pushing FILE would make to the playlist.
+#[defun]
+#[tokio::main]
+pub async fn playlist_diff(env: &Env, pid: i64, file: String) -> EResult<EValue<'_>> {
+    let local = match read_id_list(&file) {
+        Ok(local) => local,
+        Err(e) => return error(env, e),
+    };
+    match remote_playlist(pid as usize).await {
+        Ok((_, remote)) => plan_to_lisp(env, &SyncPlan::new(&remote, &local)),
+        Err(e) => error(env, e),
+    }
+}
+
+/// Make the playlist whose id is PID the same as the local playlist FILE,
+/// with the minimal number of requests.
+/// Return (added removed reordered) like `playlist-diff'.
+#[defun]
+#[tokio::main]
+pub async fn playlist_push(env: &Env, pid: i64, file: String) -> EResult<EValue<'_>> {
+    let local = match read_id_list(&file) {
+        Ok(local) => local,
+        Err(e) => return error(env, e),
+    };
+    let (playlist, remote) = match remote_playlist(pid as usize).await {
+        Ok(remote) => remote,
+        Err(e) => return error(env, e),
+    };
+
+    let plan = SyncPlan::new(&remote, &local);
+    if let Err(e) = apply_plan(pid as usize, &plan).await {
+        return error(env, format!("Failed to push {}: {}", file, e));
+    }
+    if let Err(e) = write_snapshot(&file, &playlist.name, &local) {
+        return error(env, e);
+    }
+    plan_to_lisp(env, &plan)
+}
+
+/// Write the songs of the playlist whose id is PID to the local playlist FILE.
+/// Return the number of the songs.
+#[defun]
+#[tokio::main]
+pub async fn playlist_pull(env: &Env, pid: i64, file: String) -> EResult<EValue<'_>> {
+    let (playlist, remote) = match remote_playlist(pid as usize).await {
+        Ok(remote) => remote,
+        Err(e) => return error(env, e),
+    };
+    match write_local(&file, &playlist.name, &remote, &playlist.tracks).await {
+        Ok(_) => (remote.len() as i64).into_lisp(env),
+        Err(e) => error(env, e),
+    }
+}
+
+/// Merge the changes of the local playlist FILE and the playlist whose id is PID since
+/// the last push, pull or merge, then update both of them.
+/// The songs removed on either side are removed, the songs added on either side are
+/// added. If both sides reordered the songs differently, the local order is used.
+/// Return (added removed reordered conflicts), the first three are the changes made to
+/// the playlist, conflicts are the ids of the songs ordered differently on both sides.
+#[defun]
+#[tokio::main]
+pub async fn playlist_merge(env: &Env, pid: i64, file: String) -> EResult<EValue<'_>> {
+    let snapshot = snapshot_path(&file);
+    let base = match std::fs::read_to_string(&snapshot) {
+        Ok(text) => parse_id_list(&text),
+        Err(_) => {
+            return error(
+                env,
+                format!("No snapshot of {}, push or pull it first!", file),
+            )
+        }
+    };
+    let local = match read_id_list(&file) {
+        Ok(local) => local,
+        Err(e) => return error(env, e),
+    };
+    let (playlist, remote) = match remote_playlist(pid as usize).await {
+        Ok(remote) => remote,
+        Err(e) => return error(env, e),
+    };
+
+    let result = merge(&base, &local, &remote);
+    let plan = SyncPlan::new(&remote, &result.merged);
+    if let Err(e) = apply_plan(pid as usize, &plan).await {
+        return error(env, format!("Failed to merge {}: {}", file, e));
+    }
+    if let Err(e) = write_local(&file, &playlist.name, &result.merged, &playlist.tracks).await {
+        return error(env, e);
+    }
+    env.list((
+        ids_to_lisp(env, &plan.add)?,
+        ids_to_lisp(env, &plan.remove)?,
+        plan.order.is_some(),
+        ids_to_lisp(env, &result.conflicts)?,
+    ))
+}
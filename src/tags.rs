@@ -0,0 +1,104 @@
+// Write metadata into the downloaded songs.
+
+// Copyright (C) 2022 SpringHan
+
+use std::path::Path;
+
+use emacs::Result as EResult;
+use emacs::Value as EValue;
+use emacs::{defun, Env, IntoLisp};
+use ncmapi::lyric::Lyrics;
+use ncmapi::tag::{read_song_id, read_tags, write_tags, Tags};
+use ncmapi::types::{LyricResp, Song};
+
+use crate::api::{error, get_api};
+use crate::library::with_library;
+
+/// Collect the tags of SONG with its cover and lyrics.
+/// The cover and the lyrics are left out if they can't be fetched.
+pub(crate) async fn song_tags(song: &Song) -> Tags {
+    let api = get_api();
+    let mut tags = Tags::from_song(song);
+
+    if !song.album.pic_url.is_empty() {
+        tags.cover = api.fetch(&song.album.pic_url).await.ok();
+    }
+    if let Some(result) = api
+        .lyric(song.id)
+        .await
+        .ok()
+        .and_then(|response| response.deserialize::<LyricResp>().ok())
+    {
+        let text = |lyric: Option<ncmapi::types::Lyric>| {
+            lyric.map(|lyric| lyric.lyric).unwrap_or_default()
+        };
+        let lyrics = Lyrics::parse(
+            &text(result.lrc),
+            &text(result.tlyric),
+            &text(result.romalrc),
+        );
+        if !lyrics.entries.is_empty() {
+            tags.lyrics = Some(lyrics);
+        }
+    }
+    tags
+}
+
+/// Tag the file at PATH with the info of SONG, and index it into the library.
+pub(crate) async fn tag_song_file(path: &Path, song: &Song) -> Result<(), String> {
+    let tags = song_tags(song).await;
+    write_tags(path, &tags).map_err(|e| e.to_string())?;
+
+    with_library(|library| {
+        library.add_songs(std::slice::from_ref(song))?;
+        library.add_file(path, &tags)
+    });
+    Ok(())
+}
+
+/// Write the title, artists, album, track number, year, cover, lyrics and the song id into
+/// the MP3 or FLAC FILE, MP3 files get ID3v2.4 tags with the synchronized lyrics, FLAC
+/// files get Vorbis comments and the cover picture.
+/// SID is the id of the song, nil means the id written by the last tagging of FILE, so the
+/// files downloaded before can be tagged again.
+/// Offline, the info of the song comes from the library or the tags already in FILE, and
+/// the cover and lyrics in FILE are kept.
+/// Return t if succeeded.
+#[defun]
+#[tokio::main]
+pub async fn tag_file<'a>(env: &'a Env, file: String, sid: Option<i64>) -> EResult<EValue<'a>> {
+    let path = Path::new(&file);
+    let sid = match sid {
+        Some(sid) => sid as usize,
+        None => match read_song_id(path) {
+            Ok(Some(sid)) => sid,
+            _ => return error(env, format!("No song id in {}!", file)),
+        },
+    };
+
+    let song = match get_api().song_detail(&[sid]).await {
+        Ok(detail) if !detail.songs.is_empty() => Some(detail.songs[0].to_owned()),
+        Ok(_) => return error(env, "The sid can not found!".to_string()),
+        // Offline, use the song in the library
+        Err(_) => with_library(|library| library.song(sid).ok().flatten()).flatten(),
+    };
+
+    let result = match song {
+        Some(song) => tag_song_file(path, &song).await,
+        // Neither online nor in the library, write the tags already in the file again
+        None => match read_tags(path) {
+            Ok(Some(tags)) if tags.song_id == sid => {
+                write_tags(path, &tags).map_err(|e| e.to_string())
+            }
+            Ok(_) => Err(format!(
+                "the song {} is neither online nor in the library",
+                sid
+            )),
+            Err(e) => Err(e.to_string()),
+        },
+    };
+    match result {
+        Ok(_) => true.into_lisp(env),
+        Err(e) => error(env, format!("Failed to tag {}: {}", file, e)),
+    }
+}